@@ -36,11 +36,19 @@ impl<'a> ClientBlockBuilder<'a> {
         log::debug!("[get_custom_inputs]");
 
         let mut inputs_data = Vec::new();
-        let current_time = self.client.get_time_checked().await?;
+        // An offline client can't check the time with a node
+        let current_time = if self.client.node_manager.offline {
+            self.client.get_time_unchecked()
+        } else {
+            self.client.get_time_checked().await?
+        };
 
         if let Some(inputs) = &self.inputs {
             for input in inputs {
-                let output_response = self.client.get_output(input.output_id()).await?;
+                let output_response = match self.supplied_input(input.output_id()) {
+                    Some(output_response) => output_response.clone(),
+                    None => self.client.get_output(input.output_id()).await?,
+                };
                 let output = Output::try_from(&output_response.output)?;
 
                 if !output_response.metadata.is_spent {
@@ -7,15 +7,16 @@ pub mod transaction;
 
 use std::{collections::HashSet, ops::Range};
 
+use bee_api_types::responses::OutputResponse;
 use bee_block::{
     address::{Address, Ed25519Address},
     input::{UtxoInput, INPUT_COUNT_MAX},
     output::{
         dto::OutputDto,
         unlock_condition::{AddressUnlockCondition, UnlockCondition},
-        AliasId, Output, OUTPUT_COUNT_RANGE,
+        AliasId, Output, OutputId, OUTPUT_COUNT_RANGE,
     },
-    payload::{Payload, TaggedDataPayload},
+    payload::{transaction::TransactionId, Payload, TaggedDataPayload},
     Block, BlockId,
 };
 use packable::{
@@ -23,7 +24,7 @@ use packable::{
     PackableExt,
 };
 
-pub use self::transaction::verify_semantic;
+pub use self::transaction::{verify_protocol_parameters, verify_semantic};
 use crate::{
     api::do_pow,
     block::{input::dto::UtxoInputDto, output::BasicOutputBuilder},
@@ -41,6 +42,7 @@ pub struct ClientBlockBuilder<'a> {
     account_index: u32,
     initial_address_index: u32,
     inputs: Option<Vec<UtxoInput>>,
+    input_data: Vec<OutputResponse>,
     input_range: Range<u32>,
     outputs: Vec<Output>,
     custom_remainder_address: Option<Address>,
@@ -103,6 +105,7 @@ impl<'a> ClientBlockBuilder<'a> {
             account_index: 0,
             initial_address_index: 0,
             inputs: None,
+            input_data: Vec::new(),
             input_range: 0..100,
             outputs: Vec::new(),
             custom_remainder_address: None,
@@ -159,6 +162,42 @@ impl<'a> ClientBlockBuilder<'a> {
         Ok(self)
     }
 
+    /// Set custom inputs together with their outputs and metadata, so they don't have to be requested from a node.
+    /// With the protocol parameters set, a transaction with these inputs can be prepared by an offline client.
+    pub fn with_input_data(mut self, outputs: Vec<OutputResponse>) -> Result<Self> {
+        for output in outputs {
+            let transaction_id = output.metadata.transaction_id.parse::<TransactionId>()?;
+            self = self.with_input(UtxoInput::new(transaction_id, output.metadata.output_index)?)?;
+            self.input_data.push(output);
+        }
+        Ok(self)
+    }
+
+    // Returns the supplied output of the input, if there is one
+    fn supplied_input(&self, output_id: &OutputId) -> Option<&OutputResponse> {
+        self.input_data.iter().find(|output| {
+            output.metadata.output_index == output_id.index()
+                && output.metadata.transaction_id.parse::<TransactionId>().ok().as_ref()
+                    == Some(output_id.transaction_id())
+        })
+    }
+
+    // Returns the supplied input with the alias, if there is one
+    fn supplied_alias_input(&self, alias_id: &AliasId) -> Result<Option<&OutputResponse>> {
+        for output_response in &self.input_data {
+            if let Output::Alias(alias) = Output::try_from(&output_response.output)? {
+                let output_id = OutputId::new(
+                    output_response.metadata.transaction_id.parse()?,
+                    output_response.metadata.output_index,
+                )?;
+                if alias.alias_id().or_from_output_id(output_id) == *alias_id {
+                    return Ok(Some(output_response));
+                }
+            }
+        }
+        Ok(None)
+    }
+
     /// Set a custom range in which to search for addresses for custom provided inputs. Default: 0..100
     pub fn with_input_range(mut self, range: Range<u32>) -> Self {
         self.input_range = range;
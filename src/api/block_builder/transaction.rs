@@ -12,6 +12,7 @@ use bee_block::{
         transaction::{RegularTransactionEssence, TransactionEssence, TransactionPayload},
         Payload, TaggedDataPayload,
     },
+    protocol::ProtocolParameters,
     semantic::{semantic_validation, ConflictReason, ValidationContext},
 };
 
@@ -19,6 +20,7 @@ use crate::{
     api::{types::PreparedTransactionData, ClientBlockBuilder},
     block::output::AliasId,
     secret::{types::InputSigningData, SecretManageExt},
+    utils::hash_network,
     Error, Result,
 };
 
@@ -27,6 +29,12 @@ impl<'a> ClientBlockBuilder<'a> {
     pub async fn prepare_transaction(&self) -> Result<PreparedTransactionData> {
         log::debug!("[prepare_transaction]");
         let rent_structure = self.client.get_rent_structure().await?;
+        // The token supply is only checked if it's known, an offline client may not have it
+        match self.client.get_token_supply().await {
+            Ok(token_supply) => verify_token_supply(&self.outputs, token_supply)?,
+            Err(Error::OfflineMode(_) | Error::MissingParameter(_)) => {}
+            Err(e) => return Err(e),
+        }
 
         let mut governance_transition: Option<HashSet<AliasId>> = None;
         for output in &self.outputs {
@@ -36,8 +44,13 @@ impl<'a> ClientBlockBuilder<'a> {
                 if x.state_index() > 0 {
                     // Check if the transaction is a governance_transition, by checking if the new index is the same as
                     // the previous index
-                    let output_id = self.client.alias_output_id(*x.alias_id()).await?;
-                    let output_response = self.client.get_output(&output_id).await?;
+                    let output_response = match self.supplied_alias_input(x.alias_id())? {
+                        Some(output_response) => output_response.clone(),
+                        None => {
+                            let output_id = self.client.alias_output_id(*x.alias_id()).await?;
+                            self.client.get_output(&output_id).await?
+                        }
+                    };
                     if let OutputDto::Alias(output) = output_response.output {
                         // A governance transition is identified by an unchanged State Index in next state.
                         if x.state_index() == output.state_index {
@@ -95,12 +108,26 @@ impl<'a> ClientBlockBuilder<'a> {
     pub async fn sign_transaction(&self, prepared_transaction_data: PreparedTransactionData) -> Result<Payload> {
         log::debug!("[sign_transaction] {:?}", prepared_transaction_data);
         let secret_manager = self.secret_manager.ok_or(Error::MissingParameter("secret manager"))?;
+        // Prepared transaction data can come from another machine, so it's checked against our protocol parameters
+        // before anything is signed. An offline client without protocol parameters signs it as it is.
+        match self.client.get_protocol_parameters().await {
+            Ok(protocol_parameters) => {
+                verify_protocol_parameters(&prepared_transaction_data.essence, &protocol_parameters)?
+            }
+            Err(Error::OfflineMode(_) | Error::MissingParameter(_)) => {}
+            Err(e) => return Err(e),
+        }
         let unlocks = secret_manager
             .sign_transaction_essence(&prepared_transaction_data)
             .await?;
         let tx_payload = TransactionPayload::new(prepared_transaction_data.essence.clone(), unlocks)?;
 
-        let current_time = self.client.get_time_checked().await?;
+        // An offline client can't check the time with a node
+        let current_time = if self.client.node_manager.offline {
+            self.client.get_time_unchecked()
+        } else {
+            self.client.get_time_checked().await?
+        };
 
         let conflict = verify_semantic(&prepared_transaction_data.inputs_data, &tx_payload, current_time)?;
 
@@ -113,6 +140,34 @@ impl<'a> ClientBlockBuilder<'a> {
     }
 }
 
+/// Verifies that a transaction essence is for the network of the protocol parameters and that its outputs don't exceed
+/// the token supply. [`verify_semantic()`] only checks the transaction against its inputs.
+pub fn verify_protocol_parameters(
+    essence: &TransactionEssence,
+    protocol_parameters: &ProtocolParameters,
+) -> Result<()> {
+    let TransactionEssence::Regular(essence) = essence;
+    let expected = hash_network(protocol_parameters.network_name())?;
+    if essence.network_id() != expected {
+        return Err(Error::NetworkIdMismatch {
+            expected,
+            found: essence.network_id(),
+        });
+    }
+    verify_token_supply(essence.outputs(), protocol_parameters.token_supply())
+}
+
+fn verify_token_supply<'a>(outputs: impl IntoIterator<Item = &'a Output>, token_supply: u64) -> Result<()> {
+    // Saturating, an overflow exceeds the token supply anyway
+    let amount = outputs
+        .into_iter()
+        .fold(0u64, |amount, output| amount.saturating_add(output.amount()));
+    if amount > token_supply {
+        return Err(Error::TokenSupplyExceeded { amount, token_supply });
+    }
+    Ok(())
+}
+
 // TODO @thibault-martinez: this is very cumbersome with the current state, will refactor.
 /// Verifies the semantic of a prepared transaction.
 pub fn verify_semantic(
@@ -0,0 +1,333 @@
+// Copyright 2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Proofs that a milestone referenced a block, verifiable without a node
+
+use std::str::FromStr;
+
+use bee_block::{
+    payload::milestone::{MerkleRoot, MilestonePayload},
+    Block, BlockDto, BlockId,
+};
+use crypto::hashes::{blake2b::Blake2b256, Digest};
+use packable::PackableExt;
+
+use crate::{
+    api::{milestone_verifier::merkle_hash, MilestoneVerificationError, MilestoneVerifier},
+    node_api::poi::responses::{InclusionProofDto, MerkleAuditPathDto},
+    Client, Error, Result,
+};
+
+// Version of the binary encoding of inclusion proofs
+const INCLUSION_PROOF_VERSION: u8 = 1;
+// A path deeper than this would need more leaves than a milestone can reference
+const MAX_AUDIT_PATH_DEPTH: usize = 64;
+const NODE_TAG: u8 = 0;
+const HASH_TAG: u8 = 1;
+const VALUE_TAG: u8 = 2;
+
+/// The path from a block ID to the inclusion merkle root of a milestone, as described in TIP-0004. Subtrees without the
+/// block are reduced to their hash.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MerkleAuditPath {
+    /// An inner node of the merkle tree
+    Node(Box<MerkleAuditPath>, Box<MerkleAuditPath>),
+    /// The hash of a subtree
+    Hash([u8; 32]),
+    /// The proven block ID
+    Value(BlockId),
+}
+
+impl MerkleAuditPath {
+    /// Creates the audit path of the block ID at the position of the block IDs in white flag order, `None` if the
+    /// position is out of range.
+    pub fn new(block_ids: &[BlockId], position: usize) -> Option<Self> {
+        match block_ids {
+            [] => None,
+            [block_id] => (position == 0).then_some(Self::Value(*block_id)),
+            _ => {
+                let split = block_ids.len().next_power_of_two() / 2;
+                let (left, right) = block_ids.split_at(split);
+                Some(if position < split {
+                    Self::Node(
+                        Box::new(Self::new(left, position)?),
+                        Box::new(Self::Hash(merkle_hash(right))),
+                    )
+                } else {
+                    Self::Node(
+                        Box::new(Self::Hash(merkle_hash(left))),
+                        Box::new(Self::new(right, position - split)?),
+                    )
+                })
+            }
+        }
+    }
+
+    /// Computes the merkle root the path leads to.
+    pub fn root(&self) -> MerkleRoot {
+        MerkleRoot::new(self.hash())
+    }
+
+    /// Whether the path proves the block ID.
+    pub fn contains(&self, block_id: &BlockId) -> bool {
+        match self {
+            Self::Node(left, right) => left.contains(block_id) || right.contains(block_id),
+            Self::Hash(_) => false,
+            Self::Value(value) => value == block_id,
+        }
+    }
+
+    fn hash(&self) -> [u8; 32] {
+        match self {
+            Self::Node(left, right) => {
+                let mut hasher = Blake2b256::new();
+                hasher.update([1u8]);
+                hasher.update(left.hash());
+                hasher.update(right.hash());
+                hasher.finalize().into()
+            }
+            Self::Hash(hash) => *hash,
+            Self::Value(block_id) => {
+                let mut hasher = Blake2b256::new();
+                hasher.update([0u8]);
+                hasher.update(block_id.as_ref());
+                hasher.finalize().into()
+            }
+        }
+    }
+
+    fn pack(&self, bytes: &mut Vec<u8>) {
+        match self {
+            Self::Node(left, right) => {
+                bytes.push(NODE_TAG);
+                left.pack(bytes);
+                right.pack(bytes);
+            }
+            Self::Hash(hash) => {
+                bytes.push(HASH_TAG);
+                bytes.extend_from_slice(hash);
+            }
+            Self::Value(block_id) => {
+                bytes.push(VALUE_TAG);
+                bytes.extend_from_slice(block_id.as_ref());
+            }
+        }
+    }
+
+    fn unpack(bytes: &mut &[u8], depth: usize) -> Result<Self> {
+        if depth > MAX_AUDIT_PATH_DEPTH {
+            return Err(Error::InclusionProofError("the audit path is too deep".to_string()));
+        }
+        match take::<1>(bytes)? {
+            [NODE_TAG] => Ok(Self::Node(
+                Box::new(Self::unpack(bytes, depth + 1)?),
+                Box::new(Self::unpack(bytes, depth + 1)?),
+            )),
+            [HASH_TAG] => Ok(Self::Hash(take(bytes)?)),
+            [VALUE_TAG] => Ok(Self::Value(BlockId::new(take(bytes)?))),
+            [tag] => Err(Error::InclusionProofError(format!("invalid audit path tag {tag}"))),
+        }
+    }
+}
+
+/// A block, the milestone that referenced it and the audit path from the block to the inclusion merkle root of the
+/// milestone. It serializes to the JSON of the proof-of-inclusion plugin and to a compact binary encoding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InclusionProof {
+    /// The milestone that referenced the block
+    pub milestone: MilestonePayload,
+    /// The proven block
+    pub block: Block,
+    /// The path from the block ID to the inclusion merkle root
+    pub audit_path: MerkleAuditPath,
+}
+
+impl InclusionProof {
+    /// Verifies the proof without a node: the milestone signatures with the verifier, and that the audit path of the
+    /// block leads to the inclusion merkle root.
+    pub fn verify(&self, verifier: &MilestoneVerifier) -> Result<()> {
+        verifier.verify_signatures(&self.milestone)?;
+
+        let block_id = self.block.id();
+        if !self.audit_path.contains(&block_id) {
+            return Err(MilestoneVerificationError::BlockNotInAuditPath {
+                block_id: block_id.to_string(),
+            }
+            .into());
+        }
+        let computed = self.audit_path.root();
+        if computed != *self.milestone.essence().inclusion_merkle_root() {
+            return Err(MilestoneVerificationError::InclusionMerkleRootMismatch {
+                index: *self.milestone.essence().index(),
+                expected: self.milestone.essence().inclusion_merkle_root().to_string(),
+                computed: computed.to_string(),
+            }
+            .into());
+        }
+
+        Ok(())
+    }
+
+    /// Serializes the proof to the JSON of the proof-of-inclusion plugin.
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&InclusionProofDto::from(self))?)
+    }
+
+    /// Deserializes a proof from the JSON of the proof-of-inclusion plugin.
+    pub fn from_json(json: &str) -> Result<Self> {
+        Self::try_from(&serde_json::from_str::<InclusionProofDto>(json)?)
+    }
+
+    /// Encodes the proof as a version byte, the length prefixed milestone and block, and the audit path.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![INCLUSION_PROOF_VERSION];
+        for packed in [self.milestone.pack_to_vec(), self.block.pack_to_vec()] {
+            bytes.extend_from_slice(&(packed.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(&packed);
+        }
+        self.audit_path.pack(&mut bytes);
+        bytes
+    }
+
+    /// Decodes a proof encoded with [`InclusionProof::to_bytes()`].
+    pub fn from_bytes(mut bytes: &[u8]) -> Result<Self> {
+        let bytes = &mut bytes;
+        match take::<1>(bytes)? {
+            [INCLUSION_PROOF_VERSION] => {}
+            [version] => {
+                return Err(Error::InclusionProofError(format!(
+                    "unsupported inclusion proof version {version}"
+                )));
+            }
+        }
+        let milestone = MilestonePayload::unpack_verified(take_prefixed(bytes)?, &())?;
+        let block = Block::unpack_verified(take_prefixed(bytes)?, &())?;
+        let audit_path = MerkleAuditPath::unpack(bytes, 0)?;
+        if !bytes.is_empty() {
+            return Err(Error::InclusionProofError(format!(
+                "{} trailing bytes after the audit path",
+                bytes.len()
+            )));
+        }
+
+        Ok(Self {
+            milestone,
+            block,
+            audit_path,
+        })
+    }
+}
+
+fn take<const N: usize>(bytes: &mut &[u8]) -> Result<[u8; N]> {
+    if bytes.len() < N {
+        return Err(Error::InclusionProofError(
+            "unexpected end of the inclusion proof".to_string(),
+        ));
+    }
+    let (taken, rest) = bytes.split_at(N);
+    *bytes = rest;
+    // The length was checked
+    Ok(taken.try_into().expect("slice with N bytes"))
+}
+
+fn take_prefixed<'a>(bytes: &mut &'a [u8]) -> Result<&'a [u8]> {
+    let len = u32::from_le_bytes(take(bytes)?) as usize;
+    if bytes.len() < len {
+        return Err(Error::InclusionProofError(
+            "unexpected end of the inclusion proof".to_string(),
+        ));
+    }
+    let (taken, rest) = bytes.split_at(len);
+    *bytes = rest;
+    Ok(taken)
+}
+
+impl From<&MerkleAuditPath> for MerkleAuditPathDto {
+    fn from(value: &MerkleAuditPath) -> Self {
+        match value {
+            MerkleAuditPath::Node(left, right) => Self::Node {
+                left: Box::new(left.as_ref().into()),
+                right: Box::new(right.as_ref().into()),
+            },
+            MerkleAuditPath::Hash(hash) => Self::Hash {
+                hash: prefix_hex::encode(hash),
+            },
+            MerkleAuditPath::Value(block_id) => Self::Value {
+                value: block_id.to_string(),
+            },
+        }
+    }
+}
+
+impl TryFrom<&MerkleAuditPathDto> for MerkleAuditPath {
+    type Error = Error;
+
+    fn try_from(value: &MerkleAuditPathDto) -> Result<Self> {
+        Ok(match value {
+            MerkleAuditPathDto::Node { left, right } => Self::Node(
+                Box::new(left.as_ref().try_into()?),
+                Box::new(right.as_ref().try_into()?),
+            ),
+            MerkleAuditPathDto::Hash { hash } => Self::Hash(prefix_hex::decode(hash)?),
+            MerkleAuditPathDto::Value { value } => Self::Value(BlockId::from_str(value)?),
+        })
+    }
+}
+
+impl From<&InclusionProof> for InclusionProofDto {
+    fn from(value: &InclusionProof) -> Self {
+        Self {
+            milestone: (&value.milestone).into(),
+            block: BlockDto::from(&value.block),
+            proof: (&value.audit_path).into(),
+        }
+    }
+}
+
+impl TryFrom<&InclusionProofDto> for InclusionProof {
+    type Error = Error;
+
+    fn try_from(value: &InclusionProofDto) -> Result<Self> {
+        Ok(Self {
+            milestone: MilestonePayload::try_from(&value.milestone)?,
+            block: Block::try_from(&value.block)?,
+            audit_path: (&value.proof).try_into()?,
+        })
+    }
+}
+
+impl Client {
+    /// Builds the proof of inclusion of a block from the block, the milestone that referenced it and the blocks of
+    /// that milestone, for nodes without the proof-of-inclusion plugin.
+    pub async fn build_inclusion_proof(&self, block_id: &BlockId) -> Result<InclusionProof> {
+        let index = self
+            .get_block_metadata(block_id)
+            .await?
+            .referenced_by_milestone_index
+            .ok_or_else(|| {
+                Error::InclusionProofError(format!("block {block_id} isn't referenced by a milestone yet"))
+            })?;
+        let (block, milestone) =
+            futures::future::try_join(self.get_block(block_id), self.get_milestone_by_index(index)).await?;
+
+        let referenced_block_ids = self
+            .get_milestone_referenced_blocks(&milestone)
+            .await?
+            .iter()
+            .map(|metadata| BlockId::from_str(&metadata.block_id))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let audit_path = referenced_block_ids
+            .iter()
+            .position(|referenced| referenced == block_id)
+            .and_then(|position| MerkleAuditPath::new(&referenced_block_ids, position))
+            .ok_or_else(|| {
+                Error::InclusionProofError(format!("milestone {index} doesn't reference block {block_id}"))
+            })?;
+
+        Ok(InclusionProof {
+            milestone,
+            block,
+            audit_path,
+        })
+    }
+}
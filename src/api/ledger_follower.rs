@@ -0,0 +1,267 @@
+// Copyright 2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Follows the ledger milestone by milestone and reports the outputs created and spent on watched addresses, for nodes
+//! without MQTT
+
+use std::{collections::HashSet, str::FromStr, time::Duration};
+
+use bee_api_types::responses::OutputMetadataResponse;
+use bee_block::{
+    address::Address,
+    output::{unlock_condition::UnlockCondition, Output, OutputId},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{Client, Result};
+
+/// Default interval in which the follower checks for a new confirmed milestone.
+pub const DEFAULT_LEDGER_FOLLOWER_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The position of a [`LedgerFollower`] in the ledger, to persist and restart the follower from.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LedgerCheckpoint {
+    /// Index of the next milestone the follower processes
+    #[serde(rename = "milestoneIndex")]
+    pub milestone_index: u32,
+}
+
+/// An output that references a watched address in one of its unlock conditions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchedOutput {
+    /// The output ID
+    pub output_id: OutputId,
+    /// The output
+    pub output: Output,
+    /// The metadata of the output, at the time the milestone got processed
+    pub metadata: OutputMetadataResponse,
+    /// The watched addresses the unlock conditions reference
+    pub addresses: Vec<Address>,
+}
+
+/// A change of the ledger that concerns a watched address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LedgerEvent {
+    /// The milestone created the output
+    OutputCreated(WatchedOutput),
+    /// The milestone spent the output
+    OutputSpent(WatchedOutput),
+}
+
+/// The events of a milestone, created outputs before spent ones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MilestoneEvents {
+    /// The index of the milestone
+    pub milestone_index: u32,
+    /// The events for the watched addresses, possibly none
+    pub events: Vec<LedgerEvent>,
+    /// The checkpoint after this milestone
+    pub checkpoint: LedgerCheckpoint,
+}
+
+impl Client {
+    /// Creates a builder for a [`LedgerFollower`].
+    pub fn ledger_follower(&self) -> LedgerFollowerBuilder {
+        LedgerFollowerBuilder::new(self)
+    }
+}
+
+/// Builder of a [`LedgerFollower`].
+#[must_use]
+pub struct LedgerFollowerBuilder {
+    client: Client,
+    addresses: HashSet<Address>,
+    checkpoint: Option<LedgerCheckpoint>,
+    poll_interval: Duration,
+}
+
+impl LedgerFollowerBuilder {
+    /// Creates a builder without watched addresses that starts after the latest confirmed milestone.
+    pub fn new(client: &Client) -> Self {
+        Self {
+            client: client.clone(),
+            addresses: HashSet::new(),
+            checkpoint: None,
+            poll_interval: DEFAULT_LEDGER_FOLLOWER_POLL_INTERVAL,
+        }
+    }
+
+    /// Watches the address.
+    pub fn with_address(mut self, address: Address) -> Self {
+        self.addresses.insert(address);
+        self
+    }
+
+    /// Watches the addresses.
+    pub fn with_addresses(mut self, addresses: impl IntoIterator<Item = Address>) -> Self {
+        self.addresses.extend(addresses);
+        self
+    }
+
+    /// Resumes from a persisted checkpoint instead of the latest confirmed milestone.
+    pub fn with_checkpoint(mut self, checkpoint: LedgerCheckpoint) -> Self {
+        self.checkpoint.replace(checkpoint);
+        self
+    }
+
+    /// Sets the interval in which the follower checks for a new confirmed milestone.
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Builds the follower, requesting the latest confirmed milestone if no checkpoint was set.
+    pub async fn finish(self) -> Result<LedgerFollower> {
+        let checkpoint = match self.checkpoint {
+            Some(checkpoint) => checkpoint,
+            None => LedgerCheckpoint {
+                milestone_index: confirmed_milestone_index(&self.client).await? + 1,
+            },
+        };
+
+        Ok(LedgerFollower {
+            client: self.client,
+            addresses: self.addresses,
+            checkpoint,
+            poll_interval: self.poll_interval,
+        })
+    }
+}
+
+/// Walks the confirmed milestones through their UTXO changes and reports the outputs that were created or spent on
+/// the watched addresses.
+///
+/// Every milestone is processed exactly once: the checkpoint only advances when [`LedgerFollower::next_milestone()`]
+/// returns its events. Persisting the checkpoint of the events together with their effects makes a restart continue
+/// with the next milestone, without missing or repeating events. Milestones that no node or permanode has anymore
+/// return an error instead of being skipped.
+pub struct LedgerFollower {
+    client: Client,
+    addresses: HashSet<Address>,
+    checkpoint: LedgerCheckpoint,
+    poll_interval: Duration,
+}
+
+impl LedgerFollower {
+    /// The checkpoint of the next milestone to process.
+    pub fn checkpoint(&self) -> LedgerCheckpoint {
+        self.checkpoint
+    }
+
+    /// The watched addresses.
+    pub fn addresses(&self) -> &HashSet<Address> {
+        &self.addresses
+    }
+
+    /// Watches another address from the next milestone on.
+    pub fn watch_address(&mut self, address: Address) {
+        self.addresses.insert(address);
+    }
+
+    /// Stops watching the address from the next milestone on.
+    pub fn unwatch_address(&mut self, address: &Address) {
+        self.addresses.remove(address);
+    }
+
+    /// Processes the next milestone, waiting until it's confirmed. On error the checkpoint stays the same, so the
+    /// milestone is processed again on the next call.
+    pub async fn next_milestone(&mut self) -> Result<MilestoneEvents> {
+        loop {
+            if let Some(events) = self.try_next_milestone().await? {
+                return Ok(events);
+            }
+            #[cfg(not(target_family = "wasm"))]
+            tokio::time::sleep(self.poll_interval).await;
+            #[cfg(target_family = "wasm")]
+            gloo_timers::future::TimeoutFuture::new(self.poll_interval.as_millis().try_into().unwrap_or(u32::MAX))
+                .await;
+        }
+    }
+
+    /// Processes the next milestone if it's confirmed already.
+    pub async fn try_next_milestone(&mut self) -> Result<Option<MilestoneEvents>> {
+        let milestone_index = self.checkpoint.milestone_index;
+        if milestone_index > confirmed_milestone_index(&self.client).await? {
+            return Ok(None);
+        }
+
+        let utxo_changes = self.client.get_utxo_changes_by_index(milestone_index).await?;
+        let mut events = self
+            .watched_outputs(&utxo_changes.created_outputs)
+            .await?
+            .into_iter()
+            .map(LedgerEvent::OutputCreated)
+            .collect::<Vec<_>>();
+        events.extend(
+            self.watched_outputs(&utxo_changes.consumed_outputs)
+                .await?
+                .into_iter()
+                .map(LedgerEvent::OutputSpent),
+        );
+
+        self.checkpoint.milestone_index += 1;
+
+        Ok(Some(MilestoneEvents {
+            milestone_index,
+            events,
+            checkpoint: self.checkpoint,
+        }))
+    }
+
+    async fn watched_outputs(&self, output_ids: &[String]) -> Result<Vec<WatchedOutput>> {
+        if self.addresses.is_empty() {
+            return Ok(Vec::new());
+        }
+        let output_ids = output_ids
+            .iter()
+            .map(|output_id| OutputId::from_str(output_id))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let mut watched_outputs = Vec::new();
+        // The responses are in the order of the output IDs
+        for (output_id, response) in output_ids
+            .iter()
+            .zip(self.client.get_outputs(output_ids.clone()).await?)
+        {
+            let output = Output::try_from(&response.output)?;
+            let addresses = self.referenced_addresses(&output);
+            if !addresses.is_empty() {
+                watched_outputs.push(WatchedOutput {
+                    output_id: *output_id,
+                    output,
+                    metadata: response.metadata,
+                    addresses,
+                });
+            }
+        }
+
+        Ok(watched_outputs)
+    }
+
+    fn referenced_addresses(&self, output: &Output) -> Vec<Address> {
+        let mut addresses = Vec::new();
+        for unlock_condition in output
+            .unlock_conditions()
+            .iter()
+            .flat_map(|conditions| conditions.iter())
+        {
+            let address = match unlock_condition {
+                UnlockCondition::Address(condition) => condition.address(),
+                UnlockCondition::StorageDepositReturn(condition) => condition.return_address(),
+                UnlockCondition::Expiration(condition) => condition.return_address(),
+                UnlockCondition::StateControllerAddress(condition) => condition.address(),
+                UnlockCondition::GovernorAddress(condition) => condition.address(),
+                UnlockCondition::ImmutableAliasAddress(condition) => condition.address(),
+                UnlockCondition::Timelock(_) => continue,
+            };
+            if self.addresses.contains(address) && !addresses.contains(address) {
+                addresses.push(*address);
+            }
+        }
+        addresses
+    }
+}
+
+async fn confirmed_milestone_index(client: &Client) -> Result<u32> {
+    Ok(client.get_info().await?.node_info.status.confirmed_milestone.index)
+}
@@ -0,0 +1,245 @@
+// Copyright 2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Stream of the milestones of an index range together with their UTXO changes
+
+use std::{
+    future::Future,
+    ops::{Bound, RangeBounds},
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use bee_api_types::responses::UtxoChangesResponse;
+use bee_block::payload::milestone::MilestonePayload;
+use futures::{stream::FuturesOrdered, Stream, StreamExt};
+
+use crate::{Client, Error, Result};
+
+/// Default number of milestones that are requested ahead of the consumer.
+pub const DEFAULT_MILESTONE_PREFETCH: usize = 8;
+/// Times a milestone above the pruning index is requested again if it wasn't found
+const MILESTONE_NOT_FOUND_RETRIES: usize = 3;
+/// Delay before a milestone that wasn't found is requested again, unless the follow mode has a poll interval
+const MILESTONE_NOT_FOUND_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+#[cfg(not(target_family = "wasm"))]
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+#[cfg(target_family = "wasm")]
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T>>>;
+
+/// A milestone and the outputs it created and consumed.
+pub type MilestoneWithUtxoChanges = (MilestonePayload, UtxoChangesResponse);
+
+impl Client {
+    /// Returns a stream of the milestones in the index range with their UTXO changes, in index order.
+    ///
+    /// Up to [`DEFAULT_MILESTONE_PREFETCH`] milestones are requested concurrently. Old milestones that the nodes
+    /// pruned are requested from the permanodes. A pruned milestone that no permanode has yields
+    /// [`Error::MilestoneGap`] and the stream continues with the next index. A milestone above the pruning index that
+    /// isn't found is requested again a few times, in case the node that answered isn't synced yet. Any other error
+    /// ends the stream. To resume, create a new stream starting at [`MilestoneStream::next_index()`].
+    ///
+    /// Without follow mode the stream ends at the latest confirmed milestone, even if the range goes beyond it.
+    pub fn milestones(&self, range: impl RangeBounds<u32>) -> MilestoneStream {
+        let start = match range.start_bound() {
+            Bound::Included(start) => *start,
+            Bound::Excluded(start) => start.saturating_add(1),
+            // Milestone indexes start at 1
+            Bound::Unbounded => 1,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(end) => Some(*end),
+            Bound::Excluded(end) => Some(end.saturating_sub(1)),
+            Bound::Unbounded => None,
+        };
+
+        MilestoneStream {
+            client: self.clone(),
+            end,
+            next_request: Some(start),
+            next_index: start,
+            confirmed_index: None,
+            prefetch: DEFAULT_MILESTONE_PREFETCH,
+            follow: None,
+            requests: FuturesOrdered::new(),
+            confirmed_index_request: None,
+            finished: false,
+        }
+    }
+}
+
+/// Stream of the milestones of an index range, created with [`Client::milestones()`].
+#[must_use = "streams do nothing unless polled"]
+pub struct MilestoneStream {
+    client: Client,
+    // Last index of the range, inclusive
+    end: Option<u32>,
+    // `None` once the last possible index was requested
+    next_request: Option<u32>,
+    next_index: u32,
+    // Latest confirmed milestone index, requested before the first milestone
+    confirmed_index: Option<u32>,
+    prefetch: usize,
+    // Poll interval in follow mode
+    follow: Option<Duration>,
+    requests: FuturesOrdered<BoxFuture<(u32, Result<MilestoneWithUtxoChanges>)>>,
+    confirmed_index_request: Option<BoxFuture<Result<u32>>>,
+    finished: bool,
+}
+
+impl MilestoneStream {
+    /// Sets how many milestones are requested concurrently ahead of the consumer, at least 1.
+    pub fn with_prefetch(mut self, prefetch: usize) -> Self {
+        self.prefetch = prefetch.max(1);
+        self
+    }
+
+    /// Waits for new milestones once the latest confirmed milestone is reached instead of ending the stream, polling
+    /// the node info in the given interval. The stream only ends at the end of the range, if it has one.
+    pub fn with_follow_mode(mut self, poll_interval: Duration) -> Self {
+        self.follow.replace(poll_interval);
+        self
+    }
+
+    /// The index of the next milestone the stream yields, to resume the stream from after an error or a restart.
+    pub fn next_index(&self) -> u32 {
+        self.next_index
+    }
+
+    fn last_requestable_index(&self) -> Option<u32> {
+        let confirmed_index = self.confirmed_index?;
+        Some(self.end.map_or(confirmed_index, |end| end.min(confirmed_index)))
+    }
+
+    fn range_finished(&self) -> bool {
+        self.next_request
+            .is_none_or(|next_request| self.end.is_some_and(|end| next_request > end))
+    }
+
+    fn request_milestone(&mut self) {
+        let index = match self.next_request {
+            Some(index) => index,
+            None => return,
+        };
+        self.next_request = index.checked_add(1);
+        let client = self.client.clone();
+        let retry_interval = self.follow.unwrap_or(MILESTONE_NOT_FOUND_RETRY_INTERVAL);
+        self.requests.push_back(Box::pin(async move {
+            let mut retries = 0;
+            loop {
+                let result = futures::future::try_join(
+                    client.get_milestone_by_index(index),
+                    client.get_utxo_changes_by_index(index),
+                )
+                .await;
+                if !matches!(result, Err(Error::NotFound(_))) {
+                    return (index, result);
+                }
+                // Only pruned milestones can be missing for good
+                match client.get_info().await {
+                    Ok(info) if index <= info.node_info.status.pruning_index => {
+                        return (index, Err(Error::MilestoneGap(index)));
+                    }
+                    Ok(_) if retries < MILESTONE_NOT_FOUND_RETRIES => retries += 1,
+                    Ok(_) => return (index, result),
+                    Err(e) => return (index, Err(e)),
+                }
+                sleep(retry_interval).await;
+            }
+        }));
+    }
+
+    fn request_confirmed_index(&mut self) {
+        let client = self.client.clone();
+        // The first request isn't delayed
+        let delay = self.confirmed_index.and(self.follow);
+        self.confirmed_index_request.replace(Box::pin(async move {
+            if let Some(delay) = delay {
+                sleep(delay).await;
+            }
+            Ok(client.get_info().await?.node_info.status.confirmed_milestone.index)
+        }));
+    }
+}
+
+async fn sleep(duration: Duration) {
+    #[cfg(not(target_family = "wasm"))]
+    tokio::time::sleep(duration).await;
+    #[cfg(target_family = "wasm")]
+    gloo_timers::future::TimeoutFuture::new(duration.as_millis().try_into().unwrap_or(u32::MAX)).await;
+}
+
+impl Stream for MilestoneStream {
+    type Item = Result<MilestoneWithUtxoChanges>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if self.finished {
+                return Poll::Ready(None);
+            }
+
+            if let Some(last) = self.last_requestable_index() {
+                while self.requests.len() < self.prefetch
+                    && self.next_request.is_some_and(|next_request| next_request <= last)
+                    && !self.range_finished()
+                {
+                    self.request_milestone();
+                }
+            }
+
+            if !self.requests.is_empty() {
+                return match self.requests.poll_next_unpin(cx) {
+                    Poll::Ready(Some((index, result))) => Poll::Ready(Some(match result {
+                        Ok(milestone) => {
+                            self.next_index = index.saturating_add(1);
+                            Ok(milestone)
+                        }
+                        Err(Error::MilestoneGap(index)) => {
+                            self.next_index = index.saturating_add(1);
+                            Err(Error::MilestoneGap(index))
+                        }
+                        // The failed milestone is requested again when resuming
+                        Err(e) => {
+                            self.finished = true;
+                            Err(e)
+                        }
+                    })),
+                    Poll::Ready(None) => continue,
+                    Poll::Pending => Poll::Pending,
+                };
+            }
+
+            // Everything up to the latest known confirmed milestone was yielded
+            if self.range_finished() || (self.confirmed_index.is_some() && self.follow.is_none()) {
+                self.finished = true;
+                continue;
+            }
+
+            if self.confirmed_index_request.is_none() {
+                self.request_confirmed_index();
+            }
+            let poll = self
+                .confirmed_index_request
+                .as_mut()
+                .map(|request| request.as_mut().poll(cx))
+                .unwrap_or(Poll::Pending);
+            match poll {
+                Poll::Ready(result) => {
+                    self.confirmed_index_request = None;
+                    match result {
+                        Ok(confirmed_index) => {
+                            self.confirmed_index.replace(confirmed_index);
+                        }
+                        Err(e) => {
+                            self.finished = true;
+                            return Poll::Ready(Some(Err(e)));
+                        }
+                    }
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
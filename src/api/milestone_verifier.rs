@@ -0,0 +1,430 @@
+// Copyright 2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Local verification of milestones, so a light client doesn't have to trust the node that served them
+
+use std::{collections::HashSet, str::FromStr};
+
+use bee_api_types::responses::BlockMetadataResponse;
+use bee_block::{
+    payload::milestone::{MerkleRoot, MilestoneId, MilestonePayload, MilestoneValidationError},
+    BlockId,
+};
+use crypto::hashes::{blake2b::Blake2b256, Digest};
+use serde::{Deserialize, Serialize};
+
+use crate::{Client, Error, Result};
+
+/// A milestone public key and the milestone indexes it signs, like the key ranges of the protocol configuration of
+/// the nodes.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MilestoneKeyRange {
+    /// The hex encoded ed25519 public key
+    #[serde(rename = "publicKey")]
+    pub public_key: String,
+    /// The first milestone index the key signs
+    #[serde(rename = "startIndex")]
+    pub start_index: u32,
+    /// The last milestone index the key signs, 0 if the key has no end
+    #[serde(rename = "endIndex", default)]
+    pub end_index: u32,
+}
+
+impl MilestoneKeyRange {
+    /// Creates a key range, an `end_index` of 0 means the key has no end.
+    pub fn new(public_key: impl Into<String>, start_index: u32, end_index: u32) -> Self {
+        Self {
+            public_key: public_key.into(),
+            start_index,
+            end_index,
+        }
+    }
+
+    fn applies_to(&self, index: u32) -> bool {
+        self.start_index <= index && (self.end_index == 0 || index <= self.end_index)
+    }
+}
+
+/// Reasons a milestone fails the verification.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error, Serialize)]
+#[serde(tag = "type", content = "error")]
+pub enum MilestoneVerificationError {
+    /// The signature threshold is 0
+    #[error("the signature threshold must be at least 1")]
+    InvalidThreshold,
+    /// Fewer public keys apply to the milestone than the threshold requires
+    #[error("{applicable} public keys apply to milestone {index}, the threshold is {threshold}")]
+    TooFewApplicableKeys {
+        /// The milestone index
+        index: u32,
+        /// The keys that apply to the index
+        applicable: usize,
+        /// The signature threshold
+        threshold: usize,
+    },
+    /// The milestone has fewer signatures than the threshold requires
+    #[error("milestone {index} has {found} signatures, the threshold is {threshold}")]
+    TooFewSignatures {
+        /// The milestone index
+        index: u32,
+        /// The signatures of the milestone
+        found: usize,
+        /// The signature threshold
+        threshold: usize,
+    },
+    /// A signature of a key that doesn't apply to the milestone
+    #[error("milestone {index} is signed by {public_key}, which doesn't apply to it")]
+    UnapplicablePublicKey {
+        /// The milestone index
+        index: u32,
+        /// The hex encoded public key
+        public_key: String,
+    },
+    /// A signature doesn't match the essence
+    #[error("the signature of {public_key} on milestone {index} is invalid")]
+    InvalidSignature {
+        /// The milestone index
+        index: u32,
+        /// The hex encoded public key
+        public_key: String,
+    },
+    /// The node returned a different milestone than requested
+    #[error("requested milestone {expected}, got {found}")]
+    MilestoneIdMismatch {
+        /// The requested milestone ID
+        expected: String,
+        /// The ID of the returned milestone
+        found: String,
+    },
+    /// The milestone doesn't follow the last verified one
+    #[error("expected milestone {expected}, got {found}")]
+    IndexMismatch {
+        /// The index following the last verified milestone
+        expected: u32,
+        /// The index of the milestone
+        found: u32,
+    },
+    /// The milestone doesn't reference the last verified milestone
+    #[error("milestone {index} references the previous milestone {found} instead of {expected}")]
+    PreviousMilestoneMismatch {
+        /// The milestone index
+        index: u32,
+        /// The ID of the last verified milestone
+        expected: String,
+        /// The previous milestone ID of the milestone
+        found: String,
+    },
+    /// The referenced blocks don't match the inclusion merkle root
+    #[error(
+        "the inclusion merkle root of milestone {index} is {expected}, the referenced blocks result in {computed}"
+    )]
+    InclusionMerkleRootMismatch {
+        /// The milestone index
+        index: u32,
+        /// The merkle root of the milestone
+        expected: String,
+        /// The merkle root of the referenced blocks
+        computed: String,
+    },
+    /// The audit path of an inclusion proof doesn't contain the block
+    #[error("the audit path doesn't contain block {block_id}")]
+    BlockNotInAuditPath {
+        /// The block ID
+        block_id: String,
+    },
+    /// The applied blocks don't match the applied merkle root
+    #[error("the applied merkle root of milestone {index} is {expected}, the applied blocks result in {computed}")]
+    AppliedMerkleRootMismatch {
+        /// The milestone index
+        index: u32,
+        /// The merkle root of the milestone
+        expected: String,
+        /// The merkle root of the applied blocks
+        computed: String,
+    },
+}
+
+/// Verifies milestone signatures against a rotating set of public keys and the chain of milestones through their
+/// previous milestone IDs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MilestoneVerifier {
+    key_ranges: Vec<MilestoneKeyRange>,
+    threshold: usize,
+    // Index and ID of the last verified milestone
+    latest: Option<(u32, MilestoneId)>,
+}
+
+impl MilestoneVerifier {
+    /// Creates a verifier that requires `threshold` valid signatures of keys that apply to the milestone index.
+    pub fn new(key_ranges: Vec<MilestoneKeyRange>, threshold: usize) -> Result<Self> {
+        if threshold == 0 {
+            return Err(MilestoneVerificationError::InvalidThreshold.into());
+        }
+        let key_ranges = key_ranges
+            .into_iter()
+            .map(|range| MilestoneKeyRange {
+                // Keys are compared without prefix, like the nodes configure them
+                public_key: range.public_key.trim_start_matches("0x").to_lowercase(),
+                ..range
+            })
+            .collect();
+
+        Ok(Self {
+            key_ranges,
+            threshold,
+            latest: None,
+        })
+    }
+
+    /// Starts the chain at a milestone that is trusted without verification, e.g. one from a snapshot. Otherwise the
+    /// first milestone verified with [`MilestoneVerifier::verify()`] starts the chain.
+    #[must_use]
+    pub fn with_trusted_milestone(mut self, index: u32, milestone_id: MilestoneId) -> Self {
+        self.latest.replace((index, milestone_id));
+        self
+    }
+
+    /// Index and ID of the last verified or trusted milestone.
+    pub fn latest_milestone(&self) -> Option<(u32, MilestoneId)> {
+        self.latest
+    }
+
+    /// Verifies the signatures and that the milestone follows the last verified one, then continues the chain from it.
+    pub fn verify(&mut self, milestone: &MilestonePayload) -> Result<()> {
+        self.verify_signatures(milestone)?;
+
+        let index = *milestone.essence().index();
+        if let Some((latest_index, latest_id)) = self.latest {
+            if index != latest_index + 1 {
+                return Err(MilestoneVerificationError::IndexMismatch {
+                    expected: latest_index + 1,
+                    found: index,
+                }
+                .into());
+            }
+            if *milestone.essence().previous_milestone_id() != latest_id {
+                return Err(MilestoneVerificationError::PreviousMilestoneMismatch {
+                    index,
+                    expected: latest_id.to_string(),
+                    found: milestone.essence().previous_milestone_id().to_string(),
+                }
+                .into());
+            }
+        }
+        self.latest.replace((index, milestone.id()));
+
+        Ok(())
+    }
+
+    /// Verifies only the signatures, with the keys that apply to the milestone index.
+    pub fn verify_signatures(&self, milestone: &MilestonePayload) -> Result<()> {
+        let index = *milestone.essence().index();
+        let applicable_public_keys = self
+            .key_ranges
+            .iter()
+            .filter(|range| range.applies_to(index))
+            .map(|range| range.public_key.clone())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+
+        milestone
+            .validate(&applicable_public_keys, self.threshold)
+            .map_err(|error| {
+                match error {
+                    MilestoneValidationError::InvalidMinThreshold => MilestoneVerificationError::InvalidThreshold,
+                    MilestoneValidationError::InsufficientApplicablePublicKeys(applicable, threshold) => {
+                        MilestoneVerificationError::TooFewApplicableKeys {
+                            index,
+                            applicable,
+                            threshold,
+                        }
+                    }
+                    MilestoneValidationError::TooFewSignatures(threshold, found) => {
+                        MilestoneVerificationError::TooFewSignatures {
+                            index,
+                            found,
+                            threshold,
+                        }
+                    }
+                    MilestoneValidationError::UnapplicablePublicKey(public_key) => {
+                        MilestoneVerificationError::UnapplicablePublicKey { index, public_key }
+                    }
+                    MilestoneValidationError::InvalidSignature(_, public_key) => {
+                        MilestoneVerificationError::InvalidSignature { index, public_key }
+                    }
+                    // The public key isn't a valid curve point
+                    MilestoneValidationError::Crypto(_) => MilestoneVerificationError::InvalidSignature {
+                        index,
+                        public_key: milestone_public_keys(milestone),
+                    },
+                }
+                .into()
+            })
+    }
+
+    /// Verifies the merkle roots of the milestone against the IDs of the blocks it referenced and of the blocks with
+    /// an applied transaction, both in white flag order.
+    pub fn verify_merkle_roots(
+        milestone: &MilestonePayload,
+        referenced_block_ids: &[BlockId],
+        applied_block_ids: &[BlockId],
+    ) -> Result<()> {
+        let index = *milestone.essence().index();
+
+        let computed = merkle_root(referenced_block_ids);
+        if computed != *milestone.essence().inclusion_merkle_root() {
+            return Err(MilestoneVerificationError::InclusionMerkleRootMismatch {
+                index,
+                expected: milestone.essence().inclusion_merkle_root().to_string(),
+                computed: computed.to_string(),
+            }
+            .into());
+        }
+        let computed = merkle_root(applied_block_ids);
+        if computed != *milestone.essence().applied_merkle_root() {
+            return Err(MilestoneVerificationError::AppliedMerkleRootMismatch {
+                index,
+                expected: milestone.essence().applied_merkle_root().to_string(),
+                computed: computed.to_string(),
+            }
+            .into());
+        }
+
+        Ok(())
+    }
+}
+
+// Only used if a key can't be decoded, which bee-block doesn't attribute to a signature
+fn milestone_public_keys(milestone: &MilestonePayload) -> String {
+    milestone
+        .signatures()
+        .iter()
+        .map(|signature| {
+            let bee_block::signature::Signature::Ed25519(signature) = signature;
+            prefix_hex::encode(signature.public_key())
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Computes the merkle root of block IDs as described in TIP-0004.
+pub fn merkle_root(block_ids: &[BlockId]) -> MerkleRoot {
+    MerkleRoot::new(merkle_hash(block_ids))
+}
+
+pub(crate) fn merkle_hash(block_ids: &[BlockId]) -> [u8; 32] {
+    match block_ids {
+        [] => Blake2b256::digest([]).into(),
+        [block_id] => {
+            let mut hasher = Blake2b256::new();
+            hasher.update([0u8]);
+            hasher.update(block_id.as_ref());
+            hasher.finalize().into()
+        }
+        _ => {
+            // Split at the largest power of two smaller than the amount of leaves
+            let split = block_ids.len().next_power_of_two() / 2;
+            let mut hasher = Blake2b256::new();
+            hasher.update([1u8]);
+            hasher.update(merkle_hash(&block_ids[..split]));
+            hasher.update(merkle_hash(&block_ids[split..]));
+            hasher.finalize().into()
+        }
+    }
+}
+
+impl Client {
+    /// Gets the milestone by its index and verifies it, see [`MilestoneVerifier::verify()`].
+    pub async fn get_verified_milestone_by_index(
+        &self,
+        verifier: &mut MilestoneVerifier,
+        index: u32,
+    ) -> Result<MilestonePayload> {
+        let milestone = self.get_milestone_by_index(index).await?;
+        if *milestone.essence().index() != index {
+            return Err(MilestoneVerificationError::IndexMismatch {
+                expected: index,
+                found: *milestone.essence().index(),
+            }
+            .into());
+        }
+        verifier.verify(&milestone)?;
+
+        Ok(milestone)
+    }
+
+    /// Gets the milestone by its ID and verifies it, see [`MilestoneVerifier::verify()`].
+    pub async fn get_verified_milestone_by_id(
+        &self,
+        verifier: &mut MilestoneVerifier,
+        milestone_id: &MilestoneId,
+    ) -> Result<MilestonePayload> {
+        let milestone = self.get_milestone_by_id(milestone_id).await?;
+        // The ID is the hash of the essence, so this also checks the content
+        if milestone.id() != *milestone_id {
+            return Err(MilestoneVerificationError::MilestoneIdMismatch {
+                expected: milestone_id.to_string(),
+                found: milestone.id().to_string(),
+            }
+            .into());
+        }
+        verifier.verify(&milestone)?;
+
+        Ok(milestone)
+    }
+
+    /// Returns the metadata of the blocks the milestone referenced in white flag order, walking the past cone of its
+    /// parents until the blocks of earlier milestones.
+    pub async fn get_milestone_referenced_blocks(
+        &self,
+        milestone: &MilestonePayload,
+    ) -> Result<Vec<BlockMetadataResponse>> {
+        let index = *milestone.essence().index();
+        let mut visited = milestone.essence().parents().iter().copied().collect::<HashSet<_>>();
+        let mut level = milestone.essence().parents().to_vec();
+        let mut referenced = Vec::new();
+
+        while !level.is_empty() {
+            let metadata =
+                futures::future::try_join_all(level.iter().map(|block_id| self.get_block_metadata(block_id))).await?;
+            level = Vec::new();
+            for metadata in metadata {
+                if metadata.referenced_by_milestone_index != Some(index) {
+                    continue;
+                }
+                for parent in &metadata.parents {
+                    let parent = BlockId::from_str(parent)?;
+                    if visited.insert(parent) {
+                        level.push(parent);
+                    }
+                }
+                referenced.push(metadata);
+            }
+        }
+
+        if referenced.iter().any(|metadata| metadata.white_flag_index.is_none()) {
+            return Err(Error::UnexpectedApiResponse);
+        }
+        referenced.sort_by_key(|metadata| metadata.white_flag_index);
+
+        Ok(referenced)
+    }
+
+    /// Verifies the inclusion and applied merkle roots of the milestone against the referenced blocks the node
+    /// reports. The milestone itself should be verified before, otherwise the node could just as well forge it.
+    pub async fn verify_milestone_merkle_roots(&self, milestone: &MilestonePayload) -> Result<()> {
+        let referenced = self.get_milestone_referenced_blocks(milestone).await?;
+        let mut referenced_block_ids = Vec::with_capacity(referenced.len());
+        let mut applied_block_ids = Vec::new();
+        for metadata in &referenced {
+            let block_id = BlockId::from_str(&metadata.block_id)?;
+            referenced_block_ids.push(block_id);
+            if metadata.ledger_inclusion_state == Some(bee_api_types::dtos::LedgerInclusionStateDto::Included) {
+                applied_block_ids.push(block_id);
+            }
+        }
+
+        MilestoneVerifier::verify_merkle_roots(milestone, &referenced_block_ids, &applied_block_ids)
+    }
+}
@@ -6,11 +6,21 @@
 mod address;
 mod block_builder;
 mod consolidation;
+mod inclusion_proof;
+mod ledger_follower;
+mod milestone_stream;
+mod milestone_verifier;
+mod past_cone;
 mod types;
 
 pub use self::{
     address::*,
     block_builder::{pow::*, *},
+    inclusion_proof::*,
+    ledger_follower::*,
+    milestone_stream::*,
+    milestone_verifier::*,
+    past_cone::*,
     types::*,
 };
 
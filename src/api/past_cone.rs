@@ -0,0 +1,253 @@
+// Copyright 2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Breadth-first traversal of the past cone of a block, to inspect why a block doesn't get referenced
+
+use std::{collections::HashSet, fmt::Write, str::FromStr};
+
+use bee_api_types::dtos::LedgerInclusionStateDto;
+use bee_block::BlockId;
+use serde::{Deserialize, Serialize};
+
+use crate::{constants::DEFAULT_PAST_CONE_MAX_BLOCKS, Client, Error, Result};
+
+/// Limits of a past cone traversal.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[must_use]
+pub struct PastConeLimits {
+    #[serde(default, rename = "maxDepth")]
+    pub(crate) max_depth: Option<u32>,
+    #[serde(default = "default_max_blocks", rename = "maxBlocks")]
+    pub(crate) max_blocks: usize,
+    #[serde(default = "default_stop_at_milestone_boundary", rename = "stopAtMilestoneBoundary")]
+    pub(crate) stop_at_milestone_boundary: bool,
+}
+
+fn default_max_blocks() -> usize {
+    DEFAULT_PAST_CONE_MAX_BLOCKS
+}
+
+fn default_stop_at_milestone_boundary() -> bool {
+    true
+}
+
+impl Default for PastConeLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: None,
+            max_blocks: default_max_blocks(),
+            stop_at_milestone_boundary: default_stop_at_milestone_boundary(),
+        }
+    }
+}
+
+impl PastConeLimits {
+    /// Creates the default limits: up to 1000 blocks, stopping at the milestone boundary.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets how many parent levels are traversed, the root block has depth 0.
+    pub fn max_depth(mut self, max_depth: u32) -> Self {
+        self.max_depth.replace(max_depth);
+        self
+    }
+
+    /// Sets how many blocks are requested at most.
+    pub fn max_blocks(mut self, max_blocks: usize) -> Self {
+        self.max_blocks = max_blocks;
+        self
+    }
+
+    /// Sets whether the parents of blocks that another milestone than the one of the root block referenced are
+    /// traversed. For an unreferenced block that means the traversal stops at the referenced blocks.
+    pub fn stop_at_milestone_boundary(mut self, stop_at_milestone_boundary: bool) -> Self {
+        self.stop_at_milestone_boundary = stop_at_milestone_boundary;
+        self
+    }
+}
+
+/// A block of a past cone with its metadata.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PastConeBlock {
+    /// The block ID
+    #[serde(rename = "blockId")]
+    pub block_id: BlockId,
+    /// The distance to the root block
+    pub depth: u32,
+    /// The parents of the block, whether they are part of the cone or not
+    pub parents: Vec<BlockId>,
+    /// Whether the node has the whole past cone of the block
+    #[serde(rename = "isSolid")]
+    pub is_solid: bool,
+    /// The milestone that referenced the block
+    #[serde(rename = "referencedByMilestoneIndex", skip_serializing_if = "Option::is_none")]
+    pub referenced_by_milestone_index: Option<u32>,
+    /// The ledger inclusion state, once the block is referenced
+    #[serde(rename = "ledgerInclusionState", skip_serializing_if = "Option::is_none")]
+    pub ledger_inclusion_state: Option<LedgerInclusionStateDto>,
+    /// Whether the parents were traversed, false if a limit stopped the traversal at the block
+    pub expanded: bool,
+}
+
+/// The past cone of a block as a DAG, in breadth-first order starting with the root block.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PastCone {
+    /// The block the traversal started at
+    pub root: BlockId,
+    /// The traversed blocks
+    pub blocks: Vec<PastConeBlock>,
+    /// Parents that no node had, because they were pruned or never existed
+    pub missing: Vec<BlockId>,
+    /// Whether the block limit left out parents of expanded blocks
+    pub truncated: bool,
+}
+
+impl Client {
+    /// Traverses the parents of a block breadth-first within the limits, requesting the metadata of every level
+    /// concurrently.
+    pub async fn past_cone(&self, block_id: &BlockId, limits: PastConeLimits) -> Result<PastCone> {
+        let mut cone = PastCone {
+            root: *block_id,
+            blocks: Vec::new(),
+            missing: Vec::new(),
+            truncated: false,
+        };
+        // The milestone of the root block, blocks of other milestones are the boundary
+        let mut boundary = None;
+        let mut visited = HashSet::from([*block_id]);
+        let mut level = vec![*block_id];
+        let mut depth = 0;
+
+        while !level.is_empty() {
+            let metadata =
+                futures::future::join_all(level.iter().map(|block_id| self.get_block_metadata(block_id))).await;
+            let mut next_level = Vec::new();
+
+            for (block_id, metadata) in level.into_iter().zip(metadata) {
+                let metadata = match metadata {
+                    Ok(metadata) => metadata,
+                    Err(Error::NotFound(_)) if depth > 0 => {
+                        cone.missing.push(block_id);
+                        continue;
+                    }
+                    Err(e) => return Err(e),
+                };
+                if depth == 0 {
+                    boundary = metadata.referenced_by_milestone_index;
+                }
+                let parents = metadata
+                    .parents
+                    .iter()
+                    .map(|parent| BlockId::from_str(parent))
+                    .collect::<std::result::Result<Vec<_>, _>>()?;
+
+                let depth_reached = limits.max_depth.is_some_and(|max_depth| depth >= max_depth);
+                let boundary_reached = limits.stop_at_milestone_boundary
+                    && metadata.referenced_by_milestone_index.is_some()
+                    && metadata.referenced_by_milestone_index != boundary;
+                let expanded = !(depth_reached || boundary_reached);
+                if expanded {
+                    next_level.extend(parents.iter().filter(|parent| visited.insert(**parent)));
+                }
+
+                cone.blocks.push(PastConeBlock {
+                    block_id,
+                    depth,
+                    parents,
+                    is_solid: metadata.is_solid,
+                    referenced_by_milestone_index: metadata.referenced_by_milestone_index,
+                    ledger_inclusion_state: metadata.ledger_inclusion_state,
+                    expanded,
+                });
+            }
+
+            let remaining = limits.max_blocks.saturating_sub(cone.blocks.len() + cone.missing.len());
+            if next_level.len() > remaining {
+                next_level.truncate(remaining);
+                cone.truncated = true;
+            }
+            level = next_level;
+            depth += 1;
+        }
+
+        Ok(cone)
+    }
+}
+
+impl PastCone {
+    /// Returns the block of the cone, if it was traversed.
+    pub fn block(&self, block_id: &BlockId) -> Option<&PastConeBlock> {
+        self.blocks.iter().find(|block| block.block_id == *block_id)
+    }
+
+    /// Exports the cone as JSON.
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Exports the cone in the DOT language of Graphviz, with edges from the blocks to their parents. The fill color
+    /// shows the inclusion state, parents outside of the cone are dotted.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        let mut declared = HashSet::new();
+        // Writing to a String can't fail
+        let _ = writeln!(dot, "digraph \"past cone of {}\" {{", self.root);
+        let _ = writeln!(dot, "    rankdir=RL;");
+        let _ = writeln!(dot, "    node [shape=box, style=filled, fontname=monospace];");
+
+        for block in &self.blocks {
+            let mut label = short_block_id(&block.block_id);
+            if let Some(index) = block.referenced_by_milestone_index {
+                let _ = write!(label, "\\nmilestone {index}");
+            }
+            if !block.is_solid {
+                label.push_str("\\nnot solid");
+            }
+            let color = match (&block.ledger_inclusion_state, block.referenced_by_milestone_index) {
+                (Some(LedgerInclusionStateDto::Included), _) => "palegreen",
+                (Some(LedgerInclusionStateDto::Conflicting), _) => "salmon",
+                (Some(LedgerInclusionStateDto::NoTransaction), _) | (None, Some(_)) => "lightblue",
+                (None, None) => "white",
+            };
+            let peripheries = if block.block_id == self.root { 2 } else { 1 };
+            let _ = writeln!(
+                dot,
+                "    \"{}\" [label=\"{label}\", fillcolor={color}, peripheries={peripheries}];",
+                block.block_id
+            );
+            declared.insert(block.block_id);
+        }
+        for block_id in &self.missing {
+            let _ = writeln!(
+                dot,
+                "    \"{block_id}\" [label=\"{}\\nmissing\", style=dashed];",
+                short_block_id(block_id)
+            );
+            declared.insert(*block_id);
+        }
+
+        for block in &self.blocks {
+            for parent in &block.parents {
+                if !declared.contains(parent) {
+                    let _ = writeln!(
+                        dot,
+                        "    \"{parent}\" [label=\"{}\", style=dotted];",
+                        short_block_id(parent)
+                    );
+                    declared.insert(*parent);
+                }
+                let _ = writeln!(dot, "    \"{}\" -> \"{parent}\";", block.block_id);
+            }
+        }
+        dot.push_str("}\n");
+
+        dot
+    }
+}
+
+// The prefix and the first 4 bytes are enough to tell the blocks of a cone apart
+fn short_block_id(block_id: &BlockId) -> String {
+    let block_id = block_id.to_string();
+    format!("{}…", &block_id[..10])
+}
@@ -7,23 +7,37 @@ use std::{
     time::Duration,
 };
 
-use bee_api_types::responses::RentStructureResponse;
+use bee_api_types::responses::{ProtocolResponse, RentStructureResponse};
+use bee_block::{constant::PROTOCOL_VERSION, output::RentStructureBuilder, protocol::ProtocolParameters};
 #[cfg(not(target_family = "wasm"))]
 use {
-    std::collections::HashSet,
+    std::{collections::HashSet, path::Path},
     tokio::{runtime::Runtime, sync::broadcast::channel},
 };
 
 #[cfg(feature = "mqtt")]
 use crate::node_api::mqtt::{BrokerOptions, MqttEvent};
+#[cfg(not(target_family = "wasm"))]
+use crate::node_manager::{
+    cassette::{CassetteMode, CassettePlayer, CassetteRecorder},
+    transport::ReqwestTransport,
+};
 use crate::{
     client::Client,
-    constants::{DEFAULT_API_TIMEOUT, DEFAULT_REMOTE_POW_API_TIMEOUT, DEFAULT_TIPS_INTERVAL},
+    constants::{DEFAULT_API_TIMEOUT, DEFAULT_BELOW_MAX_DEPTH, DEFAULT_REMOTE_POW_API_TIMEOUT, DEFAULT_TIPS_INTERVAL},
     error::{Error, Result},
     node_manager::{
+        auth::SharedAuthProvider,
         builder::validate_url,
+        circuit_breaker::CircuitBreakerOptions,
+        limiter::NodeRateLimit,
         node::{Node, NodeAuth},
+        node_stats::NodeStatsMap,
+        quorum::{QuorumEndpoint, QuorumPolicy},
+        retry::RetryPolicy,
+        transport::{SharedTransport, TransportOptions},
     },
+    utils::hash_network,
 };
 
 /// Struct containing network and PoW related information
@@ -52,6 +66,84 @@ pub struct NetworkInfo {
     /// Rent structure of the protocol
     #[serde(rename = "rentStructure", default)]
     pub rent_structure: Option<RentStructureResponse>,
+    /// Token supply of the network
+    #[serde(rename = "tokenSupply", default)]
+    pub token_supply: Option<u64>,
+    /// Protocol version of the network
+    #[serde(rename = "protocolVersion", default)]
+    pub protocol_version: Option<u8>,
+}
+
+impl NetworkInfo {
+    /// Returns the protocol parameters made of the network, protocol version, bech32 HRP, min PoW score, rent
+    /// structure and token supply. Without a protocol version the one of this library is used. The below max depth
+    /// isn't part of the network info, so it's always the local default of the protocol, not the one of the network.
+    pub fn protocol_parameters(&self) -> Result<ProtocolParameters> {
+        let network_name = self
+            .network
+            .clone()
+            .ok_or(Error::MissingParameter("Missing network."))?;
+        // The network could be the part of the name that was configured to select the nodes
+        if self
+            .network_id
+            .is_some_and(|network_id| hash_network(&network_name).ok() != Some(network_id))
+        {
+            return Err(Error::InvalidProtocolParameters(format!(
+                "network {network_name} doesn't match the network ID"
+            )));
+        }
+        let rent_structure = self
+            .rent_structure
+            .as_ref()
+            .ok_or(Error::MissingParameter("Missing rent_structure."))?;
+
+        ProtocolParameters::new(
+            self.protocol_version.unwrap_or(PROTOCOL_VERSION),
+            network_name,
+            self.bech32_hrp
+                .clone()
+                .ok_or(Error::MissingParameter("Missing bech32_hrp."))?,
+            self.min_pow_score
+                .ok_or(Error::MissingParameter("Missing min_pow_score."))?,
+            DEFAULT_BELOW_MAX_DEPTH,
+            RentStructureBuilder::new()
+                .byte_cost(rent_structure.v_byte_cost)
+                .key_factor(rent_structure.v_byte_factor_key)
+                .data_factor(rent_structure.v_byte_factor_data)
+                .finish(),
+            self.token_supply
+                .ok_or(Error::MissingParameter("Missing token_supply."))?,
+        )
+        .map_err(|e| Error::InvalidProtocolParameters(e.to_string()))
+    }
+
+    /// Sets the fields of the protocol parameters.
+    pub(crate) fn set_protocol_parameters(&mut self, protocol_parameters: &ProtocolParameters) -> Result<()> {
+        let rent_structure = protocol_parameters.rent_structure();
+        self.network = Some(protocol_parameters.network_name().to_string());
+        self.network_id = Some(hash_network(protocol_parameters.network_name())?);
+        self.protocol_version = Some(protocol_parameters.version());
+        self.bech32_hrp = Some(protocol_parameters.bech32_hrp().to_string());
+        self.min_pow_score = Some(protocol_parameters.min_pow_score());
+        self.rent_structure = Some(RentStructureResponse {
+            v_byte_cost: rent_structure.v_byte_cost,
+            v_byte_factor_key: rent_structure.v_byte_factor_key,
+            v_byte_factor_data: rent_structure.v_byte_factor_data,
+        });
+        self.token_supply = Some(protocol_parameters.token_supply());
+        Ok(())
+    }
+
+    /// Sets the fields of the `protocol` object of the node info. Fields that can't be parsed are left empty.
+    pub(crate) fn set_protocol_response(&mut self, protocol: &ProtocolResponse) {
+        self.network = Some(protocol.network_name.clone());
+        self.network_id = hash_network(&protocol.network_name).ok();
+        self.protocol_version = Some(protocol.version);
+        self.bech32_hrp = Some(protocol.bech32_hrp.clone());
+        self.min_pow_score = Some(protocol.min_pow_score);
+        self.rent_structure = Some(protocol.rent_structure.clone());
+        self.token_supply = protocol.token_supply.parse().ok();
+    }
 }
 
 fn default_local_pow() -> bool {
@@ -121,6 +213,8 @@ impl Default for NetworkInfo {
             bech32_hrp: None,
             tips_interval: DEFAULT_TIPS_INTERVAL,
             rent_structure: None,
+            token_supply: None,
+            protocol_version: None,
         }
     }
 }
@@ -208,6 +302,15 @@ impl ClientBuilder {
         Ok(self)
     }
 
+    /// Sets the provider of the credentials for requests to the node with the URL, used instead of its [`NodeAuth`].
+    /// The node has to be added before, otherwise it's added as regular node.
+    pub fn with_auth_provider(mut self, url: &str, auth_provider: impl Into<SharedAuthProvider>) -> Result<Self> {
+        self.node_manager_builder = self
+            .node_manager_builder
+            .with_auth_provider(url, auth_provider.into())?;
+        Ok(self)
+    }
+
     /// Adds a list of IOTA nodes by their URLs.
     pub fn with_nodes(mut self, urls: &[&str]) -> Result<Self> {
         self.node_manager_builder = self.node_manager_builder.with_nodes(urls)?;
@@ -227,12 +330,42 @@ impl ClientBuilder {
         self
     }
 
-    /// Allows creating the client without nodes for offline address generation or signing
+    /// Allows creating the client without nodes for offline address generation or signing. No request is sent to a
+    /// node, APIs that need one return [`Error::OfflineMode`], so the protocol parameters have to be set with
+    /// [`ClientBuilder::with_protocol_parameters()`].
     pub fn with_offline_mode(mut self) -> Self {
         self.offline = true;
         self
     }
 
+    /// Sets the protocol parameters, in the format of the `protocol` object of the node info. Needed in offline mode,
+    /// otherwise they're updated from the nodes.
+    pub fn with_protocol_parameters(mut self, protocol: &ProtocolResponse) -> Result<Self> {
+        protocol
+            .token_supply
+            .parse::<u64>()
+            .map_err(|_| Error::InvalidProtocolParameters(format!("invalid token supply {}", protocol.token_supply)))?;
+        self.network_info.set_protocol_response(protocol);
+        Ok(self)
+    }
+
+    /// Sets typed protocol parameters, e.g. the ones of [`Client::get_protocol_parameters()`] that were serialized on a
+    /// machine with network access. The below max depth isn't kept, see [`NetworkInfo::protocol_parameters()`].
+    pub fn with_typed_protocol_parameters(mut self, protocol_parameters: ProtocolParameters) -> Result<Self> {
+        self.network_info.set_protocol_parameters(&protocol_parameters)?;
+        Ok(self)
+    }
+
+    /// Reads the protocol parameters from a JSON file, e.g. one with the `protocol` object of the node info that was
+    /// saved on a machine with network access.
+    #[cfg(not(target_family = "wasm"))]
+    pub fn with_protocol_parameters_from_file(self, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read(path)
+            .map_err(|e| Error::InvalidProtocolParameters(format!("can't read {}: {e}", path.display())))?;
+        self.with_protocol_parameters(&serde_json::from_slice(&json)?)
+    }
+
     /// Set if quorum should be used or not
     pub fn with_quorum(mut self, quorum: bool) -> Self {
         self.node_manager_builder = self.node_manager_builder.with_quorum(quorum);
@@ -252,6 +385,81 @@ impl ClientBuilder {
         self
     }
 
+    /// Sets the quorum policy of an endpoint class. If quorum is enabled, block requests are sent to multiple nodes by
+    /// default, the other endpoint classes only with an enabled policy.
+    pub fn with_quorum_policy(mut self, endpoint: QuorumEndpoint, policy: QuorumPolicy) -> Self {
+        self.node_manager_builder = self.node_manager_builder.with_quorum_policy(endpoint, policy);
+        self
+    }
+
+    /// Sets how requests that failed with a transient error are retried before the next node is tried.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.node_manager_builder = self.node_manager_builder.with_retry_policy(retry_policy);
+        self
+    }
+
+    /// Sets the transport that sends the requests to the nodes, instead of the default `reqwest` client. It isn't
+    /// serialized with the builder.
+    pub fn with_transport(mut self, transport: impl Into<SharedTransport>) -> Self {
+        self.node_manager_builder = self.node_manager_builder.with_transport(transport.into());
+        self
+    }
+
+    /// Sets proxies, additional root certificates, a client certificate for mutual TLS or whether invalid
+    /// certificates are accepted. They are ignored if a custom transport is set.
+    pub fn with_transport_options(mut self, transport_options: TransportOptions) -> Self {
+        self.node_manager_builder = self.node_manager_builder.with_transport_options(transport_options);
+        self
+    }
+
+    /// Records the requests to the nodes and their responses to the cassette file, appending to an existing one, or
+    /// replays them from it without sending any request. When recording, the requests are sent with the transport or
+    /// the transport options that were set before.
+    #[cfg(not(target_family = "wasm"))]
+    pub fn with_cassette(mut self, path: impl AsRef<Path>, mode: CassetteMode) -> Result<Self> {
+        let transport: SharedTransport = match mode {
+            CassetteMode::Record => match self.node_manager_builder.transport.take() {
+                Some(transport) => CassetteRecorder::with_transport(path, transport)?.into(),
+                None => CassetteRecorder::with_transport(
+                    path,
+                    ReqwestTransport::with_options(&self.node_manager_builder.transport_options)?,
+                )?
+                .into(),
+            },
+            CassetteMode::Replay => CassettePlayer::from_file(path)?.into(),
+        };
+        Ok(self.with_transport(transport))
+    }
+
+    /// Sets how many requests are sent at the same time at most, 100 by default. Further requests wait until a
+    /// request finished, every retry waits again.
+    pub fn with_max_concurrent_requests(mut self, max_concurrent_requests: usize) -> Self {
+        self.node_manager_builder = self
+            .node_manager_builder
+            .with_max_concurrent_requests(max_concurrent_requests);
+        self
+    }
+
+    /// Limits the requests to each node, e.g. to stay below the rate limit of public nodes. Requests over the limit
+    /// wait until the node can be used again instead of failing.
+    pub fn with_node_rate_limit(mut self, node_rate_limit: NodeRateLimit) -> Self {
+        self.node_manager_builder = self.node_manager_builder.with_node_rate_limit(node_rate_limit);
+        self
+    }
+
+    /// Caches up to `capacity` responses of blocks, included blocks, milestones and milestone UTXO changes, which
+    /// never change once they exist. Mutable data like output metadata and tips is always requested from the nodes.
+    pub fn with_response_cache(mut self, capacity: usize) -> Self {
+        self.node_manager_builder = self.node_manager_builder.with_response_cache(capacity);
+        self
+    }
+
+    /// Sets after how many consecutive failures nodes are skipped and for how long.
+    pub fn with_circuit_breaker_options(mut self, options: CircuitBreakerOptions) -> Self {
+        self.node_manager_builder = self.node_manager_builder.with_circuit_breaker_options(options);
+        self
+    }
+
     /// Selects the type of network to get default nodes for it, only "testnet" is supported at the moment.
     /// Nodes that don't belong to this network are ignored. The &str must match a part or all of the networkId returned
     /// in the node info from a node. For example, if the networkId is `"private-tangle"`, `"tangle"` can be used.
@@ -313,31 +521,50 @@ impl ClientBuilder {
             if self.node_manager_builder.nodes.is_empty() && self.node_manager_builder.primary_node.is_none() {
                 return Err(Error::MissingParameter("Node"));
             }
+        } else {
+            // Nothing to sync without network access
+            self.node_manager_builder = self.node_manager_builder.with_node_sync_disabled();
         }
         let network_info = Arc::new(RwLock::new(self.network_info));
-        let nodes = self
+        let nodes: std::collections::HashSet<Node> = self
             .node_manager_builder
             .nodes
             .iter()
             .map(|node| node.clone().into())
             .collect();
+        let node_stats = NodeStatsMap::new(&nodes, self.node_manager_builder.circuit_breaker.clone());
+        // Shared with the node syncing process too, so its requests are part of the metrics
+        let http_client = self.node_manager_builder.http_client()?;
+        // Shared with the node syncing process, so nodes that are added or removed later are synced too
+        let nodes = Arc::new(RwLock::new(nodes));
         #[cfg(target_family = "wasm")]
-        let (sync, network_info) = (Arc::new(RwLock::new(nodes)), network_info);
+        let (sync, network_info) = (Arc::new(RwLock::new(std::collections::HashSet::new())), network_info);
         #[cfg(not(target_family = "wasm"))]
         let (runtime, sync, sync_kill_sender, network_info) = if self.node_manager_builder.node_sync_enabled {
             let sync = Arc::new(RwLock::new(HashSet::new()));
             let sync_ = sync.clone();
             let network_info_ = network_info.clone();
+            let node_stats_ = node_stats.clone();
+            let nodes_ = nodes.clone();
+            let http_client = http_client.clone();
             let (sync_kill_sender, sync_kill_receiver) = channel(1);
             let runtime = std::thread::spawn(move || {
                 let runtime = Runtime::new().expect("failed to create Tokio runtime");
-                runtime.block_on(Client::sync_nodes(&sync_, &nodes, &network_info_));
+                runtime.block_on(Client::sync_nodes(
+                    &http_client,
+                    &sync_,
+                    &nodes_,
+                    &network_info_,
+                    &node_stats_,
+                ));
                 Client::start_sync_process(
                     &runtime,
+                    http_client,
                     sync_,
-                    nodes,
+                    nodes_,
                     self.node_manager_builder.node_sync_interval,
                     network_info_,
+                    node_stats_,
                     sync_kill_receiver,
                 );
                 runtime
@@ -346,13 +573,15 @@ impl ClientBuilder {
             .expect("failed to init node syncing process");
             (Some(Arc::new(runtime)), sync, Some(sync_kill_sender), network_info)
         } else {
-            (None, Arc::new(RwLock::new(nodes)), None, network_info)
+            (None, Arc::new(RwLock::new(HashSet::new())), None, network_info)
         };
 
         #[cfg(feature = "mqtt")]
         let (mqtt_event_tx, mqtt_event_rx) = tokio::sync::watch::channel(MqttEvent::Connected);
         let client = Client {
-            node_manager: self.node_manager_builder.build(sync),
+            node_manager: self
+                .node_manager_builder
+                .build(http_client, nodes, sync, node_stats, self.offline),
             #[cfg(not(target_family = "wasm"))]
             runtime,
             #[cfg(not(target_family = "wasm"))]
@@ -22,6 +22,7 @@ use bee_block::{
         transaction::{TransactionEssence, TransactionId},
         Payload, TaggedDataPayload,
     },
+    protocol::ProtocolParameters,
     Block, BlockId,
 };
 use bee_pow::providers::{NonceProvider, NonceProviderBuilder};
@@ -37,7 +38,12 @@ use {
 };
 #[cfg(not(target_family = "wasm"))]
 use {
-    std::collections::HashMap,
+    crate::node_manager::{
+        capabilities::NodeCapabilities,
+        node_stats::NodeStatsMap,
+        sync_status::{expected_network, NodeNetwork, NodeSyncStatus},
+    },
+    bee_api_types::responses::RoutesResponse,
     tokio::{
         runtime::Runtime,
         sync::broadcast::{Receiver, Sender},
@@ -54,11 +60,20 @@ use crate::{
     },
     error::{Error, Result},
     node_api::{high_level::GetAddressBuilder, indexer::query_parameters::QueryParameter},
-    node_manager::node::{Node, NodeAuth},
+    node_manager::{
+        cache::{CachedResource, ResponseCacheStats},
+        circuit_breaker::CircuitStateChange,
+        http_client::HttpClient,
+        limiter::RequestQueueStats,
+        metrics::{Metrics, RequestObserver},
+        node::{Node, NodeAuth},
+        node_stats::NodeStats,
+        quorum::QuorumReport,
+    },
     secret::SecretManager,
     utils::{
-        bech32_to_hex, generate_mnemonic, hash_network, hex_public_key_to_bech32_address, hex_to_bech32,
-        is_address_valid, mnemonic_to_hex_seed, mnemonic_to_seed, parse_bech32_address,
+        bech32_to_hex, generate_mnemonic, hex_public_key_to_bech32_address, hex_to_bech32, is_address_valid,
+        mnemonic_to_hex_seed, mnemonic_to_seed, parse_bech32_address,
     },
 };
 
@@ -149,12 +164,15 @@ impl Client {
 
     /// Sync the node lists per node_sync_interval milliseconds
     #[cfg(not(target_family = "wasm"))]
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn start_sync_process(
         runtime: &Runtime,
+        http_client: HttpClient,
         sync: Arc<RwLock<HashSet<Node>>>,
-        nodes: HashSet<Node>,
+        nodes: Arc<RwLock<HashSet<Node>>>,
         node_sync_interval: Duration,
         network_info: Arc<RwLock<NetworkInfo>>,
+        node_stats: NodeStatsMap,
         mut kill: Receiver<()>,
     ) {
         runtime.spawn(async move {
@@ -164,7 +182,7 @@ impl Client {
                             // delay first since the first `sync_nodes` call is made by the builder
                             // to ensure the node list is filled before the client is used
                             sleep(node_sync_interval).await;
-                            Client::sync_nodes(&sync, &nodes, &network_info).await;
+                            Client::sync_nodes(&http_client, &sync, &nodes, &network_info, &node_stats).await;
                     } => {}
                     _ = kill.recv() => {}
                 }
@@ -174,89 +192,205 @@ impl Client {
 
     #[cfg(not(target_family = "wasm"))]
     pub(crate) async fn sync_nodes(
+        http_client: &HttpClient,
         sync: &Arc<RwLock<HashSet<Node>>>,
-        nodes: &HashSet<Node>,
+        nodes: &Arc<RwLock<HashSet<Node>>>,
         network_info: &Arc<RwLock<NetworkInfo>>,
+        node_stats: &NodeStatsMap,
     ) {
         log::debug!("sync_nodes");
-        let mut synced_nodes = HashSet::new();
-        let mut network_nodes: HashMap<String, Vec<(NodeInfo, Node)>> = HashMap::new();
-
-        for node in nodes {
-            // Put the healthy node url into the network_nodes
-            if let Ok(info) = Client::get_node_info(node.url.as_ref(), None).await {
-                if info.status.is_healthy {
-                    match network_nodes.get_mut(&info.protocol.network_name) {
-                        Some(network_id_entry) => {
-                            network_id_entry.push((info, node.clone()));
-                        }
-                        None => match &network_info
-                            .read()
-                            .map_or(NetworkInfo::default().network, |info| info.network.clone())
-                        {
-                            Some(id) => {
-                                if info.protocol.network_name.contains(id) {
-                                    network_nodes
-                                        .insert(info.protocol.network_name.clone(), vec![(info, node.clone())]);
-                                }
-                            }
-                            None => {
-                                network_nodes.insert(info.protocol.network_name.clone(), vec![(info, node.clone())]);
-                            }
+        // The nodes can be changed while they're synced
+        let nodes = match nodes.read() {
+            Ok(nodes) => nodes.clone(),
+            Err(_) => return,
+        };
+        let mut node_infos = Vec::new();
+        for node in &nodes {
+            let start = std::time::Instant::now();
+            let info = Client::get_node_info_of(http_client, node.clone()).await;
+            node_stats.record_request(&node.url, start.elapsed(), &info);
+            match info {
+                Ok(info) => {
+                    // Nodes that don't list their routes are assumed to have all plugins. Only the node info request
+                    // counts for the stats, it already tells whether the node is reachable.
+                    let routes = Client::get_routes_of(http_client, node.clone()).await;
+                    node_stats.record_capabilities(
+                        &node.url,
+                        NodeCapabilities {
+                            routes: routes.ok().map(|routes| routes.routes),
+                            features: info.features.clone(),
                         },
-                    }
-                } else {
-                    log::debug!("{} is not healthy: {:?}", node.url, info);
+                    );
+                    node_infos.push((node, NodeNetwork::from(&info.protocol), info));
+                }
+                Err(_) => {
+                    log::error!("Couldn't get the node info from {}", node.url);
+                    node_stats.record_sync_status(&node.url, NodeSyncStatus::Unreachable);
                 }
-            } else {
-                log::error!("Couldn't get the node info from {}", node.url);
             }
         }
 
-        // Get network_id with the most nodes
-        let mut most_nodes = ("network_id", 0);
-        for (network_id, node) in &network_nodes {
-            if node.len() > most_nodes.1 {
-                most_nodes.0 = network_id;
-                most_nodes.1 = node.len();
-            }
+        // The sync lag of a node is relative to the most synced node of the same network
+        for (node, network, info) in &node_infos {
+            let confirmed_index = info.status.confirmed_milestone.index;
+            let latest_index = node_infos
+                .iter()
+                .filter(|(_, other_network, _)| other_network.network_name == network.network_name)
+                .map(|(.., info)| info.status.confirmed_milestone.index)
+                .max()
+                .unwrap_or(confirmed_index);
+            node_stats.record_sync(&node.url, info.status.is_healthy, Some(latest_index - confirmed_index));
         }
 
-        if let Some(nodes) = network_nodes.get(most_nodes.0) {
-            let pow_feature = String::from("pow");
-
-            for (info, node_url) in nodes.iter() {
-                if let Ok(mut client_network_info) = network_info.write() {
-                    client_network_info.network_id = hash_network(&info.protocol.network_name).ok();
-                    client_network_info.min_pow_score = Some(info.protocol.min_pow_score);
-                    client_network_info.bech32_hrp = Some(info.protocol.bech32_hrp.clone());
-                    client_network_info.rent_structure = Some(info.protocol.rent_structure.clone());
-                    if !client_network_info.local_pow {
-                        if info.features.contains(&pow_feature) {
-                            synced_nodes.insert(node_url.clone());
-                        }
-                    } else {
-                        synced_nodes.insert(node_url.clone());
-                    }
+        // The network of the client is pinned, the nodes can't switch it
+        let (configured_network, pinned_network, local_pow) = match network_info.read() {
+            Ok(info) => (
+                info.network.clone(),
+                info.protocol_parameters().ok().as_ref().map(NodeNetwork::from),
+                info.local_pow,
+            ),
+            Err(_) => return,
+        };
+        let expected = expected_network(
+            node_infos
+                .iter()
+                .filter(|(.., info)| info.status.is_healthy)
+                .map(|(_, network, _)| network),
+            configured_network.as_deref(),
+            pinned_network,
+        );
+
+        let mut synced_nodes = HashSet::new();
+        let mut expected_info = None;
+        for (node, network, info) in &node_infos {
+            let status = if expected.as_ref() != Some(network) {
+                log::warn!("quarantining {}, it's on {network:?} instead of {expected:?}", node.url);
+                NodeSyncStatus::WrongNetwork {
+                    network: network.clone(),
+                    expected: expected.clone(),
+                }
+            } else if !info.status.is_healthy {
+                log::debug!("{} is not healthy: {:?}", node.url, info);
+                NodeSyncStatus::Unhealthy
+            } else {
+                expected_info.get_or_insert(info);
+                if local_pow || info.features.iter().any(|feature| feature == "pow") {
+                    synced_nodes.insert((*node).clone());
+                    NodeSyncStatus::Synced
+                } else {
+                    NodeSyncStatus::NoRemotePow
                 }
+            };
+            node_stats.record_sync_status(&node.url, status);
+        }
+
+        // Only nodes on the expected network update the network info
+        if let Some(info) = expected_info {
+            if let Ok(mut client_network_info) = network_info.write() {
+                client_network_info.set_protocol_response(&info.protocol);
             }
         }
 
-        // Update the sync list
+        // Update the sync list, nodes that were added in the meantime are kept until the next sync checks them
         if let Ok(mut sync) = sync.write() {
+            let added_nodes = sync
+                .iter()
+                .filter(|node| !nodes.iter().any(|n| n.url == node.url))
+                .cloned()
+                .collect::<Vec<_>>();
             *sync = synced_nodes;
+            sync.extend(added_nodes);
         }
     }
 
     /// Get a node candidate from the synced node pool.
     pub async fn get_node(&self) -> Result<Node> {
-        if let Some(primary_node) = &self.node_manager.primary_node {
-            return Ok(primary_node.clone());
+        if let Some(primary_node) = self
+            .node_manager
+            .primary_node
+            .read()
+            .map_err(|_| Error::PoisonError)?
+            .clone()
+        {
+            return Ok(primary_node);
         }
-        let pool = self.node_manager.nodes.clone();
+        let mut pool = self
+            .node_manager
+            .nodes
+            .read()
+            .map_err(|_| Error::PoisonError)?
+            .iter()
+            .cloned()
+            .collect::<Vec<_>>();
+        self.node_manager.node_stats.sort_by_score(&mut pool);
         pool.into_iter().next().ok_or(Error::SyncedNodePoolEmpty)
     }
 
+    /// Returns the latency, error rate and sync lag of the nodes and the resulting scores, best node first. Nodes
+    /// with a higher score are used first for requests, only the primary nodes and permanodes are always preferred.
+    pub fn node_stats(&self) -> Result<Vec<NodeStats>> {
+        self.node_manager.node_stats.snapshot()
+    }
+
+    /// Registers a callback that is called when the circuit breaker of a node opens, allows a probe request or closes
+    /// again.
+    pub fn on_circuit_state_change<C: Fn(&CircuitStateChange) + Send + Sync + 'static>(
+        &self,
+        callback: C,
+    ) -> Result<()> {
+        self.node_manager
+            .node_stats
+            .add_circuit_state_listener(Arc::new(callback))
+    }
+
+    /// Registers a callback that is called with the outcome of every request that was sent to multiple nodes for
+    /// quorum.
+    pub fn on_quorum_report<C: Fn(&QuorumReport) + Send + Sync + 'static>(&self, callback: C) -> Result<()> {
+        self.node_manager.add_quorum_report_listener(Arc::new(callback))
+    }
+
+    /// Returns the counters and latencies of the HTTP requests per node and per route, including retries and the
+    /// requests of the node syncing process.
+    pub fn metrics(&self) -> Result<Metrics> {
+        self.node_manager.http_client.monitor().metrics()
+    }
+
+    /// Registers an observer that is called before and after every HTTP request to a node.
+    pub fn add_request_observer(&self, observer: impl RequestObserver + 'static) -> Result<()> {
+        self.node_manager.http_client.monitor().add_observer(Arc::new(observer))
+    }
+
+    /// Returns how many requests wait for the concurrency limit or the rate limit of their node, and how many are being
+    /// sent.
+    pub fn request_queue_stats(&self) -> RequestQueueStats {
+        self.node_manager.http_client.limiter().stats()
+    }
+
+    /// Returns the capacity, size, hits and misses of the response cache, `None` if it isn't enabled.
+    pub fn response_cache_stats(&self) -> Result<Option<ResponseCacheStats>> {
+        self.node_manager
+            .response_cache
+            .as_ref()
+            .map(|cache| cache.stats())
+            .transpose()
+    }
+
+    /// Removes the cached responses of a resource, so it's requested from the nodes again.
+    pub fn invalidate_cached_response(&self, resource: CachedResource) -> Result<()> {
+        match &self.node_manager.response_cache {
+            Some(cache) => cache.invalidate(resource),
+            None => Ok(()),
+        }
+    }
+
+    /// Removes all cached responses, the hit and miss counters are kept.
+    pub fn clear_response_cache(&self) -> Result<()> {
+        match &self.node_manager.response_cache {
+            Some(cache) => cache.clear(),
+            None => Ok(()),
+        }
+    }
+
     /// Gets the miner to use based on the Pow setting
     pub async fn get_pow_provider(&self) -> impl NonceProvider {
         let local_pow: bool = self.get_local_pow().await;
@@ -276,23 +410,20 @@ impl Client {
     }
 
     /// Gets the network related information such as network_id and min_pow_score
-    /// and if it's the default one, sync it first and set the NetworkInfo.
+    /// and if it's the default one, sync it first and set the NetworkInfo. In offline mode the configured protocol
+    /// parameters are returned as they are.
     pub async fn get_network_info(&self) -> Result<NetworkInfo> {
         let not_synced = self.network_info.read().map_or(true, |info| info.network_id.is_none());
 
         // For WASM we don't have the node syncing process, which updates the network_info every 60 seconds, but the Pow
         // difficulty or the byte cost could change via a milestone, so we request the node info every time, so we don't
         // create invalid transactions/blocks
-        if not_synced || cfg!(target_family = "wasm") {
+        if (not_synced || cfg!(target_family = "wasm")) && !self.node_manager.offline {
             let info = self.get_info().await?.node_info;
-            let network_id = hash_network(&info.protocol.network_name).ok();
-            {
-                let mut client_network_info = self.network_info.write().map_err(|_| crate::Error::PoisonError)?;
-                client_network_info.bech32_hrp = Some(info.protocol.bech32_hrp);
-                client_network_info.min_pow_score = Some(info.protocol.min_pow_score);
-                client_network_info.network_id = network_id;
-                client_network_info.rent_structure = Some(info.protocol.rent_structure);
-            }
+            self.network_info
+                .write()
+                .map_err(|_| crate::Error::PoisonError)?
+                .set_protocol_response(&info.protocol);
         }
         let res = self
             .network_info
@@ -306,7 +437,7 @@ impl Client {
         let network_info = self.get_network_info().await?;
         network_info
             .network_id
-            .ok_or(Error::MissingParameter("Missing network id."))
+            .ok_or_else(|| self.missing_network_info("network_id", "Missing network id."))
     }
 
     /// returns the bech32_hrp
@@ -314,7 +445,7 @@ impl Client {
         self.get_network_info()
             .await?
             .bech32_hrp
-            .ok_or(Error::MissingParameter("Missing bech32_hrp."))
+            .ok_or_else(|| self.missing_network_info("bech32_hrp", "Missing bech32_hrp."))
     }
 
     /// returns the min pow score
@@ -322,7 +453,36 @@ impl Client {
         self.get_network_info()
             .await?
             .min_pow_score
-            .ok_or(Error::MissingParameter("Missing min_pow_score."))
+            .ok_or_else(|| self.missing_network_info("min_pow_score", "Missing min_pow_score."))
+    }
+
+    /// returns the token supply
+    pub async fn get_token_supply(&self) -> Result<u64> {
+        self.get_network_info()
+            .await?
+            .token_supply
+            .ok_or_else(|| self.missing_network_info("token_supply", "Missing token_supply."))
+    }
+
+    /// Returns the protocol parameters of the network, requesting them from a node if they weren't synced yet. See
+    /// [`NetworkInfo::protocol_parameters()`].
+    pub async fn get_protocol_parameters(&self) -> Result<ProtocolParameters> {
+        self.get_network_info()
+            .await?
+            .protocol_parameters()
+            .map_err(|e| match e {
+                Error::MissingParameter(message) => self.missing_network_info("protocol_parameters", message),
+                e => e,
+            })
+    }
+
+    // Offline the network info can't be requested from a node
+    fn missing_network_info(&self, name: &str, message: &'static str) -> Error {
+        if self.node_manager.offline {
+            Error::OfflineMode(name.to_string())
+        } else {
+            Error::MissingParameter(message)
+        }
     }
 
     /// returns the tips interval
@@ -345,7 +505,7 @@ impl Client {
             .get_network_info()
             .await?
             .rent_structure
-            .ok_or(Error::MissingParameter("Missing rent_structure."))?;
+            .ok_or_else(|| self.missing_network_info("rent_structure", "Missing rent_structure."))?;
 
         let rent_structure = RentStructureBuilder::new()
             .byte_cost(rent_structure.v_byte_cost)
@@ -372,18 +532,50 @@ impl Client {
             })
     }
 
-    /// returns the unsynced nodes.
+    /// returns the unsynced nodes. They're cloned, since the nodes can be changed while the client is used.
     #[cfg(not(target_family = "wasm"))]
-    pub async fn unsynced_nodes(&self) -> HashSet<&Node> {
-        self.node_manager.synced_nodes.read().map_or(HashSet::new(), |synced| {
-            self.node_manager
-                .nodes
+    pub async fn unsynced_nodes(&self) -> HashSet<Node> {
+        let synced_nodes = self.node_manager.pool_nodes().unwrap_or_default();
+        self.node_manager.nodes.read().map_or(HashSet::new(), |nodes| {
+            nodes
                 .iter()
-                .filter(|node| !synced.contains(node))
+                .filter(|node| !synced_nodes.contains(*node))
+                .cloned()
                 .collect()
         })
     }
 
+    /// Adds a node to the pool, or replaces the node with the same url. The node is used right away and checked by the
+    /// next node sync.
+    pub fn add_node(&self, url: &str, auth: Option<NodeAuth>) -> Result<()> {
+        let mut node = Node::from(crate::node_manager::builder::validate_url(Url::parse(url)?)?);
+        node.auth = auth;
+        self.node_manager.add_node(node)
+    }
+
+    /// Removes a node from the pool, or as primary node. Returns whether the node was used.
+    pub fn remove_node(&self, url: &str) -> Result<bool> {
+        self.node_manager.remove_node(&Url::parse(url)?)
+    }
+
+    /// Sets the node which will be tried first for all requests, instead of the current one.
+    pub fn set_primary_node(&self, url: &str, auth: Option<NodeAuth>) -> Result<()> {
+        let mut node = Node::from(crate::node_manager::builder::validate_url(Url::parse(url)?)?);
+        node.auth = auth;
+        self.node_manager.set_primary_node(node)
+    }
+
+    /// Stops sending requests to a node of the pool, or to the primary node, until it's enabled again. Returns
+    /// whether the node was found.
+    pub fn disable_node(&self, url: &str) -> Result<bool> {
+        self.node_manager.set_node_disabled(&Url::parse(url)?, true)
+    }
+
+    /// Sends requests to a disabled node again. Returns whether the node was found.
+    pub fn enable_node(&self, url: &str) -> Result<bool> {
+        self.node_manager.set_node_disabled(&Url::parse(url)?, false)
+    }
+
     ///////////////////////////////////////////////////////////////////////
     // MQTT API
     //////////////////////////////////////////////////////////////////////
@@ -420,8 +612,21 @@ impl Client {
     // Node core API
     //////////////////////////////////////////////////////////////////////
 
-    /// GET /api/core/v2/info endpoint
+    /// GET /api/core/v2/info endpoint. The request is sent with the default transport, without the proxy, TLS options
+    /// or custom transport of a client, see [`Client::get_node_info_from()`].
     pub async fn get_node_info(url: &str, auth: Option<NodeAuth>) -> Result<NodeInfo> {
+        Self::get_node_info_of(&HttpClient::new(), Self::node_with_auth(url, auth)?).await
+    }
+
+    /// GET /api/core/v2/info endpoint of any node, sent with the transport of the client.
+    pub async fn get_node_info_from(&self, url: &str, auth: Option<NodeAuth>) -> Result<NodeInfo> {
+        if self.node_manager.offline {
+            return Err(Error::OfflineMode("api/core/v2/info".to_string()));
+        }
+        Self::get_node_info_of(&self.node_manager.http_client, Self::node_with_auth(url, auth)?).await
+    }
+
+    fn node_with_auth(url: &str, auth: Option<NodeAuth>) -> Result<Node> {
         let mut url = crate::node_manager::builder::validate_url(Url::parse(url)?)?;
         if let Some(auth) = &auth {
             if let Some((name, password)) = &auth.basic_auth_name_pwd {
@@ -431,23 +636,25 @@ impl Client {
                     .map_err(|_| crate::Error::UrlAuthError("password".to_string()))?;
             }
         }
-        let path = "api/core/v2/info";
-        url.set_path(path);
-
-        let resp: NodeInfo = crate::node_manager::http_client::HttpClient::new()
-            .get(
-                Node {
-                    url,
-                    auth,
-                    disabled: false,
-                },
-                DEFAULT_API_TIMEOUT,
-            )
-            .await?
-            .into_json()
-            .await?;
+        Ok(Node {
+            url,
+            auth,
+            disabled: false,
+            auth_provider: None,
+        })
+    }
+
+    // Also used by the node syncing process, so the auth provider of the node is used
+    pub(crate) async fn get_node_info_of(http_client: &HttpClient, mut node: Node) -> Result<NodeInfo> {
+        node.url.set_path("api/core/v2/info");
+        http_client.get(node, DEFAULT_API_TIMEOUT).await?.into_json().await
+    }
 
-        Ok(resp)
+    // The routes of a node for the node syncing process
+    #[cfg(not(target_family = "wasm"))]
+    pub(crate) async fn get_routes_of(http_client: &HttpClient, mut node: Node) -> Result<RoutesResponse> {
+        node.url.set_path("api/routes");
+        http_client.get(node, DEFAULT_API_TIMEOUT).await?.into_json().await
     }
 
     /// GET /api/indexer/v1/outputs/basic{query} endpoint
@@ -758,17 +965,24 @@ impl Client {
         Ok((block_id, block))
     }
 
+    /// Returns the local time, without checking it with a node. Only meant for offline clients, see
+    /// [`Client::get_time_checked()`].
+    pub fn get_time_unchecked(&self) -> u32 {
+        #[cfg(all(target_arch = "wasm32", not(target_os = "wasi")))]
+        let now = instant::SystemTime::now().duration_since(instant::SystemTime::UNIX_EPOCH);
+        #[cfg(not(all(target_arch = "wasm32", not(target_os = "wasi"))))]
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH);
+
+        now.expect("time went backwards").as_secs() as u32
+    }
+
     /// Returns the local time checked with the timestamp of the latest milestone, if the difference is larger than 5
     /// minutes an error is returned to prevent locking outputs by accident for a wrong time.
     pub async fn get_time_checked(&self) -> Result<u32> {
-        let current_time = {
-            #[cfg(all(target_arch = "wasm32", not(target_os = "wasi")))]
-            let now = instant::SystemTime::now().duration_since(instant::SystemTime::UNIX_EPOCH);
-            #[cfg(not(all(target_arch = "wasm32", not(target_os = "wasi"))))]
-            let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH);
-
-            now.expect("time went backwards").as_secs() as u32
-        };
+        if self.node_manager.offline {
+            return Err(Error::OfflineMode("get_time_checked".to_string()));
+        }
+        let current_time = self.get_time_unchecked();
 
         let status_response = self.get_info().await?.node_info.status;
         let latest_ms_timestamp = status_response.latest_milestone.timestamp;
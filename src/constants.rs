@@ -17,8 +17,27 @@ pub(crate) const DEFAULT_TIPS_INTERVAL: u64 = 5;
 pub(crate) const NODE_SYNC_INTERVAL: Duration = Duration::from_secs(15);
 pub(crate) const DEFAULT_MIN_QUORUM_SIZE: usize = 3;
 pub(crate) const DEFAULT_QUORUM_THRESHOLD: usize = 66;
-#[cfg(not(target_family = "wasm"))]
-pub(crate) const MAX_PARALLEL_API_REQUESTS: usize = 100;
+/// Consecutive failed requests after which a node is skipped
+pub(crate) const DEFAULT_CIRCUIT_BREAKER_FAILURE_THRESHOLD: u32 = 3;
+/// Time a node is skipped after it failed the first time, it doubles for every failed probe
+pub(crate) const DEFAULT_CIRCUIT_BREAKER_COOLDOWN: Duration = Duration::from_secs(10);
+/// Upper limit of the growing cooldown
+pub(crate) const DEFAULT_CIRCUIT_BREAKER_MAX_COOLDOWN: Duration = Duration::from_secs(300);
+/// Attempts per node for requests that failed with a transient error
+pub(crate) const DEFAULT_RETRY_POLICY_MAX_ATTEMPTS: u32 = 3;
+/// Delay before the first retry, it doubles for every further retry
+pub(crate) const DEFAULT_RETRY_POLICY_INITIAL_BACKOFF: Duration = Duration::from_millis(200);
+/// Upper limit of the growing delay between retries
+pub(crate) const DEFAULT_RETRY_POLICY_MAX_BACKOFF: Duration = Duration::from_secs(5);
+/// Too many requests, bad gateway, service unavailable and gateway timeout
+pub(crate) const DEFAULT_RETRYABLE_STATUS_CODES: [u16; 4] = [429, 502, 503, 504];
+/// Requests that are sent at the same time at most, further ones wait in a queue
+pub(crate) const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 100;
+/// Below max depth of the protocol, used for the protocol parameters since neither the node info nor the network info
+/// contain it. It's a local default, not the value of the network
+pub(crate) const DEFAULT_BELOW_MAX_DEPTH: u8 = 15;
+/// Blocks whose metadata a past cone traversal requests at most
+pub(crate) const DEFAULT_PAST_CONE_MAX_BLOCKS: usize = 1000;
 /// Max allowed difference between the local time and latest milestone time, 5 minutes in seconds
 pub(crate) const FIVE_MINUTES_IN_SECONDS: u32 = 300;
 
@@ -9,7 +9,7 @@ use bee_block::{output::NativeTokens, semantic::ConflictReason};
 use packable::error::UnexpectedEOF;
 use serde::{ser::Serializer, Serialize};
 
-use crate::node_api::indexer::QueryParameter;
+use crate::{node_api::indexer::QueryParameter, node_manager::retry::RetryableError};
 
 /// Type alias of `Result` in iota-client
 pub type Result<T> = std::result::Result<T, Error>;
@@ -19,6 +19,9 @@ pub type Result<T> = std::result::Result<T, Error>;
 #[allow(clippy::large_enum_variant)]
 #[serde(tag = "type", content = "error")]
 pub enum Error {
+    /// Error of an auth provider, e.g. when no new token could be requested
+    #[error("auth error: {0}")]
+    AuthError(String),
     /// Blake2b256 Error
     #[error("{0}")]
     Blake2b256Error(&'static str),
@@ -30,6 +33,9 @@ pub enum Error {
     #[error("{0}")]
     #[serde(serialize_with = "display_string")]
     BlockError(#[from] bee_block::Error),
+    /// Error when recording or replaying a cassette
+    #[error("cassette error: {0}")]
+    CassetteError(String),
     /// The wallet account has enough funds, but split on too many outputs
     #[error("the wallet account has enough funds, but split on too many outputs: {0}, max. is 128, consolidate them")]
     ConsolidationRequired(usize),
@@ -37,6 +43,9 @@ pub enum Error {
     #[error("{0}")]
     #[serde(serialize_with = "display_string")]
     CryptoError(#[from] crypto::Error),
+    /// An inclusion proof can't be created or decoded
+    #[error("inclusion proof error: {0}")]
+    InclusionProofError(String),
     /// Address not found
     #[error("address: {0} not found in range: {1}")]
     InputAddressNotFound(String, String),
@@ -49,10 +58,22 @@ pub enum Error {
     /// Invalid mnemonic error
     #[error("invalid mnemonic {0}")]
     InvalidMnemonic(String),
+    /// Protocol parameters that can't be used
+    #[error("invalid protocol parameters: {0}")]
+    InvalidProtocolParameters(String),
+    /// Transport options that can't be applied
+    #[error("invalid transport options: {0}")]
+    InvalidTransportOptions(String),
     /// JSON error
     #[error("{0}")]
     #[serde(serialize_with = "display_string")]
     Json(#[from] serde_json::Error),
+    /// No node or permanode has the milestone, which the nodes already pruned
+    #[error("milestone {0} isn't available on any node or permanode")]
+    MilestoneGap(u32),
+    /// A milestone failed the local verification
+    #[error("{0}")]
+    MilestoneVerification(#[from] crate::api::MilestoneVerificationError),
     /// Missing input for utxo chain
     #[error("missing input: {0}")]
     MissingInput(String),
@@ -62,6 +83,14 @@ pub enum Error {
     /// No input with matching ed25519 address provided
     #[error("no input with matching ed25519 address provided")]
     MissingInputWithEd25519Address,
+    /// The network ID of a transaction isn't the one of the protocol parameters
+    #[error("the transaction is for network ID {found}, expected {expected}")]
+    NetworkIdMismatch {
+        /// The network ID of the protocol parameters
+        expected: u64,
+        /// The network ID of the transaction
+        found: u64,
+    },
     /// Error on API request
     #[error("node error: {0}")]
     NodeError(String),
@@ -88,12 +117,18 @@ pub enum Error {
     /// The wallet account doesn't have enough balance for an output with the remaining native tokens.
     #[error("the wallet account doesn't have enough balance for an output with the remaining native tokens.")]
     NoBalanceForNativeTokenRemainder,
+    /// The client is in offline mode, but the request needs a node
+    #[error("the client is in offline mode, `{0}` needs a node")]
+    OfflineMode(String),
     /// Output Error
     #[error("output error: {0}")]
     OutputError(&'static str),
     /// PlaceholderSecretManager can't be used for address generation or signing
     #[error("placeholderSecretManager can't be used for address generation or signing")]
     PlaceholderSecretManager,
+    /// None of the nodes has the plugin or feature a request needs
+    #[error("no node supports {0}")]
+    PluginNotAvailable(crate::node_manager::capabilities::NodeCapability),
     /// Rw lock failed.
     #[error("rw lock failed")]
     PoisonError,
@@ -161,9 +196,25 @@ pub enum Error {
         /// The timestamp of the latest milestone.
         milestone_timestamp: u32,
     },
+    /// The outputs have more tokens than exist in the network
+    #[error("outputs with an amount of {amount} exceed the token supply of {token_supply}")]
+    TokenSupplyExceeded {
+        /// The amount of the outputs
+        amount: u64,
+        /// The token supply of the protocol parameters
+        token_supply: u64,
+    },
     /// The semantic validation of a transaction failed.
     #[error("the semantic validation of a transaction failed with conflict reason: {} - {0:?}", *.0 as u8)]
     TransactionSemantic(ConflictReason),
+    /// Error of a custom transport without a response from the node
+    #[error("transport error: {message}")]
+    TransportError {
+        /// The error message.
+        message: String,
+        /// The kind of the error, if it can be retried.
+        kind: Option<RetryableError>,
+    },
     /// Unexpected API response error
     #[error("unexpected API response")]
     UnexpectedApiResponse,
@@ -209,6 +260,14 @@ pub enum Error {
     #[error("ledger transport error")]
     LedgerMiscError,
 
+    //////////////////////////////////////////////////////////////////////
+    // Mock node
+    //////////////////////////////////////////////////////////////////////
+    /// Mock node error
+    #[cfg(feature = "mock-node")]
+    #[error("mock node error: {0}")]
+    MockNodeError(String),
+
     //////////////////////////////////////////////////////////////////////
     // MQTT
     //////////////////////////////////////////////////////////////////////
@@ -48,6 +48,8 @@ pub mod db;
 pub mod error;
 #[cfg(feature = "message_interface")]
 pub mod message_interface;
+#[cfg(feature = "mock-node")]
+pub mod mock_node;
 pub mod node_api;
 pub mod node_manager;
 pub mod secret;
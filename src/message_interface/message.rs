@@ -126,6 +126,8 @@ pub enum Message {
     GetBech32Hrp,
     /// Returns the min pow score
     GetMinPowScore,
+    /// Returns the protocol parameters
+    GetProtocolParameters,
     /// Returns the tips interval
     GetTipsInterval,
     /// Returns if local pow should be used or not
@@ -194,6 +196,32 @@ pub enum Message {
     GetInfo,
     /// Get peers
     GetPeers,
+    /// Get a peer of a node by its ID
+    GetPeer {
+        /// Url of the node
+        url: String,
+        /// Peer ID
+        #[serde(rename = "peerId")]
+        peer_id: String,
+    },
+    /// Add a peer to a node
+    AddPeer {
+        /// Url of the node
+        url: String,
+        /// Multiaddress containing the peer ID
+        #[serde(rename = "multiAddress")]
+        multi_address: String,
+        /// Alias
+        alias: Option<String>,
+    },
+    /// Remove a peer from a node
+    RemovePeer {
+        /// Url of the node
+        url: String,
+        /// Peer ID
+        #[serde(rename = "peerId")]
+        peer_id: String,
+    },
     /// Get tips
     GetTips,
     /// Post block (JSON)
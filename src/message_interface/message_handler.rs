@@ -294,6 +294,9 @@ impl ClientMessageHandler {
             Message::GetNetworkId => Ok(Response::NetworkId(self.client.get_network_id().await?)),
             Message::GetBech32Hrp => Ok(Response::Bech32Hrp(self.client.get_bech32_hrp().await?)),
             Message::GetMinPowScore => Ok(Response::MinPowScore(self.client.get_min_pow_score().await?)),
+            Message::GetProtocolParameters => Ok(Response::ProtocolParameters(
+                self.client.get_protocol_parameters().await?,
+            )),
             Message::GetTipsInterval => Ok(Response::TipsInterval(self.client.get_tips_interval().await)),
             Message::GetLocalPow => Ok(Response::LocalPow(self.client.get_local_pow().await)),
             Message::GetFallbackToLocalPow => Ok(Response::FallbackToLocalPow(
@@ -371,12 +374,26 @@ impl ClientMessageHandler {
             }
             #[cfg(not(target_family = "wasm"))]
             Message::UnsyncedNodes => Ok(Response::UnsyncedNodes(
-                self.client.unsynced_nodes().await.into_iter().cloned().collect(),
+                self.client.unsynced_nodes().await.into_iter().collect(),
             )),
             Message::GetHealth { url } => Ok(Response::Health(self.client.get_health(&url).await?)),
-            Message::GetNodeInfo { url, auth } => Ok(Response::NodeInfo(Client::get_node_info(&url, auth).await?)),
+            Message::GetNodeInfo { url, auth } => {
+                Ok(Response::NodeInfo(self.client.get_node_info_from(&url, auth).await?))
+            }
             Message::GetInfo => Ok(Response::Info(self.client.get_info().await?)),
             Message::GetPeers => Ok(Response::Peers(self.client.get_peers().await?)),
+            Message::GetPeer { url, peer_id } => Ok(Response::Peer(self.client.get_peer(&url, &peer_id).await?)),
+            Message::AddPeer {
+                url,
+                multi_address,
+                alias,
+            } => Ok(Response::Peer(
+                self.client.add_peer(&url, &multi_address, alias.as_deref()).await?,
+            )),
+            Message::RemovePeer { url, peer_id } => {
+                self.client.remove_peer(&url, &peer_id).await?;
+                Ok(Response::Ok)
+            }
             Message::GetTips => Ok(Response::Tips(self.client.get_tips().await?)),
             Message::PostBlockRaw { block_bytes } => Ok(Response::BlockId(
                 self.client
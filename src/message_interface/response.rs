@@ -19,6 +19,7 @@ use bee_block::{
         dto::{MilestonePayloadDto, PayloadDto},
         transaction::TransactionId,
     },
+    protocol::ProtocolParameters,
     BlockDto, BlockId,
 };
 use serde::Serialize;
@@ -56,6 +57,9 @@ pub enum Response {
     /// - [`GetMinPowScore`](crate::message_interface::Message::GetMinPowScore)
     MinPowScore(u32),
     /// Response for:
+    /// - [`GetProtocolParameters`](crate::message_interface::Message::GetProtocolParameters)
+    ProtocolParameters(ProtocolParameters),
+    /// Response for:
     /// - [`GetTipsInterval`](crate::message_interface::Message::GetTipsInterval)
     TipsInterval(u64),
     /// Response for:
@@ -91,6 +95,10 @@ pub enum Response {
     /// - [`GetPeers`](crate::message_interface::Message::GetPeers)
     Peers(Vec<PeerDto>),
     /// Response for:
+    /// - [`GetPeer`](crate::message_interface::Message::GetPeer)
+    /// - [`AddPeer`](crate::message_interface::Message::AddPeer)
+    Peer(PeerDto),
+    /// Response for:
     /// - [`GetTips`](crate::message_interface::Message::GetTips)
     Tips(Vec<BlockId>),
     /// Response for:
@@ -210,6 +218,7 @@ pub enum Response {
     Faucet(String),
     /// Response for:
     /// - [`StoreMnemonic`](crate::message_interface::Message::StoreMnemonic)
+    /// - [`RemovePeer`](crate::message_interface::Message::RemovePeer)
     Ok,
     /// Response for any method that returns an error.
     Error(Error),
@@ -0,0 +1,838 @@
+// Copyright 2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! In-memory UTXO ledger of the mock node
+
+use std::{collections::HashMap, str::FromStr};
+
+use bee_api_types::{
+    dtos::{LedgerInclusionStateDto, PeerDto},
+    responses::{
+        BaseTokenResponse, BlockMetadataResponse, ConfirmedMilestoneResponse, InfoResponse, LatestMilestoneResponse,
+        MetricsResponse, OutputMetadataResponse, OutputResponse, ProtocolResponse, StatusResponse, UtxoChangesResponse,
+    },
+};
+use bee_block::{
+    address::Address,
+    input::Input,
+    output::{dto::OutputDto, AliasId, FoundryId, NftId, Output, OutputId},
+    parent::Parents,
+    payload::{
+        milestone::{MilestoneEssence, MilestoneId, MilestoneIndex, MilestoneOptions, MilestonePayload},
+        transaction::{TransactionEssence, TransactionId, TransactionPayload},
+        Payload,
+    },
+    semantic::{semantic_validation, ConflictReason, ValidationContext},
+    signature::{Ed25519Signature, Signature},
+    Block, BlockBuilder, BlockId,
+};
+use crypto::{
+    hashes::{blake2b::Blake2b256, Digest},
+    signatures::ed25519,
+};
+
+use crate::{
+    api::{merkle_root, InclusionProof, MerkleAuditPath, MilestoneKeyRange, MilestoneVerifier},
+    node_api::indexer::responses::OutputIdsResponse,
+    Error, Result,
+};
+
+/// Amount of output ids returned per indexer page if no page size is requested.
+const DEFAULT_PAGE_SIZE: usize = 1000;
+
+/// Kind of outputs an indexer route returns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum OutputKind {
+    Basic,
+    Alias,
+    Foundry,
+    Nft,
+}
+
+struct BlockEntry {
+    block: Block,
+    metadata: BlockMetadataResponse,
+}
+
+struct OutputEntry {
+    output: Output,
+    metadata: OutputMetadataResponse,
+}
+
+struct MilestoneEntry {
+    payload: MilestonePayload,
+    created_outputs: Vec<OutputId>,
+    consumed_outputs: Vec<OutputId>,
+}
+
+/// The ledger state the mock node serves its routes from.
+pub(crate) struct MockLedger {
+    pub(crate) protocol: ProtocolResponse,
+    // Routes of the plugins besides the core API, e.g. `indexer/v1`
+    pub(crate) plugins: Vec<String>,
+    auto_confirm: bool,
+    milestone_key: ed25519::SecretKey,
+    blocks: HashMap<BlockId, BlockEntry>,
+    tips: Vec<BlockId>,
+    tips_with_parents: bool,
+    pending_blocks: Vec<BlockId>,
+    outputs: HashMap<OutputId, OutputEntry>,
+    // Output ids in creation order, so the indexer pagination is stable
+    output_order: Vec<OutputId>,
+    milestones: Vec<MilestoneEntry>,
+    // Milestones up to this index aren't served anymore
+    pruning_index: u32,
+    included_blocks: HashMap<TransactionId, BlockId>,
+    peers: Vec<PeerDto>,
+}
+
+impl MockLedger {
+    /// Creates a ledger with a genesis milestone that books the provided outputs.
+    pub(crate) fn new(protocol: ProtocolResponse, auto_confirm: bool, genesis_outputs: Vec<Output>) -> Result<Self> {
+        let genesis = BlockBuilder::<u64>::new(Parents::new(vec![BlockId::null()])?)
+            .with_nonce_provider(0, 0)
+            .finish()?;
+        let genesis_id = genesis.id();
+
+        let mut ledger = Self {
+            protocol,
+            auto_confirm,
+            milestone_key: ed25519::SecretKey::generate()?,
+            blocks: HashMap::new(),
+            tips: vec![genesis_id],
+            tips_with_parents: false,
+            pending_blocks: vec![genesis_id],
+            outputs: HashMap::new(),
+            output_order: Vec::new(),
+            milestones: Vec::new(),
+            pruning_index: 0,
+            included_blocks: HashMap::new(),
+            peers: Vec::new(),
+            plugins: Vec::new(),
+        };
+        ledger.blocks.insert(
+            genesis_id,
+            BlockEntry {
+                metadata: unreferenced_metadata(&genesis),
+                block: genesis,
+            },
+        );
+        ledger.confirm_with_outputs(genesis_outputs)?;
+
+        Ok(ledger)
+    }
+
+    /// Returns the node info of the mock node.
+    pub(crate) fn info(&self) -> InfoResponse {
+        let (index, timestamp, milestone_id) = self.milestones.last().map_or((0, 0, MilestoneId::null()), |entry| {
+            (
+                *entry.payload.essence().index(),
+                entry.payload.essence().timestamp(),
+                entry.payload.id(),
+            )
+        });
+
+        InfoResponse {
+            name: "mock-node".to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            status: StatusResponse {
+                is_healthy: true,
+                latest_milestone: LatestMilestoneResponse {
+                    index,
+                    timestamp,
+                    milestone_id: milestone_id.to_string(),
+                },
+                confirmed_milestone: ConfirmedMilestoneResponse {
+                    index,
+                    timestamp,
+                    milestone_id: milestone_id.to_string(),
+                },
+                pruning_index: self.pruning_index,
+            },
+            supported_protocol_versions: vec![self.protocol.version],
+            protocol: self.protocol.clone(),
+            pending_protocol_parameters: Vec::new(),
+            base_token: BaseTokenResponse {
+                name: "Shimmer".to_string(),
+                ticker_symbol: "SMR".to_string(),
+                unit: "SMR".to_string(),
+                subunit: Some("glow".to_string()),
+                decimals: 6,
+                use_metric_prefix: false,
+            },
+            metrics: MetricsResponse {
+                blocks_per_second: 0.0,
+                referenced_blocks_per_second: 0.0,
+                referenced_rate: 0.0,
+            },
+            features: vec!["pow".to_string()],
+        }
+    }
+
+    /// Returns the latest confirmed milestone index.
+    pub(crate) fn ledger_index(&self) -> u32 {
+        self.milestones.len() as u32
+    }
+
+    /// Returns the public key the milestones are signed with.
+    pub(crate) fn milestone_public_key(&self) -> [u8; 32] {
+        self.milestone_key.public_key().to_bytes()
+    }
+
+    pub(crate) fn tips(&self) -> Vec<BlockId> {
+        self.tips
+            .iter()
+            .rev()
+            .take(*Parents::COUNT_RANGE.end() as usize)
+            .copied()
+            .collect()
+    }
+
+    /// Returns the tips for a new block, every other request also the parents of the tips.
+    ///
+    /// The block builder takes a nonce of 0 as cancelled PoW and retries with new tips, without another block being
+    /// posted these would otherwise be the same again.
+    pub(crate) fn request_tips(&mut self) -> Vec<BlockId> {
+        let mut tips = self.tips();
+        self.tips_with_parents = !self.tips_with_parents;
+        if !self.tips_with_parents {
+            let parents = tips
+                .iter()
+                .filter_map(|tip| self.blocks.get(tip))
+                .flat_map(|entry| entry.block.parents().iter().copied())
+                .filter(|parent| self.blocks.contains_key(parent))
+                .collect::<Vec<_>>();
+            for parent in parents {
+                if tips.len() < *Parents::COUNT_RANGE.end() as usize && !tips.contains(&parent) {
+                    tips.push(parent);
+                }
+            }
+        }
+        tips
+    }
+
+    /// Stores a block and confirms it right away if auto confirmation is enabled.
+    pub(crate) fn insert_block(&mut self, block: Block) -> Result<BlockId> {
+        let block_id = block.id();
+        if self.blocks.contains_key(&block_id) {
+            return Ok(block_id);
+        }
+        if let Some(Payload::Milestone(_)) = block.payload() {
+            return Err(Error::MockNodeError(
+                "milestones can't be posted to the mock node".to_string(),
+            ));
+        }
+
+        self.tips
+            .retain(|tip| !block.parents().iter().any(|parent| parent == tip));
+        self.tips.push(block_id);
+        self.pending_blocks.push(block_id);
+        self.blocks.insert(
+            block_id,
+            BlockEntry {
+                metadata: unreferenced_metadata(&block),
+                block,
+            },
+        );
+
+        if self.auto_confirm {
+            self.confirm()?;
+        }
+
+        Ok(block_id)
+    }
+
+    /// Books a basic output for the address with a new milestone, like a faucet would.
+    pub(crate) fn fund(&mut self, output: Output) -> Result<OutputId> {
+        let index = self.ledger_index() + 1;
+        self.confirm_with_outputs(vec![output])?;
+        Ok(OutputId::new(synthetic_transaction_id(index), 0)?)
+    }
+
+    /// Issues a milestone that references all pending blocks and applies their transactions to the ledger.
+    pub(crate) fn confirm(&mut self) -> Result<u32> {
+        self.confirm_with_outputs(Vec::new())
+    }
+
+    fn confirm_with_outputs(&mut self, synthetic_outputs: Vec<Output>) -> Result<u32> {
+        let index = self.ledger_index() + 1;
+        let timestamp = unix_timestamp();
+        let mut created_outputs = Vec::new();
+        let mut consumed_outputs = Vec::new();
+        let mut referenced_blocks = Vec::new();
+        let mut applied_blocks = Vec::new();
+
+        // Outputs that aren't created by a transaction, like the genesis or faucet outputs
+        let transaction_id = synthetic_transaction_id(index);
+        for (output_index, output) in synthetic_outputs.into_iter().enumerate() {
+            let output_id = OutputId::new(transaction_id, output_index as u16)?;
+            self.book_output(output_id, output, BlockId::null(), index, timestamp);
+            created_outputs.push(output_id);
+        }
+
+        for (white_flag_index, block_id) in std::mem::take(&mut self.pending_blocks).into_iter().enumerate() {
+            let transaction = match self.blocks.get(&block_id).and_then(|entry| entry.block.payload()) {
+                Some(Payload::Transaction(transaction)) => Some(transaction.clone()),
+                _ => None,
+            };
+            let (inclusion_state, conflict_reason) = match transaction {
+                Some(transaction) => {
+                    let conflict = self.apply_transaction(
+                        block_id,
+                        &transaction,
+                        index,
+                        timestamp,
+                        &mut created_outputs,
+                        &mut consumed_outputs,
+                    );
+                    if conflict == ConflictReason::None {
+                        applied_blocks.push(block_id);
+                        (LedgerInclusionStateDto::Included, None)
+                    } else {
+                        (LedgerInclusionStateDto::Conflicting, Some(conflict as u8))
+                    }
+                }
+                None => (LedgerInclusionStateDto::NoTransaction, None),
+            };
+            if let Some(entry) = self.blocks.get_mut(&block_id) {
+                entry.metadata.referenced_by_milestone_index = Some(index);
+                entry.metadata.ledger_inclusion_state = Some(inclusion_state);
+                entry.metadata.conflict_reason = conflict_reason;
+                entry.metadata.white_flag_index = Some(white_flag_index as u32);
+                entry.metadata.should_promote = None;
+                entry.metadata.should_reattach = None;
+            }
+            referenced_blocks.push(block_id);
+        }
+
+        let previous_milestone_id = self
+            .milestones
+            .last()
+            .map_or_else(MilestoneId::null, |entry| entry.payload.id());
+        let parents = Parents::new(self.tips())?;
+        let essence = MilestoneEssence::new(
+            MilestoneIndex::new(index),
+            timestamp,
+            previous_milestone_id,
+            parents.clone(),
+            merkle_root(&referenced_blocks),
+            merkle_root(&applied_blocks),
+            Vec::new(),
+            MilestoneOptions::new(Vec::new())?,
+        )?;
+        let signature = self.milestone_key.sign(&essence.hash());
+        let payload = MilestonePayload::new(
+            essence,
+            vec![Signature::Ed25519(Ed25519Signature::new(
+                self.milestone_public_key(),
+                signature.to_bytes(),
+            ))],
+        )?;
+
+        let milestone_block = BlockBuilder::<u64>::new(parents)
+            .with_payload(Payload::Milestone(Box::new(payload.clone())))
+            .with_nonce_provider(0, 0)
+            .finish()?;
+        let milestone_block_id = milestone_block.id();
+        let mut metadata = unreferenced_metadata(&milestone_block);
+        metadata.milestone_index = Some(index);
+        metadata.referenced_by_milestone_index = Some(index);
+        metadata.ledger_inclusion_state = Some(LedgerInclusionStateDto::NoTransaction);
+        metadata.should_promote = None;
+        metadata.should_reattach = None;
+        self.blocks.insert(
+            milestone_block_id,
+            BlockEntry {
+                block: milestone_block,
+                metadata,
+            },
+        );
+        self.tips = vec![milestone_block_id];
+
+        self.milestones.push(MilestoneEntry {
+            payload,
+            created_outputs,
+            consumed_outputs,
+        });
+
+        Ok(index)
+    }
+
+    // Validates a transaction against the current ledger state and applies it if there is no conflict.
+    fn apply_transaction(
+        &mut self,
+        block_id: BlockId,
+        transaction: &TransactionPayload,
+        milestone_index: u32,
+        milestone_timestamp: u32,
+        created_outputs: &mut Vec<OutputId>,
+        consumed_outputs: &mut Vec<OutputId>,
+    ) -> ConflictReason {
+        let transaction_id = transaction.id();
+        let TransactionEssence::Regular(essence) = transaction.essence();
+
+        let mut inputs = Vec::new();
+        for input in essence.inputs() {
+            let output_id = match input {
+                Input::Utxo(input) => *input.output_id(),
+                Input::Treasury(_) => return ConflictReason::SemanticValidationFailed,
+            };
+            match self.outputs.get(&output_id) {
+                Some(entry) if entry.metadata.is_spent => return ConflictReason::InputUtxoAlreadySpent,
+                Some(entry) => inputs.push((output_id, entry.output.clone())),
+                None => return ConflictReason::InputUtxoNotFound,
+            }
+        }
+
+        let input_refs = inputs
+            .iter()
+            .map(|(output_id, output)| (*output_id, output))
+            .collect::<Vec<(OutputId, &Output)>>();
+        let context = ValidationContext::new(
+            &transaction_id,
+            essence,
+            inputs.iter().map(|(output_id, output)| (output_id, output)),
+            transaction.unlocks(),
+            milestone_timestamp,
+        );
+        let conflict = semantic_validation(context, &input_refs, transaction.unlocks())
+            .unwrap_or(ConflictReason::SemanticValidationFailed);
+        if conflict != ConflictReason::None {
+            return conflict;
+        }
+
+        for (output_id, _) in &inputs {
+            if let Some(entry) = self.outputs.get_mut(output_id) {
+                entry.metadata.is_spent = true;
+                entry.metadata.milestone_index_spent = Some(milestone_index);
+                entry.metadata.milestone_timestamp_spent = Some(milestone_timestamp);
+                entry.metadata.transaction_id_spent = Some(transaction_id.to_string());
+            }
+            consumed_outputs.push(*output_id);
+        }
+        for (output_index, output) in essence.outputs().iter().enumerate() {
+            // Safe to unwrap, the output count is bounded by the essence
+            let output_id = OutputId::new(transaction_id, output_index as u16).unwrap();
+            self.book_output(
+                output_id,
+                output.clone(),
+                block_id,
+                milestone_index,
+                milestone_timestamp,
+            );
+            created_outputs.push(output_id);
+        }
+        self.included_blocks.insert(transaction_id, block_id);
+
+        ConflictReason::None
+    }
+
+    fn book_output(
+        &mut self,
+        output_id: OutputId,
+        output: Output,
+        block_id: BlockId,
+        milestone_index: u32,
+        milestone_timestamp: u32,
+    ) {
+        self.outputs.insert(
+            output_id,
+            OutputEntry {
+                output,
+                metadata: OutputMetadataResponse {
+                    block_id: block_id.to_string(),
+                    transaction_id: output_id.transaction_id().to_string(),
+                    output_index: output_id.index(),
+                    is_spent: false,
+                    milestone_index_spent: None,
+                    milestone_timestamp_spent: None,
+                    transaction_id_spent: None,
+                    milestone_index_booked: milestone_index,
+                    milestone_timestamp_booked: milestone_timestamp,
+                    ledger_index: milestone_index,
+                },
+            },
+        );
+        self.output_order.push(output_id);
+    }
+
+    pub(crate) fn block(&self, block_id: &BlockId) -> Option<&Block> {
+        self.blocks.get(block_id).map(|entry| &entry.block)
+    }
+
+    pub(crate) fn block_metadata(&self, block_id: &BlockId) -> Option<&BlockMetadataResponse> {
+        self.blocks.get(block_id).map(|entry| &entry.metadata)
+    }
+
+    pub(crate) fn included_block(&self, transaction_id: &TransactionId) -> Option<&Block> {
+        self.included_blocks
+            .get(transaction_id)
+            .and_then(|block_id| self.block(block_id))
+    }
+
+    pub(crate) fn output(&self, output_id: &OutputId) -> Option<&Output> {
+        self.outputs.get(output_id).map(|entry| &entry.output)
+    }
+
+    pub(crate) fn output_metadata(&self, output_id: &OutputId) -> Option<OutputMetadataResponse> {
+        self.outputs.get(output_id).map(|entry| OutputMetadataResponse {
+            ledger_index: self.ledger_index(),
+            ..entry.metadata.clone()
+        })
+    }
+
+    pub(crate) fn output_response(&self, output_id: &OutputId) -> Option<OutputResponse> {
+        Some(OutputResponse {
+            metadata: self.output_metadata(output_id)?,
+            output: OutputDto::from(self.output(output_id)?),
+        })
+    }
+
+    pub(crate) fn milestone_by_index(&self, index: u32) -> Option<&MilestonePayload> {
+        self.milestone_entry(index).map(|entry| &entry.payload)
+    }
+
+    pub(crate) fn milestone_by_id(&self, milestone_id: &MilestoneId) -> Option<&MilestonePayload> {
+        self.milestones
+            .iter()
+            .skip(self.pruning_index as usize)
+            .map(|entry| &entry.payload)
+            .find(|payload| payload.id() == *milestone_id)
+    }
+
+    pub(crate) fn utxo_changes(&self, index: u32) -> Option<UtxoChangesResponse> {
+        self.milestone_entry(index).map(|entry| UtxoChangesResponse {
+            index,
+            created_outputs: entry.created_outputs.iter().map(ToString::to_string).collect(),
+            consumed_outputs: entry.consumed_outputs.iter().map(ToString::to_string).collect(),
+        })
+    }
+
+    pub(crate) fn peers(&self) -> &[PeerDto] {
+        &self.peers
+    }
+
+    pub(crate) fn peer(&self, peer_id: &str) -> Option<&PeerDto> {
+        self.peers.iter().find(|peer| peer.id == peer_id)
+    }
+
+    /// Adds the peer or replaces the one with the same ID, like a node does.
+    pub(crate) fn add_peer(&mut self, peer: PeerDto) {
+        self.peers.retain(|known| known.id != peer.id);
+        self.peers.push(peer);
+    }
+
+    pub(crate) fn remove_peer(&mut self, peer_id: &str) -> Option<PeerDto> {
+        let position = self.peers.iter().position(|peer| peer.id == peer_id)?;
+        Some(self.peers.remove(position))
+    }
+
+    /// Creates the proof of inclusion of a block like the proof-of-inclusion plugin, `None` for unknown blocks.
+    pub(crate) fn inclusion_proof(&self, block_id: &BlockId) -> Result<Option<InclusionProof>> {
+        let entry = match self.blocks.get(block_id) {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        let (index, milestone) = match entry.metadata.referenced_by_milestone_index {
+            Some(index) if entry.metadata.milestone_index.is_none() => (index, self.milestone_by_index(index)),
+            _ => {
+                return Err(Error::MockNodeError(format!(
+                    "block {block_id} isn't referenced by a milestone"
+                )));
+            }
+        };
+        let milestone = match milestone {
+            Some(milestone) => milestone.clone(),
+            None => return Ok(None),
+        };
+
+        // The referenced blocks of the milestone in white flag order
+        let mut referenced_blocks = self
+            .blocks
+            .iter()
+            .filter_map(|(block_id, entry)| {
+                let white_flag_index = entry.metadata.white_flag_index?;
+                (entry.metadata.referenced_by_milestone_index == Some(index)).then_some((white_flag_index, *block_id))
+            })
+            .collect::<Vec<_>>();
+        referenced_blocks.sort_unstable();
+        let referenced_blocks = referenced_blocks
+            .into_iter()
+            .map(|(_, block_id)| block_id)
+            .collect::<Vec<_>>();
+        let position = referenced_blocks
+            .iter()
+            .position(|referenced| referenced == block_id)
+            .unwrap_or_default();
+
+        Ok(
+            MerkleAuditPath::new(&referenced_blocks, position).map(|audit_path| InclusionProof {
+                milestone,
+                block: entry.block.clone(),
+                audit_path,
+            }),
+        )
+    }
+
+    /// Validates a proof of inclusion against the milestone key of the ledger.
+    pub(crate) fn validate_inclusion_proof(&self, proof: &InclusionProof) -> Result<bool> {
+        let verifier = MilestoneVerifier::new(
+            vec![MilestoneKeyRange::new(
+                prefix_hex::encode(self.milestone_public_key()),
+                0,
+                0,
+            )],
+            1,
+        )?;
+        Ok(proof.verify(&verifier).is_ok())
+    }
+
+    /// Stops serving the milestones up to the index, the ledger state stays the same.
+    pub(crate) fn prune_milestones(&mut self, index: u32) {
+        self.pruning_index = self.pruning_index.max(index.min(self.ledger_index()));
+    }
+
+    fn milestone_entry(&self, index: u32) -> Option<&MilestoneEntry> {
+        if index <= self.pruning_index {
+            return None;
+        }
+        index
+            .checked_sub(1)
+            .and_then(|position| self.milestones.get(position as usize))
+    }
+
+    /// Returns the unspent output ids of a kind that match all query parameters, one page at a time.
+    pub(crate) fn output_ids(&self, kind: OutputKind, query: Option<&str>) -> Result<OutputIdsResponse> {
+        let mut page_size = DEFAULT_PAGE_SIZE;
+        let mut cursor = None;
+        let mut filters = Vec::new();
+
+        for (key, value) in query
+            .unwrap_or_default()
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+        {
+            match key {
+                "pageSize" => page_size = parse_query_value(key, value)?,
+                "cursor" => {
+                    // The cursor is `outputId.pageSize`
+                    let (output_id, size) = value
+                        .split_once('.')
+                        .ok_or_else(|| Error::MockNodeError(format!("invalid cursor: {value}")))?;
+                    cursor.replace(OutputId::from_str(output_id)?);
+                    page_size = parse_query_value(key, size)?;
+                }
+                _ => filters.push((key, value)),
+            }
+        }
+
+        let mut items = Vec::new();
+        for output_id in &self.output_order {
+            let entry = &self.outputs[output_id];
+            if entry.metadata.is_spent || output_kind(&entry.output) != kind {
+                continue;
+            }
+            let mut matches = true;
+            for (key, value) in &filters {
+                matches &= output_matches(&entry.output, entry.metadata.milestone_timestamp_booked, key, value)?;
+            }
+            if matches {
+                items.push(*output_id);
+            }
+        }
+
+        let start = match cursor {
+            Some(cursor) => items
+                .iter()
+                .position(|output_id| *output_id == cursor)
+                .unwrap_or(items.len()),
+            None => 0,
+        };
+        let page = items.iter().skip(start).take(page_size.max(1));
+        let next = items.get(start + page_size.max(1));
+
+        Ok(OutputIdsResponse {
+            ledger_index: self.ledger_index(),
+            cursor: next.map(|output_id| format!("{output_id}.{page_size}")),
+            items: page.map(ToString::to_string).collect(),
+        })
+    }
+
+    /// Returns the output id of the unspent chain output with the alias, foundry or NFT id.
+    pub(crate) fn alias_output_id(&self, alias_id: &AliasId) -> Option<OutputId> {
+        self.unspent_outputs().find_map(|(output_id, output)| match output {
+            Output::Alias(alias) if alias.alias_id().or_from_output_id(*output_id) == *alias_id => Some(*output_id),
+            _ => None,
+        })
+    }
+
+    pub(crate) fn foundry_output_id(&self, foundry_id: &FoundryId) -> Option<OutputId> {
+        self.unspent_outputs().find_map(|(output_id, output)| match output {
+            Output::Foundry(foundry) if foundry.id() == *foundry_id => Some(*output_id),
+            _ => None,
+        })
+    }
+
+    pub(crate) fn nft_output_id(&self, nft_id: &NftId) -> Option<OutputId> {
+        self.unspent_outputs().find_map(|(output_id, output)| match output {
+            Output::Nft(nft) if nft.nft_id().or_from_output_id(*output_id) == *nft_id => Some(*output_id),
+            _ => None,
+        })
+    }
+
+    fn unspent_outputs(&self) -> impl Iterator<Item = (&OutputId, &Output)> + '_ {
+        self.output_order
+            .iter()
+            .map(|output_id| (output_id, &self.outputs[output_id]))
+            .filter(|(_, entry)| !entry.metadata.is_spent)
+            .map(|(output_id, entry)| (output_id, &entry.output))
+    }
+}
+
+fn unreferenced_metadata(block: &Block) -> BlockMetadataResponse {
+    BlockMetadataResponse {
+        block_id: block.id().to_string(),
+        parents: block.parents().iter().map(ToString::to_string).collect(),
+        is_solid: true,
+        referenced_by_milestone_index: None,
+        milestone_index: None,
+        ledger_inclusion_state: None,
+        conflict_reason: None,
+        white_flag_index: None,
+        should_promote: Some(false),
+        should_reattach: Some(false),
+    }
+}
+
+fn output_kind(output: &Output) -> OutputKind {
+    match output {
+        Output::Alias(_) => OutputKind::Alias,
+        Output::Foundry(_) => OutputKind::Foundry,
+        Output::Nft(_) => OutputKind::Nft,
+        _ => OutputKind::Basic,
+    }
+}
+
+// Checks a single indexer query parameter against an output.
+fn output_matches(output: &Output, created_at: u32, key: &str, value: &str) -> Result<bool> {
+    let unlock_conditions = output.unlock_conditions();
+    let features = output.features();
+    let native_token_count = output.native_tokens().map_or(0, |native_tokens| native_tokens.len());
+
+    Ok(match key {
+        "address" => {
+            let address = parse_bech32(value)?;
+            match output {
+                Output::Alias(alias) => {
+                    *alias.state_controller_address() == address || *alias.governor_address() == address
+                }
+                _ => unlock_conditions
+                    .and_then(|u| u.address())
+                    .is_some_and(|u| *u.address() == address),
+            }
+        }
+        "aliasAddress" => {
+            let address = parse_bech32(value)?;
+            matches!(output, Output::Foundry(foundry) if Address::Alias(*foundry.alias_address()) == address)
+        }
+        "stateController" => {
+            let address = parse_bech32(value)?;
+            matches!(output, Output::Alias(alias) if *alias.state_controller_address() == address)
+        }
+        "governor" => {
+            let address = parse_bech32(value)?;
+            matches!(output, Output::Alias(alias) if *alias.governor_address() == address)
+        }
+        "hasExpiration" => {
+            unlock_conditions.and_then(|u| u.expiration()).is_some() == parse_query_value::<bool>(key, value)?
+        }
+        "hasTimelock" => {
+            unlock_conditions.and_then(|u| u.timelock()).is_some() == parse_query_value::<bool>(key, value)?
+        }
+        "hasStorageDepositReturn" => {
+            unlock_conditions.and_then(|u| u.storage_deposit_return()).is_some()
+                == parse_query_value::<bool>(key, value)?
+        }
+        "hasNativeTokens" => (native_token_count > 0) == parse_query_value::<bool>(key, value)?,
+        "minNativeTokenCount" => native_token_count >= parse_query_value(key, value)?,
+        "maxNativeTokenCount" => native_token_count <= parse_query_value(key, value)?,
+        "expirationReturnAddress" => {
+            let address = parse_bech32(value)?;
+            unlock_conditions
+                .and_then(|u| u.expiration())
+                .is_some_and(|e| *e.return_address() == address)
+        }
+        "expiresBefore" => {
+            let timestamp: u32 = parse_query_value(key, value)?;
+            unlock_conditions
+                .and_then(|u| u.expiration())
+                .is_some_and(|e| e.timestamp() < timestamp)
+        }
+        "expiresAfter" => {
+            let timestamp: u32 = parse_query_value(key, value)?;
+            unlock_conditions
+                .and_then(|u| u.expiration())
+                .is_some_and(|e| e.timestamp() > timestamp)
+        }
+        "timelockedBefore" => {
+            let timestamp: u32 = parse_query_value(key, value)?;
+            unlock_conditions
+                .and_then(|u| u.timelock())
+                .is_some_and(|t| t.timestamp() < timestamp)
+        }
+        "timelockedAfter" => {
+            let timestamp: u32 = parse_query_value(key, value)?;
+            unlock_conditions
+                .and_then(|u| u.timelock())
+                .is_some_and(|t| t.timestamp() > timestamp)
+        }
+        "storageDepositReturnAddress" => {
+            let address = parse_bech32(value)?;
+            unlock_conditions
+                .and_then(|u| u.storage_deposit_return())
+                .is_some_and(|s| *s.return_address() == address)
+        }
+        "sender" => {
+            let address = parse_bech32(value)?;
+            features
+                .and_then(|f| f.sender())
+                .is_some_and(|s| *s.address() == address)
+        }
+        "issuer" => {
+            let address = parse_bech32(value)?;
+            output
+                .immutable_features()
+                .and_then(|f| f.issuer())
+                .is_some_and(|i| *i.address() == address)
+        }
+        "tag" => {
+            let tag = prefix_hex::decode::<Vec<u8>>(value)?;
+            features.and_then(|f| f.tag()).is_some_and(|t| t.tag() == tag)
+        }
+        "createdBefore" => created_at < parse_query_value(key, value)?,
+        "createdAfter" => created_at > parse_query_value(key, value)?,
+        _ => return Err(Error::MockNodeError(format!("unsupported query parameter: {key}"))),
+    })
+}
+
+fn parse_bech32(value: &str) -> Result<Address> {
+    Ok(Address::try_from_bech32(value)?.1)
+}
+
+fn parse_query_value<T: FromStr>(key: &str, value: &str) -> Result<T> {
+    value
+        .parse()
+        .map_err(|_| Error::MockNodeError(format!("invalid value for {key}: {value}")))
+}
+
+// Outputs that are booked without a transaction get a transaction id derived from the milestone index
+fn synthetic_transaction_id(milestone_index: u32) -> TransactionId {
+    TransactionId::new(Blake2b256::digest(milestone_index.to_le_bytes()).into())
+}
+
+fn unix_timestamp() -> u32 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .expect("time went backwards")
+        .as_secs() as u32
+}
@@ -0,0 +1,295 @@
+// Copyright 2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! An in-process mock node to run the client against without a network connection.
+//!
+//! The [`MockNode`] serves the core and indexer routes used by the client from an in-memory UTXO ledger. Posted blocks
+//! get referenced by a milestone, transactions are semantically validated and applied to the ledger, so the block
+//! builder, `retry_until_included` and `consolidate_funds` work like with a real node.
+//!
+//! ```no_run
+//! # use iota_client::{block::address::Address, mock_node::MockNode, Client, Result};
+//! # fn main() -> Result<()> {
+//! let node = MockNode::builder().finish()?;
+//! let client = Client::builder().with_node(node.url())?.finish()?;
+//!
+//! let (_, address) = Address::try_from_bech32(
+//!     "rms1qpllaj0pyveqfkwxmnngz2c488hfdtmfrj3wfkgxtk4gtyrax0jaxzt70zy",
+//! )?;
+//! node.fund(&address, 1_000_000)?;
+//! # Ok(())}
+//! ```
+
+mod ledger;
+mod routes;
+
+use std::{
+    convert::Infallible,
+    net::{SocketAddr, TcpListener},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use bee_api_types::responses::{ProtocolResponse, RentStructureResponse};
+use bee_block::{
+    address::Address,
+    output::{unlock_condition::AddressUnlockCondition, BasicOutputBuilder, Output, OutputId, UnlockCondition},
+    payload::milestone::MilestonePayload,
+    BlockId,
+};
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Server, StatusCode,
+};
+use tokio::runtime::Runtime;
+
+use self::{ledger::MockLedger, routes::RequestFilter};
+use crate::{constants::SHIMMER_TESTNET_BECH32_HRP, Error, Result};
+
+/// Builder of the [`MockNode`]
+#[must_use]
+pub struct MockNodeBuilder {
+    network_name: String,
+    bech32_hrp: String,
+    min_pow_score: u32,
+    rent_structure: RentStructureResponse,
+    token_supply: u64,
+    auto_confirm: bool,
+    outputs: Vec<Output>,
+    required_headers: Vec<(String, String)>,
+    plugins: Vec<String>,
+}
+
+impl Default for MockNodeBuilder {
+    fn default() -> Self {
+        Self {
+            network_name: "mock".to_string(),
+            bech32_hrp: SHIMMER_TESTNET_BECH32_HRP.to_string(),
+            // Keeps local PoW cheap, a score of 0 isn't supported by the miner
+            min_pow_score: 1,
+            rent_structure: RentStructureResponse {
+                v_byte_cost: 100,
+                v_byte_factor_key: 10,
+                v_byte_factor_data: 1,
+            },
+            token_supply: 1_813_620_509_061_365,
+            auto_confirm: true,
+            outputs: Vec::new(),
+            required_headers: Vec::new(),
+            plugins: vec!["indexer/v1".to_string(), "poi/v1".to_string()],
+        }
+    }
+}
+
+impl MockNodeBuilder {
+    /// Sets the network name returned in the node info.
+    pub fn with_network_name(mut self, network_name: &str) -> Self {
+        self.network_name = network_name.to_string();
+        self
+    }
+
+    /// Sets the bech32 HRP returned in the node info.
+    pub fn with_bech32_hrp(mut self, bech32_hrp: &str) -> Self {
+        self.bech32_hrp = bech32_hrp.to_string();
+        self
+    }
+
+    /// Sets the minimum PoW score returned in the node info, PoW isn't verified by the mock node.
+    pub fn with_min_pow_score(mut self, min_pow_score: u32) -> Self {
+        self.min_pow_score = min_pow_score;
+        self
+    }
+
+    /// Sets the rent structure returned in the node info.
+    pub fn with_rent_structure(mut self, rent_structure: RentStructureResponse) -> Self {
+        self.rent_structure = rent_structure;
+        self
+    }
+
+    /// Sets the token supply returned in the node info.
+    pub fn with_token_supply(mut self, token_supply: u64) -> Self {
+        self.token_supply = token_supply;
+        self
+    }
+
+    /// Sets whether every posted block is confirmed by a new milestone right away, default is true.
+    /// Without it, blocks stay unreferenced until [`MockNode::confirm()`] is called.
+    pub fn with_auto_confirm(mut self, auto_confirm: bool) -> Self {
+        self.auto_confirm = auto_confirm;
+        self
+    }
+
+    /// Adds an output to the ledger state of the first milestone.
+    pub fn with_output(mut self, output: Output) -> Self {
+        self.outputs.push(output);
+        self
+    }
+
+    /// Requires a header in every request, requests without it are rejected with a 401 status.
+    /// E.g. `("authorization", "Bearer <token>")` for a JWT.
+    pub fn with_required_header(mut self, name: &str, value: &str) -> Self {
+        self.required_headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Leaves out a plugin, e.g. `indexer/v1`. It's missing from the routes and its requests get a 404 status.
+    pub fn without_plugin(mut self, route: &str) -> Self {
+        self.plugins.retain(|plugin| plugin != route);
+        self
+    }
+
+    /// Starts the mock node on a random local port.
+    pub fn finish(self) -> Result<MockNode> {
+        let mut ledger = MockLedger::new(
+            ProtocolResponse {
+                version: bee_block::constant::PROTOCOL_VERSION,
+                network_name: self.network_name,
+                bech32_hrp: self.bech32_hrp,
+                min_pow_score: self.min_pow_score,
+                rent_structure: self.rent_structure,
+                token_supply: self.token_supply.to_string(),
+            },
+            self.auto_confirm,
+            self.outputs,
+        )?;
+        ledger.plugins = self.plugins;
+        let ledger = Arc::new(Mutex::new(ledger));
+
+        let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0))).map_err(mock_node_error)?;
+        listener.set_nonblocking(true).map_err(mock_node_error)?;
+        let address = listener.local_addr().map_err(mock_node_error)?;
+
+        let ledger_ = ledger.clone();
+        let filter = Arc::new(RequestFilter {
+            required_headers: self.required_headers,
+            failures: Default::default(),
+        });
+        let filter_ = filter.clone();
+        // The server gets its own runtime, so it keeps answering while the caller blocks, e.g. in
+        // `ClientBuilder::finish()`
+        let runtime = Runtime::new().map_err(mock_node_error)?;
+        {
+            let _guard = runtime.enter();
+            let server = Server::from_tcp(listener)
+                .map_err(mock_node_error)?
+                .serve(make_service_fn(move |_| {
+                    let ledger = ledger_.clone();
+                    let filter = filter_.clone();
+                    async move {
+                        Ok::<_, Infallible>(service_fn(move |request| {
+                            routes::handle(ledger.clone(), filter.clone(), request)
+                        }))
+                    }
+                }));
+            runtime.spawn(async move {
+                if let Err(e) = server.await {
+                    log::error!("mock node stopped: {e}");
+                }
+            });
+        }
+
+        Ok(MockNode {
+            url: format!("http://{address}"),
+            ledger,
+            filter,
+            runtime: Some(runtime),
+        })
+    }
+}
+
+/// A local node with an in-memory ledger, stopped when dropped.
+pub struct MockNode {
+    url: String,
+    ledger: Arc<Mutex<MockLedger>>,
+    filter: Arc<RequestFilter>,
+    runtime: Option<Runtime>,
+}
+
+impl std::fmt::Debug for MockNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MockNode").field("url", &self.url).finish()
+    }
+}
+
+impl Drop for MockNode {
+    fn drop(&mut self) {
+        // Not waiting for open connections, their client side might live on a runtime that is blocked by this drop
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+impl MockNode {
+    /// Creates the builder to start a mock node.
+    pub fn builder() -> MockNodeBuilder {
+        MockNodeBuilder::default()
+    }
+
+    /// Returns the URL the mock node is listening on.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Books a basic output with the amount for the address in a new milestone, like a faucet would.
+    pub fn fund(&self, address: &Address, amount: u64) -> Result<OutputId> {
+        let output = BasicOutputBuilder::new_with_amount(amount)?
+            .add_unlock_condition(UnlockCondition::Address(AddressUnlockCondition::new(*address)))
+            .finish_output()?;
+
+        self.ledger()?.fund(output)
+    }
+
+    /// Issues a new milestone that references all pending blocks and returns its index.
+    pub fn confirm(&self) -> Result<u32> {
+        self.ledger()?.confirm()
+    }
+
+    /// Returns the milestone with the index, if it was issued.
+    pub fn milestone(&self, index: u32) -> Result<Option<MilestonePayload>> {
+        Ok(self.ledger()?.milestone_by_index(index).cloned())
+    }
+
+    /// Prunes the milestones up to the index, like a node that only keeps recent history. The node answers requests for
+    /// them with 404.
+    pub fn prune_milestones(&self, index: u32) -> Result<()> {
+        self.ledger()?.prune_milestones(index);
+        Ok(())
+    }
+
+    /// Returns whether a block got referenced by a milestone.
+    pub fn is_referenced(&self, block_id: &BlockId) -> Result<bool> {
+        Ok(self
+            .ledger()?
+            .block_metadata(block_id)
+            .is_some_and(|metadata| metadata.referenced_by_milestone_index.is_some()))
+    }
+
+    /// Returns the hex encoded public key the milestones are signed with.
+    pub fn milestone_public_key(&self) -> Result<String> {
+        Ok(prefix_hex::encode(self.ledger()?.milestone_public_key()))
+    }
+
+    /// Answers the next requests with the error status, optionally with a `Retry-After` header, like an overloaded node
+    /// or a load balancer in front of it would.
+    pub fn fail_next_requests(&self, count: usize, status: u16, retry_after: Option<Duration>) -> Result<()> {
+        let failure = (
+            StatusCode::from_u16(status).map_err(mock_node_error)?,
+            retry_after.map(|retry_after| retry_after.as_secs()),
+        );
+        self.filter
+            .failures
+            .lock()
+            .map_err(|_| Error::PoisonError)?
+            .extend((0..count).map(|_| failure));
+        Ok(())
+    }
+
+    fn ledger(&self) -> Result<std::sync::MutexGuard<'_, MockLedger>> {
+        self.ledger.lock().map_err(|_| Error::PoisonError)
+    }
+}
+
+fn mock_node_error(error: impl std::fmt::Display) -> Error {
+    Error::MockNodeError(error.to_string())
+}
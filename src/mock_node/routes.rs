@@ -0,0 +1,363 @@
+// Copyright 2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Core and indexer routes served by the mock node
+
+use std::{
+    collections::VecDeque,
+    convert::Infallible,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use bee_api_types::{
+    dtos::{PeerDto, RelationDto},
+    responses::{AddPeerResponse, PeerResponse, PeersResponse, RoutesResponse, SubmitBlockResponse, TipsResponse},
+};
+use bee_block::{
+    output::{AliasId, FoundryId, NftId, OutputId},
+    payload::{
+        milestone::{MilestoneId, MilestonePayload},
+        transaction::TransactionId,
+    },
+    Block, BlockDto, BlockId,
+};
+use hyper::{
+    header::{ACCEPT, CONTENT_TYPE, RETRY_AFTER},
+    Body, Method, Request, Response, StatusCode,
+};
+use packable::PackableExt;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::InclusionProof,
+    mock_node::ledger::{MockLedger, OutputKind},
+    node_api::poi::responses::{InclusionProofDto, ValidateProofResponse},
+};
+
+const SERIALIZER_MIME: &str = "application/vnd.iota.serializer-v1";
+const JSON_MIME: &str = "application/json";
+
+type RouteResult = std::result::Result<Response<Body>, (StatusCode, String)>;
+
+#[derive(Deserialize)]
+struct AddPeerRequest {
+    #[serde(rename = "multiAddress")]
+    multi_address: String,
+    alias: Option<String>,
+}
+
+/// Rejects requests before they reach the ledger.
+#[derive(Default)]
+pub(crate) struct RequestFilter {
+    pub(crate) required_headers: Vec<(String, String)>,
+    // Status and `Retry-After` seconds of the responses to the next requests
+    pub(crate) failures: Mutex<VecDeque<(StatusCode, Option<u64>)>>,
+}
+
+impl RequestFilter {
+    fn reject(&self, request: &Request<Body>) -> Option<Response<Body>> {
+        let authorized = self.required_headers.iter().all(|(name, value)| {
+            request
+                .headers()
+                .get(name.as_str())
+                .is_some_and(|header| header.as_bytes() == value.as_bytes())
+        });
+        if !authorized {
+            return Some(error(StatusCode::UNAUTHORIZED, "missing or invalid credentials"));
+        }
+
+        let (status, retry_after) = self.failures.lock().ok()?.pop_front()?;
+        let mut response = error(status, "injected failure");
+        if let Some(retry_after) = retry_after {
+            response.headers_mut().insert(RETRY_AFTER, retry_after.into());
+        }
+        Some(response)
+    }
+}
+
+/// Answers a single request from the ledger state.
+pub(crate) async fn handle(
+    ledger: Arc<Mutex<MockLedger>>,
+    filter: Arc<RequestFilter>,
+    request: Request<Body>,
+) -> Result<Response<Body>, Infallible> {
+    if let Some(rejection) = filter.reject(&request) {
+        return Ok(rejection);
+    }
+
+    let raw = has_serializer_mime(&request, ACCEPT);
+    let raw_body = has_serializer_mime(&request, CONTENT_TYPE);
+    let method = request.method().clone();
+    let path = request.uri().path().trim_matches('/').to_string();
+    let query = request.uri().query().map(ToString::to_string);
+
+    let response = match hyper::body::to_bytes(request.into_body()).await {
+        Ok(body) => match ledger.lock() {
+            Ok(mut ledger) => route(
+                &mut ledger,
+                &method,
+                &path.split('/').collect::<Vec<_>>(),
+                query.as_deref(),
+                raw,
+                raw_body,
+                &body,
+            ),
+            Err(_) => Err((StatusCode::INTERNAL_SERVER_ERROR, "ledger lock poisoned".to_string())),
+        },
+        Err(e) => Err((StatusCode::BAD_REQUEST, e.to_string())),
+    };
+
+    Ok(response.unwrap_or_else(|(status, message)| error(status, &message)))
+}
+
+fn route(
+    ledger: &mut MockLedger,
+    method: &Method,
+    segments: &[&str],
+    query: Option<&str>,
+    raw: bool,
+    raw_body: bool,
+    body: &[u8],
+) -> RouteResult {
+    // Like a node without the plugin
+    if let ["api", plugin, version, ..] = segments {
+        let route = format!("{plugin}/{version}");
+        if route != "core/v2" && !ledger.plugins.contains(&route) {
+            return Err((StatusCode::NOT_FOUND, format!("no plugin {route}")));
+        }
+    }
+
+    match (method, segments) {
+        (&Method::GET, ["health"]) => Ok(Response::new(Body::empty())),
+        (&Method::GET, ["api", "routes"]) => json(
+            StatusCode::OK,
+            &RoutesResponse {
+                routes: std::iter::once("core/v2".to_string())
+                    .chain(ledger.plugins.iter().cloned())
+                    .collect(),
+            },
+        ),
+
+        // Core routes
+        (&Method::GET, ["api", "core", "v2", "info"]) => json(StatusCode::OK, &ledger.info()),
+        (&Method::GET, ["api", "core", "v2", "tips"]) => json(
+            StatusCode::OK,
+            &TipsResponse {
+                tips: ledger.request_tips().iter().map(ToString::to_string).collect(),
+            },
+        ),
+        (&Method::POST, ["api", "core", "v2", "blocks"]) => {
+            let block = if raw_body {
+                Block::unpack_strict(body, &()).map_err(bad_request)?
+            } else {
+                let dto: BlockDto = serde_json::from_slice(body).map_err(bad_request)?;
+                Block::try_from(&dto).map_err(bad_request)?
+            };
+            let block_id = ledger.insert_block(block).map_err(bad_request)?;
+            json(
+                StatusCode::CREATED,
+                &SubmitBlockResponse {
+                    block_id: block_id.to_string(),
+                },
+            )
+        }
+        (&Method::GET, ["api", "core", "v2", "blocks", block_id]) => {
+            block(ledger.block(&parse::<BlockId>(block_id)?), raw)
+        }
+        (&Method::GET, ["api", "core", "v2", "blocks", block_id, "metadata"]) => json(
+            StatusCode::OK,
+            found(ledger.block_metadata(&parse::<BlockId>(block_id)?))?,
+        ),
+        (&Method::GET, ["api", "core", "v2", "outputs", output_id]) => {
+            let output_id = parse::<OutputId>(output_id)?;
+            if raw {
+                packed(found(ledger.output(&output_id))?)
+            } else {
+                json(StatusCode::OK, &found(ledger.output_response(&output_id))?)
+            }
+        }
+        (&Method::GET, ["api", "core", "v2", "outputs", output_id, "metadata"]) => json(
+            StatusCode::OK,
+            &found(ledger.output_metadata(&parse::<OutputId>(output_id)?))?,
+        ),
+        (&Method::GET, ["api", "core", "v2", "transactions", transaction_id, "included-block"]) => {
+            block(ledger.included_block(&parse::<TransactionId>(transaction_id)?), raw)
+        }
+        (&Method::GET, ["api", "core", "v2", "milestones", "by-index", index]) => {
+            milestone(ledger.milestone_by_index(parse(index)?), raw)
+        }
+        (&Method::GET, ["api", "core", "v2", "milestones", "by-index", index, "utxo-changes"]) => {
+            json(StatusCode::OK, &found(ledger.utxo_changes(parse(index)?))?)
+        }
+        (&Method::GET, ["api", "core", "v2", "milestones", milestone_id]) => {
+            milestone(ledger.milestone_by_id(&parse::<MilestoneId>(milestone_id)?), raw)
+        }
+        (&Method::GET, ["api", "core", "v2", "milestones", milestone_id, "utxo-changes"]) => {
+            let index = *found(ledger.milestone_by_id(&parse::<MilestoneId>(milestone_id)?))?
+                .essence()
+                .index();
+            json(StatusCode::OK, &found(ledger.utxo_changes(index))?)
+        }
+        (&Method::GET, ["api", "core", "v2", "peers"]) => json(StatusCode::OK, &PeersResponse(ledger.peers().to_vec())),
+        (&Method::POST, ["api", "core", "v2", "peers"]) => {
+            let request: AddPeerRequest = serde_json::from_slice(body).map_err(bad_request)?;
+            // The peer ID is the last protocol of the multiaddress, e.g. /ip4/127.0.0.1/tcp/15600/p2p/<peer id>
+            let peer_id = match request.multi_address.rsplit_once("/p2p/") {
+                Some((_, peer_id)) if !peer_id.is_empty() && !peer_id.contains('/') => peer_id.to_string(),
+                _ => return Err(bad_request(format!("no peer ID in {}", request.multi_address))),
+            };
+            let peer = PeerDto {
+                id: peer_id,
+                multi_addresses: vec![request.multi_address],
+                alias: request.alias,
+                relation: RelationDto::Known,
+                connected: false,
+                gossip: None,
+            };
+            ledger.add_peer(peer.clone());
+            json(StatusCode::CREATED, &AddPeerResponse(peer))
+        }
+        (&Method::GET, ["api", "core", "v2", "peers", peer_id]) => {
+            json(StatusCode::OK, &PeerResponse(found(ledger.peer(peer_id))?.clone()))
+        }
+        (&Method::DELETE, ["api", "core", "v2", "peers", peer_id]) => {
+            found(ledger.remove_peer(peer_id))?;
+            response(StatusCode::NO_CONTENT, JSON_MIME, Vec::new())
+        }
+
+        // Indexer routes
+        (&Method::GET, ["api", "indexer", "v1", "outputs", kind]) => {
+            let kind = match *kind {
+                "basic" => OutputKind::Basic,
+                "alias" => OutputKind::Alias,
+                "foundry" => OutputKind::Foundry,
+                "nft" => OutputKind::Nft,
+                _ => return Err((StatusCode::NOT_FOUND, format!("unknown output kind: {kind}"))),
+            };
+            json(StatusCode::OK, &ledger.output_ids(kind, query).map_err(bad_request)?)
+        }
+        (&Method::GET, ["api", "indexer", "v1", "outputs", "alias", alias_id]) => {
+            let output_id = found(ledger.alias_output_id(&parse::<AliasId>(alias_id)?))?;
+            single_output_id(ledger, output_id)
+        }
+        (&Method::GET, ["api", "indexer", "v1", "outputs", "foundry", foundry_id]) => {
+            let output_id = found(ledger.foundry_output_id(&parse::<FoundryId>(foundry_id)?))?;
+            single_output_id(ledger, output_id)
+        }
+        (&Method::GET, ["api", "indexer", "v1", "outputs", "nft", nft_id]) => {
+            let output_id = found(ledger.nft_output_id(&parse::<NftId>(nft_id)?))?;
+            single_output_id(ledger, output_id)
+        }
+
+        // Proof-of-inclusion routes
+        (&Method::GET, ["api", "poi", "v1", "create", block_id]) => {
+            let proof = found(
+                ledger
+                    .inclusion_proof(&parse::<BlockId>(block_id)?)
+                    .map_err(bad_request)?,
+            )?;
+            json(StatusCode::OK, &InclusionProofDto::from(&proof))
+        }
+        (&Method::POST, ["api", "poi", "v1", "validate"]) => {
+            let dto: InclusionProofDto = serde_json::from_slice(body).map_err(bad_request)?;
+            let proof = InclusionProof::try_from(&dto).map_err(bad_request)?;
+            json(
+                StatusCode::OK,
+                &ValidateProofResponse {
+                    valid: ledger.validate_inclusion_proof(&proof).map_err(bad_request)?,
+                },
+            )
+        }
+
+        _ => Err((
+            StatusCode::NOT_FOUND,
+            format!("no route for {method} /{}", segments.join("/")),
+        )),
+    }
+}
+
+fn has_serializer_mime(request: &Request<Body>, header: hyper::header::HeaderName) -> bool {
+    request
+        .headers()
+        .get(header)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.contains(SERIALIZER_MIME))
+}
+
+fn parse<T: FromStr>(value: &str) -> std::result::Result<T, (StatusCode, String)>
+where
+    T::Err: std::fmt::Display,
+{
+    T::from_str(value).map_err(bad_request)
+}
+
+fn found<T>(value: Option<T>) -> std::result::Result<T, (StatusCode, String)> {
+    value.ok_or((StatusCode::NOT_FOUND, "the requested data was not found".to_string()))
+}
+
+fn bad_request(error: impl std::fmt::Display) -> (StatusCode, String) {
+    (StatusCode::BAD_REQUEST, error.to_string())
+}
+
+fn block(block: Option<&Block>, raw: bool) -> RouteResult {
+    let block = found(block)?;
+    if raw {
+        packed(block)
+    } else {
+        json(StatusCode::OK, &BlockDto::from(block))
+    }
+}
+
+fn milestone(milestone: Option<&MilestonePayload>, raw: bool) -> RouteResult {
+    let milestone = found(milestone)?;
+    if raw {
+        packed(milestone)
+    } else {
+        json(
+            StatusCode::OK,
+            &bee_block::payload::milestone::dto::MilestonePayloadDto::from(milestone),
+        )
+    }
+}
+
+fn single_output_id(ledger: &MockLedger, output_id: OutputId) -> RouteResult {
+    json(
+        StatusCode::OK,
+        &crate::node_api::indexer::responses::OutputIdsResponse {
+            ledger_index: ledger.ledger_index(),
+            cursor: None,
+            items: vec![output_id.to_string()],
+        },
+    )
+}
+
+fn json<T: Serialize>(status: StatusCode, value: &T) -> RouteResult {
+    let body = serde_json::to_vec(value).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    response(status, JSON_MIME, body)
+}
+
+fn packed<P: PackableExt>(value: &P) -> RouteResult {
+    response(StatusCode::OK, SERIALIZER_MIME, value.pack_to_vec())
+}
+
+fn response(status: StatusCode, content_type: &str, body: Vec<u8>) -> RouteResult {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, content_type)
+        .body(Body::from(body))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+// Errors have the same shape as the ones returned by the node
+fn error(status: StatusCode, message: &str) -> Response<Body> {
+    let body = serde_json::json!({
+        "error": {
+            "code": status.as_u16().to_string(),
+            "message": message,
+        }
+    });
+    let mut response = Response::new(Body::from(body.to_string()));
+    *response.status_mut() = status;
+    response
+}
@@ -8,8 +8,6 @@ pub mod routes;
 use bee_api_types::responses::OutputResponse;
 use bee_block::output::OutputId;
 
-#[cfg(not(target_family = "wasm"))]
-use crate::constants::MAX_PARALLEL_API_REQUESTS;
 use crate::{Client, Result};
 
 impl Client {
@@ -21,7 +19,10 @@ impl Client {
             outputs.push(self.get_output(&output_id).await?);
         }
         #[cfg(not(target_family = "wasm"))]
-        for output_ids_chunk in output_ids.chunks(MAX_PARALLEL_API_REQUESTS).map(<[OutputId]>::to_vec) {
+        for output_ids_chunk in output_ids
+            .chunks(self.node_manager.http_client.limiter().max_concurrent_requests())
+            .map(<[OutputId]>::to_vec)
+        {
             let mut tasks = Vec::new();
             for output_id in output_ids_chunk {
                 let client_ = self.clone();
@@ -53,7 +54,10 @@ impl Client {
             }
         }
         #[cfg(not(target_family = "wasm"))]
-        for output_ids_chunk in output_ids.chunks(MAX_PARALLEL_API_REQUESTS).map(<[OutputId]>::to_vec) {
+        for output_ids_chunk in output_ids
+            .chunks(self.node_manager.http_client.limiter().max_concurrent_requests())
+            .map(<[OutputId]>::to_vec)
+        {
             let mut tasks = Vec::new();
             for output_id in output_ids_chunk {
                 let client_ = self.clone();
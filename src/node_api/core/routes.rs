@@ -8,8 +8,9 @@ use std::str::FromStr;
 use bee_api_types::{
     dtos::{PeerDto, ReceiptDto},
     responses::{
-        BlockMetadataResponse, BlockResponse, MilestoneResponse, OutputMetadataResponse, OutputResponse, PeersResponse,
-        ReceiptsResponse, RoutesResponse, SubmitBlockResponse, TipsResponse, TreasuryResponse, UtxoChangesResponse,
+        AddPeerResponse, BlockMetadataResponse, BlockResponse, MilestoneResponse, OutputMetadataResponse,
+        OutputResponse, PeerResponse, PeersResponse, ReceiptsResponse, RoutesResponse, SubmitBlockResponse,
+        TipsResponse, TreasuryResponse, UtxoChangesResponse,
     },
 };
 use bee_block::{
@@ -23,7 +24,11 @@ use bee_block::{
 use packable::PackableExt;
 use url::Url;
 
-use crate::{constants::DEFAULT_API_TIMEOUT, node_manager::node::Node, Client, Error, NodeInfoWrapper, Result};
+use crate::{
+    constants::DEFAULT_API_TIMEOUT,
+    node_manager::{cache::CachedResource, node::Node, quorum::QuorumEndpoint, response_source::ResponseSource},
+    Client, Error, NodeInfoWrapper, Result,
+};
 
 impl Client {
     // Node routes.
@@ -35,12 +40,15 @@ impl Client {
 
         let mut url = Url::parse(url)?;
         url.set_path(path);
-        let status = crate::node_manager::http_client::HttpClient::new()
+        let status = self
+            .node_manager
+            .http_client
             .get(
                 Node {
                     url,
                     auth: None,
                     disabled: false,
+                    auth_provider: None,
                 },
                 DEFAULT_API_TIMEOUT,
             )
@@ -59,7 +67,7 @@ impl Client {
         let path = "api/routes";
 
         self.node_manager
-            .get_request(path, None, self.get_timeout(), false, false)
+            .get_request(path, None, self.get_timeout(), None, false)
             .await
     }
 
@@ -69,7 +77,7 @@ impl Client {
         let path = "api/core/v2/info";
 
         self.node_manager
-            .get_request(path, None, self.get_timeout(), false, false)
+            .get_request(path, None, self.get_timeout(), None, false)
             .await
     }
 
@@ -82,7 +90,7 @@ impl Client {
 
         let resp = self
             .node_manager
-            .get_request::<TipsResponse>(path, None, self.get_timeout(), false, false)
+            .get_request::<TipsResponse>(path, None, self.get_timeout(), None, false)
             .await?;
 
         resp.tips
@@ -108,7 +116,13 @@ impl Client {
         // fallback to local PoW if remote PoW fails
         let resp = match self
             .node_manager
-            .post_request_json::<SubmitBlockResponse>(path, timeout, serde_json::to_value(block_dto)?, local_pow)
+            .post_request_json::<SubmitBlockResponse>(
+                path,
+                timeout,
+                serde_json::to_value(block_dto)?,
+                local_pow,
+                local_pow.then(|| block.id()),
+            )
             .await
         {
             Ok(res) => res,
@@ -149,7 +163,13 @@ impl Client {
                         let block_dto = BlockDto::from(&block_with_local_pow);
 
                         self.node_manager
-                            .post_request_json(path, timeout, serde_json::to_value(block_dto)?, true)
+                            .post_request_json(
+                                path,
+                                timeout,
+                                serde_json::to_value(block_dto)?,
+                                true,
+                                Some(block_with_local_pow.id()),
+                            )
                             .await?
                     } else {
                         return Err(Error::NodeError(e));
@@ -177,7 +197,13 @@ impl Client {
         // fallback to local Pow if remote Pow fails
         let resp = match self
             .node_manager
-            .post_request_bytes::<SubmitBlockResponse>(path, timeout, &block.pack_to_vec(), local_pow)
+            .post_request_bytes::<SubmitBlockResponse>(
+                path,
+                timeout,
+                &block.pack_to_vec(),
+                local_pow,
+                local_pow.then(|| block.id()),
+            )
             .await
         {
             Ok(res) => res,
@@ -216,7 +242,13 @@ impl Client {
                             }
                         };
                         self.node_manager
-                            .post_request_bytes(path, timeout, &block_with_local_pow.pack_to_vec(), true)
+                            .post_request_bytes(
+                                path,
+                                timeout,
+                                &block_with_local_pow.pack_to_vec(),
+                                true,
+                                Some(block_with_local_pow.id()),
+                            )
                             .await?
                     } else {
                         return Err(Error::NodeError(e));
@@ -233,15 +265,28 @@ impl Client {
     /// Finds a block by its BlockId. This method returns the given block object.
     /// GET /api/core/v2/blocks/{BlockId}
     pub async fn get_block(&self, block_id: &BlockId) -> Result<Block> {
+        Ok(self.get_block_with_source(block_id).await?.0)
+    }
+
+    /// Finds a block by its BlockId, like [`Client::get_block()`], and returns where it was found. The permanodes are
+    /// asked first, then the nodes.
+    /// GET /api/core/v2/blocks/{BlockId}
+    pub async fn get_block_with_source(&self, block_id: &BlockId) -> Result<(Block, ResponseSource)> {
         let path = &format!("api/core/v2/blocks/{}", block_id);
 
-        let resp = self
+        let (resp, source) = self
             .node_manager
-            .get_request::<BlockResponse>(path, None, self.get_timeout(), false, true)
+            .get_cached_request_with_source::<BlockResponse>(
+                CachedResource::Block(*block_id),
+                path,
+                self.get_timeout(),
+                Some(QuorumEndpoint::Blocks),
+                true,
+            )
             .await?;
 
         match resp {
-            BlockResponse::Json(dto) => Ok(Block::try_from(&dto)?),
+            BlockResponse::Json(dto) => Ok((Block::try_from(&dto)?, source)),
             BlockResponse::Raw(_) => Err(crate::Error::UnexpectedApiResponse),
         }
     }
@@ -252,7 +297,12 @@ impl Client {
         let path = &format!("api/core/v2/blocks/{}", block_id);
 
         self.node_manager
-            .get_request_bytes(path, None, self.get_timeout())
+            .get_cached_request_bytes(
+                CachedResource::Block(*block_id),
+                path,
+                self.get_timeout(),
+                Some(QuorumEndpoint::Blocks),
+            )
             .await
     }
 
@@ -262,7 +312,7 @@ impl Client {
         let path = &format!("api/core/v2/blocks/{}/metadata", block_id);
 
         self.node_manager
-            .get_request(path, None, self.get_timeout(), true, true)
+            .get_request(path, None, self.get_timeout(), Some(QuorumEndpoint::Blocks), true)
             .await
     }
 
@@ -271,10 +321,17 @@ impl Client {
     /// Finds an output, as JSON, by its OutputId (TransactionId + output_index).
     /// GET /api/core/v2/outputs/{outputId}
     pub async fn get_output(&self, output_id: &OutputId) -> Result<OutputResponse> {
+        Ok(self.get_output_with_source(output_id).await?.0)
+    }
+
+    /// Finds an output, as JSON, by its OutputId and returns where it was found. The permanodes are asked first, then
+    /// the nodes.
+    /// GET /api/core/v2/outputs/{outputId}
+    pub async fn get_output_with_source(&self, output_id: &OutputId) -> Result<(OutputResponse, ResponseSource)> {
         let path = &format!("api/core/v2/outputs/{}", output_id);
 
         self.node_manager
-            .get_request(path, None, self.get_timeout(), false, true)
+            .get_request_with_source(path, None, self.get_timeout(), Some(QuorumEndpoint::Outputs), true)
             .await
     }
 
@@ -284,7 +341,7 @@ impl Client {
         let path = &format!("api/core/v2/outputs/{}", output_id);
 
         self.node_manager
-            .get_request_bytes(path, None, self.get_timeout())
+            .get_request_bytes(path, None, self.get_timeout(), Some(QuorumEndpoint::Outputs))
             .await
     }
 
@@ -294,7 +351,7 @@ impl Client {
         let path = &format!("api/core/v2/outputs/{}/metadata", output_id);
 
         self.node_manager
-            .get_request::<OutputMetadataResponse>(path, None, self.get_timeout(), false, true)
+            .get_request::<OutputMetadataResponse>(path, None, self.get_timeout(), Some(QuorumEndpoint::Outputs), true)
             .await
     }
 
@@ -305,7 +362,7 @@ impl Client {
 
         let resp = self
             .node_manager
-            .get_request::<ReceiptsResponse>(path, None, DEFAULT_API_TIMEOUT, false, false)
+            .get_request::<ReceiptsResponse>(path, None, DEFAULT_API_TIMEOUT, None, false)
             .await?;
 
         Ok(resp.receipts)
@@ -318,7 +375,7 @@ impl Client {
 
         let resp = self
             .node_manager
-            .get_request::<ReceiptsResponse>(path, None, DEFAULT_API_TIMEOUT, false, false)
+            .get_request::<ReceiptsResponse>(path, None, DEFAULT_API_TIMEOUT, None, false)
             .await?;
 
         Ok(resp.receipts)
@@ -331,22 +388,37 @@ impl Client {
         let path = "api/core/v2/treasury";
 
         self.node_manager
-            .get_request(path, None, DEFAULT_API_TIMEOUT, false, false)
+            .get_request(path, None, DEFAULT_API_TIMEOUT, None, false)
             .await
     }
 
     /// Returns the block, as object, that was included in the ledger for a given TransactionId.
     /// GET /api/core/v2/transactions/{transactionId}/included-block
     pub async fn get_included_block(&self, transaction_id: &TransactionId) -> Result<Block> {
+        Ok(self.get_included_block_with_source(transaction_id).await?.0)
+    }
+
+    /// Returns the block that was included in the ledger for a given TransactionId and where it was found.
+    /// GET /api/core/v2/transactions/{transactionId}/included-block
+    pub async fn get_included_block_with_source(
+        &self,
+        transaction_id: &TransactionId,
+    ) -> Result<(Block, ResponseSource)> {
         let path = &format!("api/core/v2/transactions/{}/included-block", transaction_id);
 
-        let resp = self
+        let (resp, source) = self
             .node_manager
-            .get_request::<BlockResponse>(path, None, self.get_timeout(), true, true)
+            .get_cached_request_with_source::<BlockResponse>(
+                CachedResource::IncludedBlock(*transaction_id),
+                path,
+                self.get_timeout(),
+                Some(QuorumEndpoint::Blocks),
+                true,
+            )
             .await?;
 
         match resp {
-            BlockResponse::Json(dto) => Ok(Block::try_from(&dto)?),
+            BlockResponse::Json(dto) => Ok((Block::try_from(&dto)?, source)),
             BlockResponse::Raw(_) => Err(crate::Error::UnexpectedApiResponse),
         }
     }
@@ -357,7 +429,12 @@ impl Client {
         let path = &format!("api/core/v2/transactions/{}/included-block", transaction_id);
 
         self.node_manager
-            .get_request_bytes(path, None, self.get_timeout())
+            .get_cached_request_bytes(
+                CachedResource::IncludedBlock(*transaction_id),
+                path,
+                self.get_timeout(),
+                Some(QuorumEndpoint::Blocks),
+            )
             .await
     }
 
@@ -370,7 +447,13 @@ impl Client {
 
         let resp = self
             .node_manager
-            .get_request::<MilestoneResponse>(path, None, self.get_timeout(), false, true)
+            .get_cached_request::<MilestoneResponse>(
+                CachedResource::MilestoneById(*milestone_id),
+                path,
+                self.get_timeout(),
+                Some(QuorumEndpoint::Milestones),
+                true,
+            )
             .await?;
 
         match resp {
@@ -385,7 +468,12 @@ impl Client {
         let path = &format!("api/core/v2/milestones/{}", milestone_id);
 
         self.node_manager
-            .get_request_bytes(path, None, self.get_timeout())
+            .get_cached_request_bytes(
+                CachedResource::MilestoneById(*milestone_id),
+                path,
+                self.get_timeout(),
+                Some(QuorumEndpoint::Milestones),
+            )
             .await
     }
 
@@ -395,22 +483,41 @@ impl Client {
         let path = &format!("api/core/v2/milestones/{}/utxo-changes", milestone_id);
 
         self.node_manager
-            .get_request(path, None, self.get_timeout(), false, false)
+            .get_cached_request(
+                CachedResource::UtxoChangesById(*milestone_id),
+                path,
+                self.get_timeout(),
+                None,
+                false,
+            )
             .await
     }
 
     /// Gets the milestone by the given milestone index.
     /// GET /api/core/v2/milestones/{index}
     pub async fn get_milestone_by_index(&self, index: u32) -> Result<MilestonePayload> {
+        Ok(self.get_milestone_by_index_with_source(index).await?.0)
+    }
+
+    /// Gets the milestone by the given milestone index and where it was found, old milestones may only be available
+    /// on permanodes.
+    /// GET /api/core/v2/milestones/{index}
+    pub async fn get_milestone_by_index_with_source(&self, index: u32) -> Result<(MilestonePayload, ResponseSource)> {
         let path = &format!("api/core/v2/milestones/by-index/{}", index);
 
-        let resp = self
+        let (resp, source) = self
             .node_manager
-            .get_request::<MilestoneResponse>(path, None, self.get_timeout(), false, true)
+            .get_cached_request_with_source::<MilestoneResponse>(
+                CachedResource::MilestoneByIndex(index),
+                path,
+                self.get_timeout(),
+                Some(QuorumEndpoint::Milestones),
+                true,
+            )
             .await?;
 
         match resp {
-            MilestoneResponse::Json(dto) => Ok(MilestonePayload::try_from(&dto)?),
+            MilestoneResponse::Json(dto) => Ok((MilestonePayload::try_from(&dto)?, source)),
             MilestoneResponse::Raw(_) => Err(crate::Error::UnexpectedApiResponse),
         }
     }
@@ -421,7 +528,12 @@ impl Client {
         let path = &format!("api/core/v2/milestones/by-index/{}", index);
 
         self.node_manager
-            .get_request_bytes(path, None, self.get_timeout())
+            .get_cached_request_bytes(
+                CachedResource::MilestoneByIndex(index),
+                path,
+                self.get_timeout(),
+                Some(QuorumEndpoint::Milestones),
+            )
             .await
     }
 
@@ -431,7 +543,13 @@ impl Client {
         let path = &format!("api/core/v2/milestones/by-index/{}/utxo-changes", index);
 
         self.node_manager
-            .get_request(path, None, self.get_timeout(), false, false)
+            .get_cached_request(
+                CachedResource::UtxoChangesByIndex(index),
+                path,
+                self.get_timeout(),
+                None,
+                false,
+            )
             .await
     }
 
@@ -443,21 +561,53 @@ impl Client {
 
         let resp = self
             .node_manager
-            .get_request::<PeersResponse>(path, None, self.get_timeout(), false, false)
+            .get_request::<PeersResponse>(path, None, self.get_timeout(), None, false)
             .await?;
 
         Ok(resp.0)
     }
 
-    // // RoutePeer is the route for getting peers by their peerID.
-    // // GET returns the peer
-    // // DELETE deletes the peer.
-    // RoutePeer = "/peers/:" + restapipkg.ParameterPeerID
+    /// GET /api/core/v2/peers/{peerId}
+    /// The peers are managed per node, so only the node with the url is asked. The credentials of the node are used if
+    /// the client has it.
+    pub async fn get_peer(&self, url: &str, peer_id: &str) -> Result<PeerDto> {
+        let path = &format!("api/core/v2/peers/{peer_id}");
 
-    // // RoutePeers is the route for getting all peers of the node.
-    // // GET returns a list of all peers.
-    // // POST adds a new peer.
-    // RoutePeers = "/peers"
+        let resp = self
+            .node_manager
+            .get_request_to::<PeerResponse>(&Url::parse(url)?, path, self.get_timeout())
+            .await?;
+
+        Ok(resp.0)
+    }
+
+    /// POST /api/core/v2/peers
+    /// Adds a peer to the node with the url by its multiaddress, which has to contain the peer ID, e.g.
+    /// `/ip4/127.0.0.1/tcp/15600/p2p/<peerId>`.
+    pub async fn add_peer(&self, url: &str, multi_address: &str, alias: Option<&str>) -> Result<PeerDto> {
+        let path = "api/core/v2/peers";
+        let mut body = serde_json::json!({ "multiAddress": multi_address });
+        if let Some(alias) = alias {
+            body["alias"] = serde_json::Value::from(alias);
+        }
+
+        let resp = self
+            .node_manager
+            .post_request_json_to::<AddPeerResponse>(&Url::parse(url)?, path, self.get_timeout(), body)
+            .await?;
+
+        Ok(resp.0)
+    }
+
+    /// DELETE /api/core/v2/peers/{peerId}
+    /// Removes the peer from the node with the url.
+    pub async fn remove_peer(&self, url: &str, peer_id: &str) -> Result<()> {
+        let path = &format!("api/core/v2/peers/{peer_id}");
+
+        self.node_manager
+            .delete_request_to(&Url::parse(url)?, path, self.get_timeout())
+            .await
+    }
 
     // Control routes.
 
@@ -6,6 +6,7 @@
 pub mod query_parameters;
 pub mod responses;
 pub mod routes;
+pub mod stream;
 
 use std::str::FromStr;
 
@@ -15,7 +16,7 @@ pub(crate) use self::{
     query_parameters::{QueryParameter, QueryParameters},
     responses::OutputIdsResponse,
 };
-use crate::{Client, Result};
+use crate::{node_manager::quorum::QuorumEndpoint, Client, Result};
 
 impl Client {
     /// Get all output ids for a provided URL route and query parameters.
@@ -36,7 +37,7 @@ impl Client {
                     route,
                     query_parameters.to_query_string().as_deref(),
                     self.get_timeout(),
-                    need_quorum,
+                    need_quorum.then_some(QuorumEndpoint::Indexer),
                     prefer_permanode,
                 )
                 .await?;
@@ -4,7 +4,10 @@
 //! IOTA node indexer routes
 use bee_block::output::{AliasId, FoundryId, NftId, OutputId};
 
-use crate::{node_api::indexer::query_parameters::QueryParameter, Client, Error, Result};
+use crate::{
+    node_api::indexer::{query_parameters::QueryParameter, stream::OutputIdsStream},
+    Client, Error, Result,
+};
 
 // hornet: https://github.com/gohornet/hornet/blob/develop/plugins/indexer/routes.go
 
@@ -30,33 +33,24 @@ impl Client {
     pub async fn basic_output_ids(&self, query_parameters: Vec<QueryParameter>) -> Result<Vec<OutputId>> {
         let route = "api/indexer/v1/outputs/basic";
 
-        verify_query_parameters!(
-            query_parameters,
-            QueryParameter::Address,
-            QueryParameter::HasNativeTokens,
-            QueryParameter::MinNativeTokenCount,
-            QueryParameter::MaxNativeTokenCount,
-            QueryParameter::HasStorageDepositReturn,
-            QueryParameter::StorageDepositReturnAddress,
-            QueryParameter::HasTimelock,
-            QueryParameter::TimelockedBefore,
-            QueryParameter::TimelockedAfter,
-            QueryParameter::HasExpiration,
-            QueryParameter::ExpiresBefore,
-            QueryParameter::ExpiresAfter,
-            QueryParameter::ExpirationReturnAddress,
-            QueryParameter::Sender,
-            QueryParameter::Tag,
-            QueryParameter::CreatedBefore,
-            QueryParameter::CreatedAfter,
-            QueryParameter::PageSize,
-            QueryParameter::Cursor
-        )?;
+        verify_basic_query_parameters(&query_parameters)?;
 
         self.get_output_ids_with_pagination(route, query_parameters, true, false)
             .await
     }
 
+    /// Returns a stream of the pages of [`Client::basic_output_ids()`], to process the output IDs page by page and
+    /// resume the query with a cursor.
+    pub fn basic_output_ids_stream(&self, query_parameters: Vec<QueryParameter>) -> Result<OutputIdsStream> {
+        verify_basic_query_parameters(&query_parameters)?;
+
+        Ok(OutputIdsStream::new(
+            self,
+            "api/indexer/v1/outputs/basic",
+            query_parameters,
+        ))
+    }
+
     /// Get alias outputs filtered by the given parameters.
     /// GET with query parameter returns all outputIDs that fit these filter criteria.
     /// Query parameters: "stateController", "governor", "issuer", "sender", "createdBefore", "createdAfter"
@@ -65,25 +59,24 @@ impl Client {
     pub async fn alias_output_ids(&self, query_parameters: Vec<QueryParameter>) -> Result<Vec<OutputId>> {
         let route = "api/indexer/v1/outputs/alias";
 
-        verify_query_parameters!(
-            query_parameters,
-            QueryParameter::StateController,
-            QueryParameter::Governor,
-            QueryParameter::Issuer,
-            QueryParameter::Sender,
-            QueryParameter::HasNativeTokens,
-            QueryParameter::MinNativeTokenCount,
-            QueryParameter::MaxNativeTokenCount,
-            QueryParameter::CreatedBefore,
-            QueryParameter::CreatedAfter,
-            QueryParameter::PageSize,
-            QueryParameter::Cursor
-        )?;
+        verify_alias_query_parameters(&query_parameters)?;
 
         self.get_output_ids_with_pagination(route, query_parameters, true, false)
             .await
     }
 
+    /// Returns a stream of the pages of [`Client::alias_output_ids()`], to process the output IDs page by page and
+    /// resume the query with a cursor.
+    pub fn alias_output_ids_stream(&self, query_parameters: Vec<QueryParameter>) -> Result<OutputIdsStream> {
+        verify_alias_query_parameters(&query_parameters)?;
+
+        Ok(OutputIdsStream::new(
+            self,
+            "api/indexer/v1/outputs/alias",
+            query_parameters,
+        ))
+    }
+
     /// Get alias output by its aliasID.
     /// api/indexer/v1/outputs/alias/:{AliasId}
     pub async fn alias_output_id(&self, alias_id: AliasId) -> Result<OutputId> {
@@ -104,22 +97,24 @@ impl Client {
     pub async fn foundry_output_ids(&self, query_parameters: Vec<QueryParameter>) -> Result<Vec<OutputId>> {
         let route = "api/indexer/v1/outputs/foundry";
 
-        verify_query_parameters!(
-            query_parameters,
-            QueryParameter::AliasAddress,
-            QueryParameter::HasNativeTokens,
-            QueryParameter::MinNativeTokenCount,
-            QueryParameter::MaxNativeTokenCount,
-            QueryParameter::CreatedBefore,
-            QueryParameter::CreatedAfter,
-            QueryParameter::PageSize,
-            QueryParameter::Cursor
-        )?;
+        verify_foundry_query_parameters(&query_parameters)?;
 
         self.get_output_ids_with_pagination(route, query_parameters, true, false)
             .await
     }
 
+    /// Returns a stream of the pages of [`Client::foundry_output_ids()`], to process the output IDs page by page and
+    /// resume the query with a cursor.
+    pub fn foundry_output_ids_stream(&self, query_parameters: Vec<QueryParameter>) -> Result<OutputIdsStream> {
+        verify_foundry_query_parameters(&query_parameters)?;
+
+        Ok(OutputIdsStream::new(
+            self,
+            "api/indexer/v1/outputs/foundry",
+            query_parameters,
+        ))
+    }
+
     /// Get foundry output by its foundryID.
     /// api/indexer/v1/outputs/foundry/:{FoundryID}
     pub async fn foundry_output_id(&self, foundry_id: FoundryId) -> Result<OutputId> {
@@ -141,33 +136,24 @@ impl Client {
     pub async fn nft_output_ids(&self, query_parameters: Vec<QueryParameter>) -> Result<Vec<OutputId>> {
         let route = "api/indexer/v1/outputs/nft";
 
-        verify_query_parameters!(
-            query_parameters,
-            QueryParameter::Address,
-            QueryParameter::HasNativeTokens,
-            QueryParameter::MinNativeTokenCount,
-            QueryParameter::MaxNativeTokenCount,
-            QueryParameter::HasStorageDepositReturn,
-            QueryParameter::StorageDepositReturnAddress,
-            QueryParameter::HasTimelock,
-            QueryParameter::TimelockedBefore,
-            QueryParameter::TimelockedAfter,
-            QueryParameter::HasExpiration,
-            QueryParameter::ExpiresBefore,
-            QueryParameter::ExpiresAfter,
-            QueryParameter::ExpirationReturnAddress,
-            QueryParameter::Sender,
-            QueryParameter::Tag,
-            QueryParameter::CreatedBefore,
-            QueryParameter::CreatedAfter,
-            QueryParameter::PageSize,
-            QueryParameter::Cursor
-        )?;
+        verify_nft_query_parameters(&query_parameters)?;
 
         self.get_output_ids_with_pagination(route, query_parameters, true, false)
             .await
     }
 
+    /// Returns a stream of the pages of [`Client::nft_output_ids()`], to process the output IDs page by page and
+    /// resume the query with a cursor.
+    pub fn nft_output_ids_stream(&self, query_parameters: Vec<QueryParameter>) -> Result<OutputIdsStream> {
+        verify_nft_query_parameters(&query_parameters)?;
+
+        Ok(OutputIdsStream::new(
+            self,
+            "api/indexer/v1/outputs/nft",
+            query_parameters,
+        ))
+    }
+
     /// Get NFT output by its nftID.
     /// api/indexer/v1/outputs/nft/:{NftId}
     pub async fn nft_output_id(&self, nft_id: NftId) -> Result<OutputId> {
@@ -180,3 +166,84 @@ impl Client {
             .ok_or_else(|| crate::Error::NodeError("no output id for nft".to_string()))?))
     }
 }
+
+fn verify_basic_query_parameters(query_parameters: &[QueryParameter]) -> Result<()> {
+    verify_query_parameters!(
+        query_parameters,
+        QueryParameter::Address,
+        QueryParameter::HasNativeTokens,
+        QueryParameter::MinNativeTokenCount,
+        QueryParameter::MaxNativeTokenCount,
+        QueryParameter::HasStorageDepositReturn,
+        QueryParameter::StorageDepositReturnAddress,
+        QueryParameter::HasTimelock,
+        QueryParameter::TimelockedBefore,
+        QueryParameter::TimelockedAfter,
+        QueryParameter::HasExpiration,
+        QueryParameter::ExpiresBefore,
+        QueryParameter::ExpiresAfter,
+        QueryParameter::ExpirationReturnAddress,
+        QueryParameter::Sender,
+        QueryParameter::Tag,
+        QueryParameter::CreatedBefore,
+        QueryParameter::CreatedAfter,
+        QueryParameter::PageSize,
+        QueryParameter::Cursor
+    )
+}
+
+fn verify_alias_query_parameters(query_parameters: &[QueryParameter]) -> Result<()> {
+    verify_query_parameters!(
+        query_parameters,
+        QueryParameter::StateController,
+        QueryParameter::Governor,
+        QueryParameter::Issuer,
+        QueryParameter::Sender,
+        QueryParameter::HasNativeTokens,
+        QueryParameter::MinNativeTokenCount,
+        QueryParameter::MaxNativeTokenCount,
+        QueryParameter::CreatedBefore,
+        QueryParameter::CreatedAfter,
+        QueryParameter::PageSize,
+        QueryParameter::Cursor
+    )
+}
+
+fn verify_foundry_query_parameters(query_parameters: &[QueryParameter]) -> Result<()> {
+    verify_query_parameters!(
+        query_parameters,
+        QueryParameter::AliasAddress,
+        QueryParameter::HasNativeTokens,
+        QueryParameter::MinNativeTokenCount,
+        QueryParameter::MaxNativeTokenCount,
+        QueryParameter::CreatedBefore,
+        QueryParameter::CreatedAfter,
+        QueryParameter::PageSize,
+        QueryParameter::Cursor
+    )
+}
+
+fn verify_nft_query_parameters(query_parameters: &[QueryParameter]) -> Result<()> {
+    verify_query_parameters!(
+        query_parameters,
+        QueryParameter::Address,
+        QueryParameter::HasNativeTokens,
+        QueryParameter::MinNativeTokenCount,
+        QueryParameter::MaxNativeTokenCount,
+        QueryParameter::HasStorageDepositReturn,
+        QueryParameter::StorageDepositReturnAddress,
+        QueryParameter::HasTimelock,
+        QueryParameter::TimelockedBefore,
+        QueryParameter::TimelockedAfter,
+        QueryParameter::HasExpiration,
+        QueryParameter::ExpiresBefore,
+        QueryParameter::ExpiresAfter,
+        QueryParameter::ExpirationReturnAddress,
+        QueryParameter::Sender,
+        QueryParameter::Tag,
+        QueryParameter::CreatedBefore,
+        QueryParameter::CreatedAfter,
+        QueryParameter::PageSize,
+        QueryParameter::Cursor
+    )
+}
@@ -0,0 +1,184 @@
+// Copyright 2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Stream of the pages of an indexer query, for queries with too many output IDs to collect at once
+
+use std::{
+    future::Future,
+    pin::Pin,
+    str::FromStr,
+    task::{Context, Poll},
+};
+
+use bee_api_types::responses::OutputResponse;
+use bee_block::output::OutputId;
+use futures::Stream;
+
+use crate::{
+    node_api::indexer::{OutputIdsResponse, QueryParameter, QueryParameters},
+    node_manager::quorum::QuorumEndpoint,
+    Client, Result,
+};
+
+#[cfg(not(target_family = "wasm"))]
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+#[cfg(target_family = "wasm")]
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T>>>;
+
+/// A page of the output IDs of an indexer query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputIdsPage {
+    /// The output IDs of the page
+    pub output_ids: Vec<OutputId>,
+    /// The outputs in the order of the output IDs, if the stream resolves them
+    pub outputs: Option<Vec<OutputResponse>>,
+    /// The ledger index at which the indexer collected the page
+    pub ledger_index: u32,
+    /// The cursor of the next page, `None` on the last page
+    pub cursor: Option<String>,
+}
+
+/// Stream of the pages of an indexer query, created with the `*_output_ids_stream()` methods of the [`Client`].
+///
+/// A page is only requested once the previous one was consumed. An error ends the stream, the failed page is
+/// requested again when resuming from [`OutputIdsStream::cursor()`].
+#[must_use = "streams do nothing unless polled"]
+pub struct OutputIdsStream {
+    client: Client,
+    route: String,
+    query_parameters: QueryParameters,
+    resolve_outputs: bool,
+    // The cursor of the next page, `None` before the first page
+    cursor: Option<String>,
+    request: Option<BoxFuture<Result<OutputIdsPage>>>,
+    finished: bool,
+}
+
+impl OutputIdsStream {
+    pub(crate) fn new(client: &Client, route: &str, query_parameters: Vec<QueryParameter>) -> Self {
+        let cursor = query_parameters
+            .iter()
+            .find_map(|query_parameter| match query_parameter {
+                QueryParameter::Cursor(cursor) => Some(cursor.clone()),
+                _ => None,
+            });
+
+        Self {
+            client: client.clone(),
+            route: route.to_string(),
+            query_parameters: QueryParameters::new(query_parameters),
+            resolve_outputs: false,
+            cursor,
+            request: None,
+            finished: false,
+        }
+    }
+
+    /// Sets the maximum number of output IDs per page. A cursor contains the page size already, so resuming with a
+    /// cursor keeps the page size of the interrupted stream.
+    pub fn with_page_size(mut self, page_size: usize) -> Self {
+        self.query_parameters.replace(QueryParameter::PageSize(page_size));
+        self
+    }
+
+    /// Resumes the query at the page of the cursor, e.g. the one of the last consumed [`OutputIdsPage`].
+    pub fn with_cursor(mut self, cursor: impl Into<String>) -> Self {
+        let cursor = cursor.into();
+        self.query_parameters.replace(QueryParameter::Cursor(cursor.clone()));
+        self.cursor.replace(cursor);
+        self
+    }
+
+    /// Requests the outputs of every page concurrently, in [`OutputIdsPage::outputs`].
+    pub fn with_outputs(mut self) -> Self {
+        self.resolve_outputs = true;
+        self
+    }
+
+    /// The cursor of the next page, to resume the query from after an error or a restart. `None` before the first
+    /// page, unless the stream was created with a cursor.
+    pub fn cursor(&self) -> Option<&str> {
+        self.cursor.as_deref()
+    }
+
+    /// Whether the last page was yielded or an error ended the stream.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    fn request_page(&mut self) {
+        let client = self.client.clone();
+        let route = self.route.clone();
+        let query = self.query_parameters.to_query_string();
+        let resolve_outputs = self.resolve_outputs;
+
+        self.request.replace(Box::pin(async move {
+            let response = client
+                .node_manager
+                .get_request::<OutputIdsResponse>(
+                    &route,
+                    query.as_deref(),
+                    client.get_timeout(),
+                    Some(QuorumEndpoint::Indexer),
+                    false,
+                )
+                .await?;
+            let output_ids = response
+                .items
+                .iter()
+                .map(|output_id| OutputId::from_str(output_id))
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            let outputs = if resolve_outputs {
+                Some(client.get_outputs(output_ids.clone()).await?)
+            } else {
+                None
+            };
+
+            Ok(OutputIdsPage {
+                output_ids,
+                outputs,
+                ledger_index: response.ledger_index,
+                cursor: response.cursor,
+            })
+        }));
+    }
+}
+
+impl Stream for OutputIdsStream {
+    type Item = Result<OutputIdsPage>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.finished {
+            return Poll::Ready(None);
+        }
+        if self.request.is_none() {
+            self.request_page();
+        }
+        let poll = self
+            .request
+            .as_mut()
+            .map(|request| request.as_mut().poll(cx))
+            .unwrap_or(Poll::Pending);
+
+        match poll {
+            Poll::Ready(result) => {
+                self.request = None;
+                match &result {
+                    Ok(page) => match &page.cursor {
+                        Some(cursor) => {
+                            self.query_parameters.replace(QueryParameter::Cursor(cursor.clone()));
+                            self.cursor.replace(cursor.clone());
+                        }
+                        None => {
+                            self.cursor = None;
+                            self.finished = true;
+                        }
+                    },
+                    Err(_) => self.finished = true,
+                }
+                Poll::Ready(Some(result))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
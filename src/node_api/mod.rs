@@ -8,3 +8,4 @@ pub mod high_level;
 pub mod indexer;
 #[cfg(feature = "mqtt")]
 pub mod mqtt;
+pub mod poi;
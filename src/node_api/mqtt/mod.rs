@@ -20,7 +20,7 @@ use rumqttc::{
 use tokio::sync::{watch::Sender, RwLock};
 
 pub use self::types::*;
-use crate::{Client, Result};
+use crate::{node_manager::capabilities::NodeCapability, Client, Result};
 
 async fn get_mqtt_client(client: &mut Client) -> Result<&mut MqttClient> {
     // if the client was disconnected, we clear it so we can start over
@@ -30,22 +30,19 @@ async fn get_mqtt_client(client: &mut Client) -> Result<&mut MqttClient> {
     match client.mqtt_client {
         Some(ref mut c) => Ok(c),
         None => {
-            let nodes = if client.node_manager.node_sync_enabled {
-                #[cfg(not(target_family = "wasm"))]
-                {
-                    client
-                        .node_manager
-                        .synced_nodes
-                        .read()
-                        .map_or(client.node_manager.nodes.clone(), |synced_nodes| synced_nodes.clone())
-                }
-                #[cfg(target_family = "wasm")]
-                {
-                    client.node_manager.nodes.clone()
+            if client.node_manager.offline {
+                return Err(crate::Error::OfflineMode("MQTT".to_string()));
+            }
+            let mut nodes = client.node_manager.pool_nodes()?.into_iter().collect::<Vec<_>>();
+            if !nodes.is_empty() {
+                client
+                    .node_manager
+                    .node_stats
+                    .retain_capable(&mut nodes, NodeCapability::Mqtt);
+                if nodes.is_empty() {
+                    return Err(crate::Error::PluginNotAvailable(NodeCapability::Mqtt));
                 }
-            } else {
-                client.node_manager.nodes.clone()
-            };
+            }
             for node in &nodes {
                 let host = node.url.host_str().expect("can't get host from URL");
                 let mut entropy = [0u8; 8];
@@ -67,6 +64,10 @@ async fn get_mqtt_client(client: &mut Client) -> Result<&mut MqttClient> {
                     mqtt_options.set_transport(Transport::ws());
                 }
                 mqtt_options.set_connection_timeout(client.broker_options.timeout.as_secs());
+                if let Some(auth_provider) = node.auth_provider() {
+                    let (username, password) = auth_provider.credentials().await?.into_mqtt_credentials();
+                    mqtt_options.set_credentials(username, password);
+                }
                 let (_, mut connection) = MqttClient::new(mqtt_options.clone(), 10);
                 // poll the event loop until we find a ConnAck event,
                 // which means that the mqtt client is ready to be used on this host
@@ -0,0 +1,7 @@
+// Copyright 2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Node proof-of-inclusion plugin API.
+
+pub mod responses;
+pub mod routes;
@@ -0,0 +1,53 @@
+// Copyright 2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Types of the proof-of-inclusion plugin
+
+use bee_block::{payload::milestone::dto::MilestonePayloadDto, BlockDto};
+use serde::{Deserialize, Serialize};
+
+/// Response of GET /api/poi/v1/create/{blockId} and body of POST /api/poi/v1/validate.
+/// A block, the milestone that referenced it and the audit path from the block to the inclusion merkle root.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct InclusionProofDto {
+    /// The milestone that referenced the block
+    pub milestone: MilestonePayloadDto,
+    /// The proven block
+    pub block: BlockDto,
+    /// The audit path of the block
+    pub proof: MerkleAuditPathDto,
+}
+
+/// A node of the merkle tree of the blocks a milestone referenced, with only the hashes of the subtrees that don't
+/// contain the proven block.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MerkleAuditPathDto {
+    /// An inner node
+    Node {
+        /// The left subtree
+        #[serde(rename = "l")]
+        left: Box<MerkleAuditPathDto>,
+        /// The right subtree
+        #[serde(rename = "r")]
+        right: Box<MerkleAuditPathDto>,
+    },
+    /// The hash of a subtree
+    Hash {
+        /// The hex encoded hash
+        #[serde(rename = "h")]
+        hash: String,
+    },
+    /// The proven block
+    Value {
+        /// The block ID
+        value: String,
+    },
+}
+
+/// Response of POST /api/poi/v1/validate.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ValidateProofResponse {
+    /// Whether the proof is valid
+    pub valid: bool,
+}
@@ -0,0 +1,45 @@
+// Copyright 2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Proof-of-inclusion plugin routes.
+
+use bee_block::BlockId;
+
+use crate::{
+    api::InclusionProof,
+    node_api::poi::responses::{InclusionProofDto, ValidateProofResponse},
+    Client, Result,
+};
+
+impl Client {
+    /// Creates the proof of inclusion of a block that a milestone referenced.
+    /// GET /api/poi/v1/create/{blockId}
+    pub async fn create_inclusion_proof(&self, block_id: &BlockId) -> Result<InclusionProof> {
+        let path = &format!("api/poi/v1/create/{block_id}");
+
+        let dto = self
+            .node_manager
+            .get_request::<InclusionProofDto>(path, None, self.get_timeout(), None, false)
+            .await?;
+
+        InclusionProof::try_from(&dto)
+    }
+
+    /// Lets the node validate a proof of inclusion. This trusts the node, [`InclusionProof::verify()`] validates it
+    /// locally.
+    /// POST /api/poi/v1/validate
+    pub async fn validate_inclusion_proof(&self, proof: &InclusionProof) -> Result<bool> {
+        let path = "api/poi/v1/validate";
+
+        let resp = self
+            .node_manager
+            .post_request_json_without_pow::<ValidateProofResponse>(
+                path,
+                self.get_timeout(),
+                serde_json::to_value(InclusionProofDto::from(proof))?,
+            )
+            .await?;
+
+        Ok(resp.valid)
+    }
+}
@@ -0,0 +1,248 @@
+// Copyright 2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Providers of the credentials that are attached to the requests to a node
+
+#[cfg(not(target_family = "wasm"))]
+use std::time::Instant;
+use std::{
+    fmt::Debug,
+    future::Future,
+    hash::{Hash, Hasher},
+    ops::Deref,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+#[cfg(target_family = "wasm")]
+use instant::Instant;
+
+use crate::{Error, Result};
+
+/// Credentials for a single request.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Credentials {
+    /// Token sent as `Authorization: Bearer <token>`
+    Bearer(String),
+    /// HTTP basic auth
+    Basic {
+        /// Username
+        username: String,
+        /// Password
+        password: String,
+    },
+    /// Custom header, e.g. with an API key
+    Header {
+        /// Header name
+        name: String,
+        /// Header value
+        value: String,
+    },
+}
+
+impl Credentials {
+    pub(crate) fn apply(self, request_builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match self {
+            Self::Bearer(token) => request_builder.bearer_auth(token),
+            Self::Basic { username, password } => request_builder.basic_auth(username, Some(password)),
+            Self::Header { name, value } => request_builder.header(name, value),
+        }
+    }
+
+    /// MQTT has no headers, so the credentials are sent as username and password of the connect packet: basic auth as
+    /// is, a bearer token as password with an empty username and a custom header as its name and value.
+    #[cfg(feature = "mqtt")]
+    pub(crate) fn into_mqtt_credentials(self) -> (String, String) {
+        match self {
+            Self::Bearer(token) => (String::new(), token),
+            Self::Basic { username, password } => (username, password),
+            Self::Header { name, value } => (name, value),
+        }
+    }
+}
+
+/// Provides the credentials for the requests to a node.
+#[async_trait]
+pub trait AuthProvider: Send + Sync + Debug {
+    /// Returns the credentials for the next request.
+    async fn credentials(&self) -> Result<Credentials>;
+
+    /// Called when the node rejected the credentials with a 401 status. Returns whether new credentials are available,
+    /// in which case the request is sent once more.
+    fn invalidate(&self) -> bool {
+        false
+    }
+}
+
+/// An [`AuthProvider`] that can be shared between nodes and compared by identity, so it can be part of a [`Node`].
+///
+/// [`Node`]: crate::node_manager::node::Node
+#[derive(Clone)]
+pub struct SharedAuthProvider(Arc<dyn AuthProvider>);
+
+impl SharedAuthProvider {
+    /// Wraps an auth provider.
+    pub fn new(auth_provider: impl AuthProvider + 'static) -> Self {
+        Self(Arc::new(auth_provider))
+    }
+}
+
+impl<P: AuthProvider + 'static> From<P> for SharedAuthProvider {
+    fn from(auth_provider: P) -> Self {
+        Self::new(auth_provider)
+    }
+}
+
+impl Deref for SharedAuthProvider {
+    type Target = dyn AuthProvider;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref()
+    }
+}
+
+impl Debug for SharedAuthProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl PartialEq for SharedAuthProvider {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for SharedAuthProvider {}
+
+impl Hash for SharedAuthProvider {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (Arc::as_ptr(&self.0) as *const () as usize).hash(state);
+    }
+}
+
+/// Static JWT, sent as bearer token.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JwtAuth(pub String);
+
+#[async_trait]
+impl AuthProvider for JwtAuth {
+    async fn credentials(&self) -> Result<Credentials> {
+        Ok(Credentials::Bearer(self.0.clone()))
+    }
+}
+
+/// HTTP basic auth.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BasicAuth {
+    /// Username
+    pub username: String,
+    /// Password
+    pub password: String,
+}
+
+#[async_trait]
+impl AuthProvider for BasicAuth {
+    async fn credentials(&self) -> Result<Credentials> {
+        Ok(Credentials::Basic {
+            username: self.username.clone(),
+            password: self.password.clone(),
+        })
+    }
+}
+
+/// Custom header, e.g. for an API key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HeaderAuth {
+    /// Header name
+    pub name: String,
+    /// Header value
+    pub value: String,
+}
+
+impl HeaderAuth {
+    /// Sends the key in the `X-API-Key` header.
+    pub fn api_key(key: &str) -> Self {
+        Self {
+            name: "X-API-Key".to_string(),
+            value: key.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl AuthProvider for HeaderAuth {
+    async fn credentials(&self) -> Result<Credentials> {
+        Ok(Credentials::Header {
+            name: self.name.clone(),
+            value: self.value.clone(),
+        })
+    }
+}
+
+/// Token returned by the callback of a [`RefreshableTokenAuth`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Token {
+    /// Token sent as bearer token
+    pub value: String,
+    /// Time after which a new token is requested, the token is used until the node rejects it if none is set
+    pub expires_in: Option<Duration>,
+}
+
+/// Future returned by the callback of a [`RefreshableTokenAuth`].
+pub type TokenFuture = Pin<Box<dyn Future<Output = Result<Token>> + Send>>;
+
+/// Bearer token that is requested from a callback, again when it expired or the node rejected it.
+pub struct RefreshableTokenAuth {
+    refresh: Box<dyn Fn() -> TokenFuture + Send + Sync>,
+    token: Mutex<Option<(String, Option<Instant>)>>,
+}
+
+impl RefreshableTokenAuth {
+    /// Creates the provider with the callback that returns a new token.
+    pub fn new<F: Fn() -> TokenFuture + Send + Sync + 'static>(refresh: F) -> Self {
+        Self {
+            refresh: Box::new(refresh),
+            token: Mutex::new(None),
+        }
+    }
+}
+
+impl Debug for RefreshableTokenAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RefreshableTokenAuth").finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl AuthProvider for RefreshableTokenAuth {
+    async fn credentials(&self) -> Result<Credentials> {
+        let cached = self
+            .token
+            .lock()
+            .map_err(|_| Error::PoisonError)?
+            .clone()
+            .filter(|(_, expires_at)| expires_at.is_none_or(|expires_at| Instant::now() < expires_at));
+        if let Some((token, _)) = cached {
+            return Ok(Credentials::Bearer(token));
+        }
+
+        // Concurrent requests might refresh the token at the same time, the last token is kept
+        let token = (self.refresh)().await?;
+        let expires_at = token.expires_in.map(|expires_in| Instant::now() + expires_in);
+        self.token
+            .lock()
+            .map_err(|_| Error::PoisonError)?
+            .replace((token.value.clone(), expires_at));
+        Ok(Credentials::Bearer(token.value))
+    }
+
+    fn invalidate(&self) -> bool {
+        if let Ok(mut token) = self.token.lock() {
+            token.take();
+        }
+        true
+    }
+}
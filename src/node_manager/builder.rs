@@ -4,7 +4,8 @@
 //! The node manager that takes care of sending requests with synced nodes and quorum if enabled
 
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
+    num::NonZeroUsize,
     sync::{Arc, RwLock},
     time::Duration,
 };
@@ -14,11 +15,21 @@ use url::Url;
 
 use crate::{
     builder::NetworkInfo,
-    constants::{DEFAULT_MIN_QUORUM_SIZE, DEFAULT_QUORUM_THRESHOLD, NODE_SYNC_INTERVAL},
+    constants::{
+        DEFAULT_MAX_CONCURRENT_REQUESTS, DEFAULT_MIN_QUORUM_SIZE, DEFAULT_QUORUM_THRESHOLD, NODE_SYNC_INTERVAL,
+    },
     error::{Error, Result},
     node_manager::{
+        auth::SharedAuthProvider,
+        cache::ResponseCache,
+        circuit_breaker::CircuitBreakerOptions,
         http_client::HttpClient,
+        limiter::{NodeRateLimit, RequestLimiter},
         node::{Node, NodeAuth, NodeDto},
+        node_stats::NodeStatsMap,
+        quorum::{QuorumEndpoint, QuorumPolicy},
+        retry::RetryPolicy,
+        transport::{ReqwestTransport, SharedTransport, TransportOptions},
         NodeManager,
     },
 };
@@ -53,6 +64,30 @@ pub struct NodeManagerBuilder {
     /// % of nodes that have to return the same response so it gets accepted
     #[serde(rename = "quorumThreshold", default = "default_quorum_threshold")]
     pub quorum_threshold: usize,
+    /// Quorum policies of endpoint classes that don't use the default policy
+    #[serde(rename = "quorumPolicies", default)]
+    pub quorum_policies: HashMap<QuorumEndpoint, QuorumPolicy>,
+    /// Options of the circuit breakers that skip failing nodes
+    #[serde(rename = "circuitBreaker", default)]
+    pub circuit_breaker: CircuitBreakerOptions,
+    /// Policy for retrying requests that failed with a transient error
+    #[serde(rename = "retryPolicy", default)]
+    pub retry_policy: RetryPolicy,
+    /// Transport that sends the requests, `reqwest` if none is set
+    #[serde(skip)]
+    pub transport: Option<SharedTransport>,
+    /// Proxy and TLS options of the default transport
+    #[serde(rename = "transportOptions", default)]
+    pub transport_options: TransportOptions,
+    /// Maximum amount of requests that are sent at the same time
+    #[serde(rename = "maxConcurrentRequests", default = "default_max_concurrent_requests")]
+    pub max_concurrent_requests: usize,
+    /// Rate limit per node, requests aren't limited if none is set
+    #[serde(rename = "nodeRateLimit", default)]
+    pub node_rate_limit: Option<NodeRateLimit>,
+    /// Maximum amount of cached responses of immutable resources, nothing is cached if none is set
+    #[serde(rename = "responseCacheCapacity", default)]
+    pub response_cache_capacity: Option<usize>,
 }
 
 fn default_node_sync_enabled() -> bool {
@@ -63,6 +98,10 @@ fn default_node_sync_interval() -> Duration {
     NODE_SYNC_INTERVAL
 }
 
+fn default_max_concurrent_requests() -> usize {
+    DEFAULT_MAX_CONCURRENT_REQUESTS
+}
+
 fn default_min_quorum_size() -> usize {
     DEFAULT_MIN_QUORUM_SIZE
 }
@@ -82,6 +121,7 @@ impl NodeManagerBuilder {
             url,
             auth: None,
             disabled: false,
+            auth_provider: None,
         }));
         Ok(self)
     }
@@ -100,6 +140,7 @@ impl NodeManagerBuilder {
             url,
             auth,
             disabled: false,
+            auth_provider: None,
         }));
         Ok(self)
     }
@@ -118,6 +159,7 @@ impl NodeManagerBuilder {
             url,
             auth,
             disabled: false,
+            auth_provider: None,
         }));
         Ok(self)
     }
@@ -138,6 +180,7 @@ impl NodeManagerBuilder {
                     url,
                     auth,
                     disabled: false,
+                    auth_provider: None,
                 }));
             }
             None => {
@@ -146,6 +189,7 @@ impl NodeManagerBuilder {
                     url,
                     auth,
                     disabled: false,
+                    auth_provider: None,
                 }));
                 self.permanodes.replace(permanodes);
             }
@@ -172,10 +216,41 @@ impl NodeManagerBuilder {
             url,
             auth,
             disabled: false,
+            auth_provider: None,
         }));
         Ok(self)
     }
 
+    pub(crate) fn with_auth_provider(mut self, url: &str, auth_provider: SharedAuthProvider) -> Result<Self> {
+        let url = without_credentials(validate_url(Url::parse(url)?)?);
+        let mut found = false;
+        let mut attach = |node: NodeDto| {
+            let mut with_provider = Node::from(&node);
+            if without_credentials(with_provider.url.clone()) == url {
+                found = true;
+                with_provider.auth_provider.replace(auth_provider.clone());
+                NodeDto::Node(with_provider)
+            } else {
+                node
+            }
+        };
+        self.primary_node = self.primary_node.map(&mut attach);
+        self.primary_pow_node = self.primary_pow_node.map(&mut attach);
+        self.nodes = self.nodes.into_iter().map(&mut attach).collect();
+        self.permanodes = self
+            .permanodes
+            .map(|permanodes| permanodes.into_iter().map(&mut attach).collect());
+        if !found {
+            self.nodes.insert(NodeDto::Node(Node {
+                url,
+                auth: None,
+                disabled: false,
+                auth_provider: Some(auth_provider),
+            }));
+        }
+        Ok(self)
+    }
+
     pub(crate) fn with_nodes(mut self, urls: &[&str]) -> Result<Self> {
         for url in urls {
             let url = validate_url(Url::parse(url)?)?;
@@ -183,6 +258,7 @@ impl NodeManagerBuilder {
                 url,
                 auth: None,
                 disabled: false,
+                auth_provider: None,
             }));
         }
         Ok(self)
@@ -208,6 +284,46 @@ impl NodeManagerBuilder {
         self
     }
 
+    pub(crate) fn with_quorum_policy(mut self, endpoint: QuorumEndpoint, policy: QuorumPolicy) -> Self {
+        self.quorum_policies.insert(endpoint, policy);
+        self
+    }
+
+    pub(crate) fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub(crate) fn with_transport(mut self, transport: SharedTransport) -> Self {
+        self.transport.replace(transport);
+        self
+    }
+
+    pub(crate) fn with_transport_options(mut self, transport_options: TransportOptions) -> Self {
+        self.transport_options = transport_options;
+        self
+    }
+
+    pub(crate) fn with_max_concurrent_requests(mut self, max_concurrent_requests: usize) -> Self {
+        self.max_concurrent_requests = max_concurrent_requests;
+        self
+    }
+
+    pub(crate) fn with_node_rate_limit(mut self, node_rate_limit: NodeRateLimit) -> Self {
+        self.node_rate_limit.replace(node_rate_limit);
+        self
+    }
+
+    pub(crate) fn with_response_cache(mut self, capacity: usize) -> Self {
+        self.response_cache_capacity.replace(capacity);
+        self
+    }
+
+    pub(crate) fn with_circuit_breaker_options(mut self, options: CircuitBreakerOptions) -> Self {
+        self.circuit_breaker = options;
+        self
+    }
+
     pub(crate) fn add_default_nodes(mut self, network_info: &NetworkInfo) -> Result<Self> {
         let default_testnet_nodes = vec![];
         if self.nodes.is_empty() && self.primary_node.is_none() {
@@ -226,11 +342,30 @@ impl NodeManagerBuilder {
         Ok(self)
     }
 
-    pub(crate) fn build(self, synced_nodes: Arc<RwLock<HashSet<Node>>>) -> NodeManager {
+    // The transport options only apply to the default transport
+    pub(crate) fn http_client(&self) -> Result<HttpClient> {
+        let transport = match &self.transport {
+            Some(transport) => transport.clone(),
+            None => ReqwestTransport::with_options(&self.transport_options)?.into(),
+        };
+        Ok(HttpClient::new()
+            .with_retry_policy(self.retry_policy.clone())
+            .with_transport(transport)
+            .with_limiter(RequestLimiter::new(self.max_concurrent_requests, self.node_rate_limit)))
+    }
+
+    pub(crate) fn build(
+        self,
+        http_client: HttpClient,
+        nodes: Arc<RwLock<HashSet<Node>>>,
+        synced_nodes: Arc<RwLock<HashSet<Node>>>,
+        node_stats: NodeStatsMap,
+        offline: bool,
+    ) -> NodeManager {
         NodeManager {
-            primary_node: self.primary_node.map(|node| node.into()),
+            primary_node: Arc::new(RwLock::new(self.primary_node.map(|node| node.into()))),
             primary_pow_node: self.primary_pow_node.map(|node| node.into()),
-            nodes: self.nodes.into_iter().map(|node| node.into()).collect(),
+            nodes,
             permanodes: self
                 .permanodes
                 .map(|nodes| nodes.into_iter().map(|node| node.into()).collect()),
@@ -240,7 +375,15 @@ impl NodeManagerBuilder {
             quorum: self.quorum,
             min_quorum_size: self.min_quorum_size,
             quorum_threshold: self.quorum_threshold,
-            http_client: HttpClient::new(),
+            quorum_policies: self.quorum_policies,
+            quorum_report_listeners: Default::default(),
+            http_client,
+            node_stats,
+            response_cache: self
+                .response_cache_capacity
+                .and_then(NonZeroUsize::new)
+                .map(|capacity| Arc::new(ResponseCache::new(capacity))),
+            offline,
         }
     }
 }
@@ -257,10 +400,24 @@ impl Default for NodeManagerBuilder {
             quorum: false,
             min_quorum_size: DEFAULT_MIN_QUORUM_SIZE,
             quorum_threshold: DEFAULT_QUORUM_THRESHOLD,
+            quorum_policies: HashMap::new(),
+            circuit_breaker: CircuitBreakerOptions::default(),
+            retry_policy: RetryPolicy::default(),
+            transport: None,
+            transport_options: TransportOptions::default(),
+            max_concurrent_requests: DEFAULT_MAX_CONCURRENT_REQUESTS,
+            node_rate_limit: None,
+            response_cache_capacity: None,
         }
     }
 }
 
+fn without_credentials(mut url: Url) -> Url {
+    let _ = url.set_username("");
+    let _ = url.set_password(None);
+    url
+}
+
 /// Validates if the url starts with http or https
 pub fn validate_url(url: Url) -> Result<Url> {
     if url.scheme() != "http" && url.scheme() != "https" {
@@ -0,0 +1,179 @@
+// Copyright 2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Cache for the responses of resources that never change once they exist, like blocks and milestones
+
+use std::{
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+use bee_block::{
+    payload::{milestone::MilestoneId, transaction::TransactionId},
+    BlockId,
+};
+use lru::LruCache;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{Error, Result};
+
+/// An immutable resource whose responses can be cached.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(tag = "type", content = "id", rename_all = "camelCase")]
+pub enum CachedResource {
+    /// Block by its ID
+    Block(BlockId),
+    /// Block that was included in the ledger for a transaction
+    IncludedBlock(TransactionId),
+    /// Milestone by its ID
+    MilestoneById(MilestoneId),
+    /// Milestone by its index
+    MilestoneByIndex(u32),
+    /// UTXO changes of a milestone by its ID
+    UtxoChangesById(MilestoneId),
+    /// UTXO changes of a milestone by its index
+    UtxoChangesByIndex(u32),
+}
+
+/// Usage of the response cache.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ResponseCacheStats {
+    /// Maximum amount of cached responses
+    pub capacity: usize,
+    /// Amount of cached responses, the JSON and the binary representation of a resource are counted separately
+    pub entries: usize,
+    /// Requests that were answered from the cache
+    pub hits: u64,
+    /// Requests that were sent to a node, because the response wasn't cached
+    pub misses: u64,
+}
+
+#[derive(Debug, Clone)]
+enum CachedResponse {
+    Json(Value),
+    Bytes(Vec<u8>),
+}
+
+// The least recently used responses are evicted once the capacity is reached
+#[derive(Debug)]
+pub(crate) struct ResponseCache {
+    responses: Mutex<LruCache<(CachedResource, bool), CachedResponse>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl ResponseCache {
+    pub(crate) fn new(capacity: NonZeroUsize) -> Self {
+        Self {
+            responses: Mutex::new(LruCache::new(capacity)),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    fn get(&self, resource: CachedResource, bytes: bool) -> Result<Option<CachedResponse>> {
+        let response = self
+            .responses
+            .lock()
+            .map_err(|_| Error::PoisonError)?
+            .get(&(resource, bytes))
+            .cloned();
+        let counter = if response.is_some() { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        Ok(response)
+    }
+
+    fn put(&self, resource: CachedResource, response: CachedResponse) -> Result<()> {
+        let bytes = matches!(response, CachedResponse::Bytes(_));
+        self.responses
+            .lock()
+            .map_err(|_| Error::PoisonError)?
+            .put((resource, bytes), response);
+        Ok(())
+    }
+
+    pub(crate) fn get_json(&self, resource: CachedResource) -> Result<Option<Value>> {
+        Ok(match self.get(resource, false)? {
+            Some(CachedResponse::Json(json)) => Some(json),
+            _ => None,
+        })
+    }
+
+    pub(crate) fn put_json(&self, resource: CachedResource, json: Value) -> Result<()> {
+        self.put(resource, CachedResponse::Json(json))
+    }
+
+    pub(crate) fn get_bytes(&self, resource: CachedResource) -> Result<Option<Vec<u8>>> {
+        Ok(match self.get(resource, true)? {
+            Some(CachedResponse::Bytes(bytes)) => Some(bytes),
+            _ => None,
+        })
+    }
+
+    pub(crate) fn put_bytes(&self, resource: CachedResource, bytes: Vec<u8>) -> Result<()> {
+        self.put(resource, CachedResponse::Bytes(bytes))
+    }
+
+    /// Removes both representations of the resource.
+    pub(crate) fn invalidate(&self, resource: CachedResource) -> Result<()> {
+        let mut responses = self.responses.lock().map_err(|_| Error::PoisonError)?;
+        responses.pop(&(resource, false));
+        responses.pop(&(resource, true));
+        Ok(())
+    }
+
+    pub(crate) fn clear(&self) -> Result<()> {
+        self.responses.lock().map_err(|_| Error::PoisonError)?.clear();
+        Ok(())
+    }
+
+    pub(crate) fn stats(&self) -> Result<ResponseCacheStats> {
+        let responses = self.responses.lock().map_err(|_| Error::PoisonError)?;
+        Ok(ResponseCacheStats {
+            capacity: responses.cap().get(),
+            entries: responses.len(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let cache = ResponseCache::new(NonZeroUsize::new(2).unwrap());
+        let (first, second, third) = (
+            CachedResource::MilestoneByIndex(1),
+            CachedResource::MilestoneByIndex(2),
+            CachedResource::MilestoneByIndex(3),
+        );
+        cache.put_json(first, Value::from(1)).unwrap();
+        cache.put_bytes(second, vec![2]).unwrap();
+        assert_eq!(cache.get_json(first).unwrap(), Some(Value::from(1)));
+        cache.put_json(third, Value::from(3)).unwrap();
+
+        assert_eq!(cache.get_bytes(second).unwrap(), None);
+        assert_eq!(cache.get_json(first).unwrap(), Some(Value::from(1)));
+        // The representations are cached separately
+        assert_eq!(cache.get_bytes(third).unwrap(), None);
+
+        cache.invalidate(first).unwrap();
+        assert_eq!(cache.get_json(first).unwrap(), None);
+        assert_eq!(
+            cache.stats().unwrap(),
+            ResponseCacheStats {
+                capacity: 2,
+                entries: 1,
+                hits: 2,
+                misses: 3,
+            }
+        );
+    }
+}
@@ -0,0 +1,79 @@
+// Copyright 2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! The plugins and features of the nodes, from their routes and node info
+
+use serde::{Deserialize, Serialize};
+
+/// A plugin or feature of a node that requests can depend on.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum NodeCapability {
+    /// The indexer plugin, for the output ID queries
+    Indexer,
+    /// The MQTT plugin, for the event subscriptions
+    Mqtt,
+    /// Remote proof of work, for blocks posted without nonce
+    Pow,
+    /// The participation plugin, for voting and staking events
+    Participation,
+    /// The proof-of-inclusion plugin
+    ProofOfInclusion,
+}
+
+impl NodeCapability {
+    const PLUGINS: [Self; 4] = [Self::Indexer, Self::Mqtt, Self::Participation, Self::ProofOfInclusion];
+
+    /// The route of the plugin in the routes of a node, `None` for remote PoW, which the node info lists as feature.
+    pub fn route(&self) -> Option<&'static str> {
+        match self {
+            Self::Indexer => Some("indexer/v1"),
+            Self::Mqtt => Some("mqtt/v1"),
+            Self::Pow => None,
+            Self::Participation => Some("participation/v1"),
+            Self::ProofOfInclusion => Some("poi/v1"),
+        }
+    }
+
+    // The plugin the path of a request belongs to, `None` for the core API
+    pub(crate) fn of_path(path: &str) -> Option<Self> {
+        let route = path.strip_prefix("api/")?;
+        Self::PLUGINS.into_iter().find(|capability| {
+            capability
+                .route()
+                .is_some_and(|plugin_route| route.starts_with(plugin_route))
+        })
+    }
+}
+
+impl std::fmt::Display for NodeCapability {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.route() {
+            Some(route) => write!(f, "{route}"),
+            None => write!(f, "remote PoW"),
+        }
+    }
+}
+
+/// The routes and features a node reported during the last node sync.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NodeCapabilities {
+    /// The routes of the core API and the plugins, e.g. `indexer/v1`. `None` if the node doesn't list its routes, then
+    /// it's assumed to have all plugins.
+    pub routes: Option<Vec<String>>,
+    /// The features of the node info, e.g. `pow`
+    pub features: Vec<String>,
+}
+
+impl NodeCapabilities {
+    /// Whether the node has the plugin or feature.
+    pub fn supports(&self, capability: NodeCapability) -> bool {
+        match capability.route() {
+            Some(route) => self
+                .routes
+                .as_ref()
+                .is_none_or(|routes| routes.iter().any(|r| r == route)),
+            None => self.features.iter().any(|feature| feature == "pow"),
+        }
+    }
+}
@@ -0,0 +1,389 @@
+// Copyright 2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Cassettes with the requests to the nodes and their responses, recorded once and replayed without a node
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    node_manager::{
+        retry::RetryableError,
+        transport::{ReqwestTransport, SharedTransport, Transport, TransportRequest, TransportResponse},
+    },
+    Error, Result,
+};
+
+/// Whether the requests are sent to the nodes and recorded, or answered from the cassette.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum CassetteMode {
+    /// Sends the requests and appends them with their responses to the cassette file
+    Record,
+    /// Answers the requests with the responses from the cassette file, without sending them
+    Replay,
+}
+
+/// The [`Transport`] method of a request.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum RequestKind {
+    /// GET for JSON
+    Get,
+    /// GET for the binary representation
+    GetBytes,
+    /// POST with a JSON body
+    PostJson,
+    /// POST with the binary representation as body
+    PostBytes,
+    /// DELETE
+    Delete,
+}
+
+impl RequestKind {
+    fn method(self) -> &'static str {
+        match self {
+            Self::Get | Self::GetBytes => "GET",
+            Self::PostJson | Self::PostBytes => "POST",
+            Self::Delete => "DELETE",
+        }
+    }
+
+    fn is_binary(self) -> bool {
+        matches!(self, Self::GetBytes | Self::PostBytes)
+    }
+}
+
+/// A request or response body, JSON is kept as is so cassettes can be read and edited.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Body {
+    /// JSON body
+    Json(Value),
+    /// Any other body, hex encoded
+    Binary(String),
+}
+
+impl Body {
+    // Bodies of binary requests are kept as they are, even if they happen to be valid JSON
+    fn from_bytes(bytes: &[u8], kind: RequestKind) -> Self {
+        match serde_json::from_slice(bytes) {
+            Ok(json) if !kind.is_binary() => Self::Json(json),
+            _ => Self::Binary(prefix_hex::encode(bytes)),
+        }
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        match self {
+            Self::Json(json) => Ok(serde_json::to_vec(json)?),
+            Self::Binary(hex) => {
+                prefix_hex::decode(hex).map_err(|e| Error::CassetteError(format!("invalid binary body: {e}")))
+            }
+        }
+    }
+}
+
+/// The error of a request that failed without a response, e.g. because it timed out.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct InteractionError {
+    /// The error message
+    pub message: String,
+    /// The kind of the error, if it can be retried
+    #[serde(default)]
+    pub kind: Option<RetryableError>,
+}
+
+/// A request to a node and its response, or the error if there was no response.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Interaction {
+    /// Transport method of the request
+    pub kind: RequestKind,
+    /// Path of the request
+    pub path: String,
+    /// Query of the request
+    #[serde(default)]
+    pub query: Option<String>,
+    /// Body of a POST request
+    #[serde(rename = "requestBody", default)]
+    pub request_body: Option<Body>,
+    /// Status code of the response, `None` if there was no response
+    #[serde(default)]
+    pub status: Option<u16>,
+    /// Headers of the response with lowercase names
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Body of the response
+    #[serde(rename = "responseBody", default)]
+    pub response_body: Option<Body>,
+    /// Error of a request without a response
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<InteractionError>,
+}
+
+impl Interaction {
+    fn matches(&self, kind: RequestKind, request: &TransportRequest, body: Option<&Body>) -> bool {
+        self.kind == kind
+            && self.path == request.url.path()
+            && self.query.as_deref() == request.url.query()
+            && body.is_none_or(|body| self.request_body.as_ref() == Some(body))
+    }
+}
+
+/// Recorded interactions with the nodes, in the order they happened. The file has one JSON interaction per line, so
+/// recordings are appended without rewriting it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Cassette {
+    /// Interactions
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    /// Reads a cassette from a file with one JSON interaction per line.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let lines = fs::read_to_string(path)
+            .map_err(|e| Error::CassetteError(format!("can't read {}: {e}", path.display())))?;
+        let interactions = lines
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<std::result::Result<_, _>>()?;
+        Ok(Self { interactions })
+    }
+
+    /// Writes the cassette to a file with one JSON interaction per line.
+    pub fn to_file(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let mut lines = Vec::new();
+        for interaction in &self.interactions {
+            serde_json::to_writer(&mut lines, interaction)?;
+            lines.push(b'\n');
+        }
+        fs::write(path, lines).map_err(|e| Error::CassetteError(format!("can't write {}: {e}", path.display())))
+    }
+}
+
+/// A [`Transport`] that sends the requests with another transport and records them with their responses, or their
+/// errors if they failed without a response. Interactions are appended to an existing cassette file.
+#[derive(Debug)]
+pub struct CassetteRecorder {
+    transport: SharedTransport,
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl CassetteRecorder {
+    /// Creates a recorder that sends the requests with `reqwest`.
+    pub fn new(path: impl AsRef<Path>) -> Result<Self> {
+        Self::with_transport(path, ReqwestTransport::new())
+    }
+
+    /// Creates a recorder that sends the requests with the transport.
+    pub fn with_transport(path: impl AsRef<Path>, transport: impl Into<SharedTransport>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| Error::CassetteError(format!("can't open {}: {e}", path.display())))?;
+        Ok(Self {
+            transport: transport.into(),
+            path: path.to_path_buf(),
+            file: Mutex::new(file),
+        })
+    }
+
+    // Every interaction is appended right away, so the recording is complete even if the process aborts
+    fn record(
+        &self,
+        kind: RequestKind,
+        request: &TransportRequest,
+        request_body: Option<Body>,
+        response: Result<TransportResponse>,
+    ) -> Result<TransportResponse> {
+        let interaction = Interaction {
+            kind,
+            path: request.url.path().to_string(),
+            query: request.url.query().map(ToString::to_string),
+            request_body,
+            status: response.as_ref().ok().map(|response| response.status),
+            headers: response
+                .as_ref()
+                .map(|response| response.headers.clone())
+                .unwrap_or_default(),
+            response_body: response
+                .as_ref()
+                .ok()
+                .map(|response| Body::from_bytes(&response.body, kind)),
+            error: response.as_ref().err().map(|error| InteractionError {
+                message: error.to_string(),
+                kind: RetryableError::of(error),
+            }),
+        };
+        let mut line = serde_json::to_vec(&interaction)?;
+        line.push(b'\n');
+        self.file
+            .lock()
+            .map_err(|_| Error::PoisonError)?
+            .write_all(&line)
+            .map_err(|e| Error::CassetteError(format!("can't write {}: {e}", self.path.display())))?;
+        response
+    }
+}
+
+#[async_trait]
+impl Transport for CassetteRecorder {
+    async fn get(&self, request: TransportRequest) -> Result<TransportResponse> {
+        let response = self.transport.get(request.clone()).await;
+        self.record(RequestKind::Get, &request, None, response)
+    }
+
+    async fn get_bytes(&self, request: TransportRequest) -> Result<TransportResponse> {
+        let response = self.transport.get_bytes(request.clone()).await;
+        self.record(RequestKind::GetBytes, &request, None, response)
+    }
+
+    async fn post_json(&self, request: TransportRequest, json: &Value) -> Result<TransportResponse> {
+        let response = self.transport.post_json(request.clone(), json).await;
+        self.record(
+            RequestKind::PostJson,
+            &request,
+            Some(Body::Json(json.clone())),
+            response,
+        )
+    }
+
+    async fn post_bytes(&self, request: TransportRequest, body: &[u8]) -> Result<TransportResponse> {
+        let response = self.transport.post_bytes(request.clone(), body).await;
+        self.record(
+            RequestKind::PostBytes,
+            &request,
+            Some(Body::from_bytes(body, RequestKind::PostBytes)),
+            response,
+        )
+    }
+
+    async fn delete(&self, request: TransportRequest) -> Result<TransportResponse> {
+        let response = self.transport.delete(request.clone()).await;
+        self.record(RequestKind::Delete, &request, None, response)
+    }
+}
+
+/// A [`Transport`] that answers the requests from a cassette, without sending them.
+///
+/// Requests are matched by their method, path and query. Interactions are replayed in the recorded order, once all
+/// matching ones were replayed the last one is repeated. Recorded errors are returned as [`Error::TransportError`]. A
+/// request without a matching interaction fails with [`Error::CassetteError`].
+#[derive(Debug)]
+pub struct CassettePlayer {
+    cassette: Cassette,
+    match_request_bodies: bool,
+    replayed: Mutex<HashSet<usize>>,
+}
+
+impl CassettePlayer {
+    /// Creates a player for the cassette.
+    pub fn new(cassette: Cassette) -> Self {
+        Self {
+            cassette,
+            match_request_bodies: false,
+            replayed: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Creates a player for the cassette in the JSON file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        Cassette::from_file(path).map(Self::new)
+    }
+
+    /// Sets whether the bodies of POST requests have to match too. They don't by default, since blocks with a nonce
+    /// from local PoW differ between runs.
+    pub fn match_request_bodies(mut self, match_request_bodies: bool) -> Self {
+        self.match_request_bodies = match_request_bodies;
+        self
+    }
+
+    fn replay(
+        &self,
+        kind: RequestKind,
+        request: &TransportRequest,
+        request_body: Option<Body>,
+    ) -> Result<TransportResponse> {
+        let request_body = request_body.filter(|_| self.match_request_bodies);
+        let matching = self
+            .cassette
+            .interactions
+            .iter()
+            .enumerate()
+            .filter(|(_, interaction)| interaction.matches(kind, request, request_body.as_ref()))
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+
+        let mut replayed = self.replayed.lock().map_err(|_| Error::PoisonError)?;
+        let index = matching
+            .iter()
+            .find(|index| !replayed.contains(index))
+            .or_else(|| matching.last())
+            .copied()
+            .ok_or_else(|| {
+                let error = format!("no recorded interaction for {} {}", kind.method(), request.url);
+                log::error!("{error}");
+                Error::CassetteError(error)
+            })?;
+        replayed.insert(index);
+
+        let interaction = &self.cassette.interactions[index];
+        if let Some(error) = &interaction.error {
+            return Err(Error::TransportError {
+                message: error.message.clone(),
+                kind: error.kind,
+            });
+        }
+        Ok(TransportResponse {
+            status: interaction.status.ok_or_else(|| {
+                Error::CassetteError(format!("interaction {index} has neither a status nor an error"))
+            })?,
+            headers: interaction.headers.clone(),
+            body: match &interaction.response_body {
+                Some(body) => body.to_bytes()?,
+                None => Vec::new(),
+            },
+        })
+    }
+}
+
+#[async_trait]
+impl Transport for CassettePlayer {
+    async fn get(&self, request: TransportRequest) -> Result<TransportResponse> {
+        self.replay(RequestKind::Get, &request, None)
+    }
+
+    async fn get_bytes(&self, request: TransportRequest) -> Result<TransportResponse> {
+        self.replay(RequestKind::GetBytes, &request, None)
+    }
+
+    async fn post_json(&self, request: TransportRequest, json: &Value) -> Result<TransportResponse> {
+        self.replay(RequestKind::PostJson, &request, Some(Body::Json(json.clone())))
+    }
+
+    async fn post_bytes(&self, request: TransportRequest, body: &[u8]) -> Result<TransportResponse> {
+        self.replay(
+            RequestKind::PostBytes,
+            &request,
+            Some(Body::from_bytes(body, RequestKind::PostBytes)),
+        )
+    }
+
+    async fn delete(&self, request: TransportRequest) -> Result<TransportResponse> {
+        self.replay(RequestKind::Delete, &request, None)
+    }
+}
@@ -0,0 +1,241 @@
+// Copyright 2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Circuit breaker that skips nodes after consecutive failures for an exponentially growing cooldown
+
+use std::time::Duration;
+#[cfg(not(target_family = "wasm"))]
+use std::time::Instant;
+
+#[cfg(target_family = "wasm")]
+use instant::Instant;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::constants::{
+    DEFAULT_CIRCUIT_BREAKER_COOLDOWN, DEFAULT_CIRCUIT_BREAKER_FAILURE_THRESHOLD, DEFAULT_CIRCUIT_BREAKER_MAX_COOLDOWN,
+};
+
+/// Options of the circuit breaker that every node has.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[must_use]
+pub struct CircuitBreakerOptions {
+    #[serde(default = "default_failure_threshold", rename = "failureThreshold")]
+    pub(crate) failure_threshold: u32,
+    #[serde(default = "default_cooldown")]
+    pub(crate) cooldown: Duration,
+    #[serde(default = "default_max_cooldown", rename = "maxCooldown")]
+    pub(crate) max_cooldown: Duration,
+}
+
+fn default_failure_threshold() -> u32 {
+    DEFAULT_CIRCUIT_BREAKER_FAILURE_THRESHOLD
+}
+
+fn default_cooldown() -> Duration {
+    DEFAULT_CIRCUIT_BREAKER_COOLDOWN
+}
+
+fn default_max_cooldown() -> Duration {
+    DEFAULT_CIRCUIT_BREAKER_MAX_COOLDOWN
+}
+
+impl Default for CircuitBreakerOptions {
+    fn default() -> Self {
+        Self {
+            failure_threshold: default_failure_threshold(),
+            cooldown: default_cooldown(),
+            max_cooldown: default_max_cooldown(),
+        }
+    }
+}
+
+impl CircuitBreakerOptions {
+    /// Creates the default circuit breaker options.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets after how many consecutive failed requests a node is skipped, 0 disables the circuit breaker.
+    pub fn failure_threshold(mut self, failure_threshold: u32) -> Self {
+        self.failure_threshold = failure_threshold;
+        self
+    }
+
+    /// Sets how long a node is skipped the first time, the cooldown doubles every time the probe afterwards fails.
+    pub fn cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// Sets the upper limit of the growing cooldown.
+    pub fn max_cooldown(mut self, max_cooldown: Duration) -> Self {
+        self.max_cooldown = max_cooldown;
+        self
+    }
+}
+
+/// State of the circuit breaker of a node.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum CircuitState {
+    /// Requests are sent to the node.
+    Closed,
+    /// The node failed too often and is skipped until the cooldown elapsed.
+    Open,
+    /// The cooldown elapsed, the next request is a probe that decides whether the node is used again.
+    HalfOpen,
+}
+
+/// Change of the circuit breaker state of a node, passed to the callbacks registered with
+/// [`Client::on_circuit_state_change()`](crate::Client::on_circuit_state_change).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CircuitStateChange {
+    /// Node url, without credentials
+    pub url: Url,
+    /// State before the change
+    #[serde(rename = "previousState")]
+    pub previous_state: CircuitState,
+    /// New state
+    pub state: CircuitState,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct CircuitBreaker {
+    state: CircuitState,
+    consecutive_failures: u32,
+    // Times the breaker opened without a successful request in between, the cooldown doubles for each
+    openings: u32,
+    // End of the cooldown while open, end of the probe while half-open
+    until: Option<Instant>,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self {
+            state: CircuitState::Closed,
+            consecutive_failures: 0,
+            openings: 0,
+            until: None,
+        }
+    }
+}
+
+impl CircuitBreaker {
+    pub(crate) fn state(&self) -> CircuitState {
+        self.state
+    }
+
+    /// Returns whether a request could be sent to the node, without taking the probe.
+    pub(crate) fn is_available(&self, now: Instant) -> bool {
+        match self.state {
+            CircuitState::Closed => true,
+            CircuitState::Open | CircuitState::HalfOpen => self.until.is_none_or(|until| now >= until),
+        }
+    }
+
+    /// Returns whether a request can be sent to the node. After the cooldown only one request gets through as probe,
+    /// if its outcome isn't recorded within another cooldown, the next request becomes the probe.
+    pub(crate) fn try_acquire(&mut self, options: &CircuitBreakerOptions, now: Instant) -> bool {
+        match self.state {
+            CircuitState::Closed => true,
+            CircuitState::Open | CircuitState::HalfOpen => {
+                if self.until.is_none_or(|until| now >= until) {
+                    self.state = CircuitState::HalfOpen;
+                    self.until.replace(now + self.cooldown(options));
+                    true
+                } else {
+                    false
+                }
+            }
+        }
+    }
+
+    /// Updates the state with the outcome of a request to the node.
+    pub(crate) fn record(&mut self, failed: bool, options: &CircuitBreakerOptions, now: Instant) {
+        if !failed {
+            *self = Self::default();
+            return;
+        }
+        self.consecutive_failures += 1;
+        let open = match self.state {
+            CircuitState::Closed => {
+                options.failure_threshold > 0 && self.consecutive_failures >= options.failure_threshold
+            }
+            CircuitState::HalfOpen => true,
+            // Failures of requests that were sent before the breaker opened don't extend the cooldown
+            CircuitState::Open => false,
+        };
+        if open {
+            self.openings += 1;
+            self.state = CircuitState::Open;
+            self.until.replace(now + self.cooldown(options));
+        }
+    }
+
+    fn cooldown(&self, options: &CircuitBreakerOptions) -> Duration {
+        let factor = 2u32.saturating_pow(self.openings.saturating_sub(1));
+        options
+            .cooldown
+            .checked_mul(factor)
+            .map_or(options.max_cooldown, |cooldown| cooldown.min(options.max_cooldown))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cooldown_grows_until_probe_succeeds() {
+        let options = CircuitBreakerOptions::new()
+            .failure_threshold(2)
+            .cooldown(Duration::from_secs(10))
+            .max_cooldown(Duration::from_secs(25));
+        let mut breaker = CircuitBreaker::default();
+        let start = Instant::now();
+
+        breaker.record(true, &options, start);
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        breaker.record(true, &options, start);
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(!breaker.is_available(start + Duration::from_secs(9)));
+        assert!(!breaker.try_acquire(&options, start + Duration::from_secs(9)));
+
+        // Only a single probe after the cooldown
+        let after_cooldown = start + Duration::from_secs(10);
+        assert!(breaker.is_available(after_cooldown));
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(breaker.try_acquire(&options, after_cooldown));
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        assert!(!breaker.is_available(after_cooldown));
+        assert!(!breaker.try_acquire(&options, after_cooldown));
+
+        // A failed probe doubles the cooldown
+        breaker.record(true, &options, after_cooldown);
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(!breaker.try_acquire(&options, after_cooldown + Duration::from_secs(19)));
+        assert!(breaker.try_acquire(&options, after_cooldown + Duration::from_secs(20)));
+
+        // Capped by the max cooldown
+        breaker.record(true, &options, after_cooldown);
+        assert!(breaker.try_acquire(&options, after_cooldown + Duration::from_secs(25)));
+
+        breaker.record(false, &options, after_cooldown);
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert_eq!(breaker.cooldown(&options), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn disabled_with_zero_threshold() {
+        let options = CircuitBreakerOptions::new().failure_threshold(0);
+        let mut breaker = CircuitBreaker::default();
+        let now = Instant::now();
+
+        for _ in 0..10 {
+            breaker.record(true, &options, now);
+        }
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert!(breaker.try_acquire(&options, now));
+    }
+}
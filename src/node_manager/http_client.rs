@@ -7,115 +7,270 @@ use std::time::Duration;
 
 use serde::de::DeserializeOwned;
 use serde_json::Value;
+use url::Url;
 
 use crate::{
+    constants::DEFAULT_MAX_CONCURRENT_REQUESTS,
     error::{Error, Result},
-    node_manager::node::Node,
+    node_manager::{
+        auth::Credentials,
+        limiter::RequestLimiter,
+        metrics::{RequestInfo, RequestMonitor},
+        node::Node,
+        retry::RetryPolicy,
+        transport::{ReqwestTransport, SharedTransport, TransportRequest, TransportResponse},
+    },
 };
 
-pub(crate) struct Response(reqwest::Response);
+pub(crate) struct Response(TransportResponse);
 
 impl Response {
     pub(crate) fn status(&self) -> u16 {
-        self.0.status().as_u16()
+        self.0.status
     }
 
     pub(crate) async fn into_json<T: DeserializeOwned>(self) -> Result<T> {
-        self.0.json().await.map_err(Into::into)
+        serde_json::from_slice(&self.0.body).map_err(Into::into)
     }
 
     pub(crate) async fn into_text(self) -> Result<String> {
-        self.0.text().await.map_err(Into::into)
+        Ok(String::from_utf8_lossy(&self.0.body).into_owned())
     }
 
     pub(crate) async fn into_bytes(self) -> Result<Vec<u8>> {
-        self.0.bytes().await.map(|b| b.to_vec()).map_err(Into::into)
+        Ok(self.0.body)
     }
 }
 
-#[derive(Clone)]
+/// Whether a request that failed with a transient error can be sent again.
+#[derive(Clone, Copy)]
+pub(crate) enum Retry<'a> {
+    /// Sending the request again has no other effect, like for all GET requests
+    Idempotent,
+    /// The request is only sent again if the resource at the path doesn't exist, otherwise its response is returned
+    UnlessFound(&'a str),
+    Never,
+}
+
+#[derive(Clone, Debug)]
 pub(crate) struct HttpClient {
-    client: reqwest::Client,
+    transport: SharedTransport,
+    retry_policy: RetryPolicy,
+    monitor: RequestMonitor,
+    limiter: RequestLimiter,
+}
+
+// Requests of the same kind, sent again for retries
+#[derive(Clone, Copy)]
+enum Method<'a> {
+    Get,
+    GetBytes,
+    PostJson(&'a Value),
+    PostBytes(&'a [u8]),
+    Delete,
+}
+
+impl Method<'_> {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Get | Self::GetBytes => "GET",
+            Self::PostJson(_) | Self::PostBytes(_) => "POST",
+            Self::Delete => "DELETE",
+        }
+    }
+
+    fn body_len(self) -> usize {
+        match self {
+            Self::Get | Self::GetBytes | Self::Delete => 0,
+            Self::PostJson(json) => json.to_string().len(),
+            Self::PostBytes(body) => body.len(),
+        }
+    }
 }
 
 impl HttpClient {
     pub(crate) fn new() -> Self {
         Self {
-            client: reqwest::Client::new(),
+            transport: SharedTransport::new(ReqwestTransport::new()),
+            retry_policy: RetryPolicy::default(),
+            monitor: RequestMonitor::default(),
+            limiter: RequestLimiter::new(DEFAULT_MAX_CONCURRENT_REQUESTS, None),
         }
     }
 
-    async fn parse_response(response: reqwest::Response, url: &url::Url) -> Result<Response> {
-        let status = response.status();
-        if status.is_success() {
+    pub(crate) fn with_transport(mut self, transport: SharedTransport) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    pub(crate) fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub(crate) fn with_limiter(mut self, limiter: RequestLimiter) -> Self {
+        self.limiter = limiter;
+        self
+    }
+
+    pub(crate) fn monitor(&self) -> &RequestMonitor {
+        &self.monitor
+    }
+
+    pub(crate) fn limiter(&self) -> &RequestLimiter {
+        &self.limiter
+    }
+
+    fn parse_response(response: TransportResponse, url: &Url) -> Result<Response> {
+        if (200..300).contains(&response.status) {
             Ok(Response(response))
         } else {
             Err(Error::ResponseError {
-                code: status.as_u16(),
-                text: response.text().await?,
+                code: response.status,
+                text: String::from_utf8_lossy(&response.body).into_owned(),
                 url: url.to_string(),
             })
         }
     }
 
-    pub(crate) async fn get(&self, node: Node, _timeout: Duration) -> Result<Response> {
-        let mut request_builder = self.client.get(node.url.clone());
-        if let Some(node_auth) = node.auth {
-            if let Some(jwt) = node_auth.jwt {
-                request_builder = request_builder.bearer_auth(jwt);
+    async fn send_once(
+        &self,
+        method: Method<'_>,
+        url: Url,
+        timeout: Duration,
+        credentials: Option<Credentials>,
+    ) -> Result<TransportResponse> {
+        // Every attempt waits for the limits, but the time in the queue isn't part of the latency
+        let _permit = self.limiter.acquire(&url).await?;
+        let info = RequestInfo::new(method.name(), &url, method.body_len());
+        let start = self.monitor.before(&info);
+        let request = TransportRequest {
+            url,
+            timeout,
+            credentials,
+        };
+        let response = match method {
+            Method::Get => self.transport.get(request).await,
+            Method::GetBytes => self.transport.get_bytes(request).await,
+            Method::PostJson(json) => self.transport.post_json(request, json).await,
+            Method::PostBytes(body) => self.transport.post_bytes(request, body).await,
+            Method::Delete => self.transport.delete(request).await,
+        };
+        self.monitor.after(&info, start, &response);
+        response
+    }
+
+    // The url credentials are replaced by the ones of the auth provider, so they aren't sent twice
+    async fn send(&self, node: &Node, timeout: Duration, retry: Retry<'_>, method: Method<'_>) -> Result<Response> {
+        let mut url = node.url.clone();
+        let _ = url.set_username("");
+        let _ = url.set_password(None);
+        let auth_provider = node.auth_provider();
+        let credentials = || async {
+            match &auth_provider {
+                Some(auth_provider) => auth_provider.credentials().await.map(Some),
+                None => Ok(None),
             }
+        };
+        let mut refreshed = false;
+        let mut attempt = 1;
+        loop {
+            let result = self.send_once(method, url.clone(), timeout, credentials().await?).await;
+            // Some if the request can be retried, with the delay the node asked for
+            let retry_after = match &result {
+                Ok(resp) => {
+                    // Rejected credentials are refreshed once, e.g. an expired token
+                    if resp.status == 401
+                        && !refreshed
+                        && auth_provider
+                            .as_ref()
+                            .is_some_and(|auth_provider| auth_provider.invalidate())
+                    {
+                        refreshed = true;
+                        continue;
+                    }
+                    self.retry_policy
+                        .is_retryable_status(resp.status)
+                        .then(|| retry_after(resp))
+                }
+                Err(e) => self.retry_policy.is_retryable_error(e).then_some(None),
+            };
+            let delay = match (retry, retry_after) {
+                (Retry::Never, _) | (_, None) => None,
+                (_, Some(retry_after)) => self.retry_policy.delay(attempt, retry_after),
+            };
+            let delay = match delay {
+                Some(delay) => delay,
+                None => return Self::parse_response(result?, &url),
+            };
+
+            log::debug!("retrying request to {url} in {delay:?}");
+            #[cfg(not(target_family = "wasm"))]
+            tokio::time::sleep(delay).await;
+            #[cfg(target_family = "wasm")]
+            gloo_timers::future::TimeoutFuture::new(delay.as_millis().try_into().unwrap_or(u32::MAX)).await;
+
+            // The node might have processed the failed request anyway
+            if let Retry::UnlessFound(path) = retry {
+                let mut resource_url = url.clone();
+                resource_url.set_path(path);
+                let found = self
+                    .send_once(Method::Get, resource_url, timeout, credentials().await?)
+                    .await;
+                if let Ok(found) = found {
+                    if (200..300).contains(&found.status) {
+                        return Ok(Response(found));
+                    }
+                }
+            }
+            attempt += 1;
         }
-        #[cfg(not(target_family = "wasm"))]
-        {
-            request_builder = request_builder.timeout(_timeout);
-        }
-        let resp = request_builder.send().await?;
-        Self::parse_response(resp, &node.url).await
+    }
+
+    pub(crate) async fn get(&self, node: Node, timeout: Duration) -> Result<Response> {
+        self.send(&node, timeout, Retry::Idempotent, Method::Get).await
     }
 
     // Get with header: "accept", "application/vnd.iota.serializer-v1"
-    pub(crate) async fn get_bytes(&self, node: Node, _timeout: Duration) -> Result<Response> {
-        let mut request_builder = self.client.get(node.url.clone());
-        if let Some(node_auth) = node.auth {
-            if let Some(jwt) = node_auth.jwt {
-                request_builder = request_builder.bearer_auth(jwt);
-            }
-        }
-        #[cfg(not(target_family = "wasm"))]
-        {
-            request_builder = request_builder.timeout(_timeout);
-        }
-        request_builder = request_builder.header("accept", "application/vnd.iota.serializer-v1");
-        let resp = request_builder.send().await?;
-        Self::parse_response(resp, &node.url).await
+    pub(crate) async fn get_bytes(&self, node: Node, timeout: Duration) -> Result<Response> {
+        self.send(&node, timeout, Retry::Idempotent, Method::GetBytes).await
     }
 
-    pub(crate) async fn post_json(&self, node: Node, _timeout: Duration, json: Value) -> Result<Response> {
-        let mut request_builder = self.client.post(node.url.clone());
-        if let Some(node_auth) = node.auth {
-            if let Some(jwt) = node_auth.jwt {
-                request_builder = request_builder.bearer_auth(jwt);
-            }
-        }
-        #[cfg(not(target_family = "wasm"))]
-        {
-            request_builder = request_builder.timeout(_timeout);
-        }
-        Self::parse_response(request_builder.json(&json).send().await?, &node.url).await
+    pub(crate) async fn post_json(
+        &self,
+        node: Node,
+        timeout: Duration,
+        retry: Retry<'_>,
+        json: Value,
+    ) -> Result<Response> {
+        self.send(&node, timeout, retry, Method::PostJson(&json)).await
     }
 
-    pub(crate) async fn post_bytes(&self, node: Node, _timeout: Duration, body: &[u8]) -> Result<Response> {
-        let mut request_builder = self.client.post(node.url.clone());
-        if let Some(node_auth) = node.auth {
-            if let Some(jwt) = node_auth.jwt {
-                request_builder = request_builder.bearer_auth(jwt);
-            }
-        }
-        #[cfg(not(target_family = "wasm"))]
-        {
-            request_builder = request_builder.timeout(_timeout);
-        }
-        request_builder = request_builder.header("Content-Type", "application/vnd.iota.serializer-v1");
-        Self::parse_response(request_builder.body(body.to_vec()).send().await?, &node.url).await
+    pub(crate) async fn post_bytes(
+        &self,
+        node: Node,
+        timeout: Duration,
+        retry: Retry<'_>,
+        body: &[u8],
+    ) -> Result<Response> {
+        self.send(&node, timeout, retry, Method::PostBytes(body)).await
+    }
+
+    // Not retried, the resource might be deleted already
+    pub(crate) async fn delete(&self, node: Node, timeout: Duration) -> Result<Response> {
+        self.send(&node, timeout, Retry::Never, Method::Delete).await
+    }
+}
+
+// Only the delay in seconds is supported, not an HTTP date
+fn retry_after(response: &TransportResponse) -> Option<Duration> {
+    if ![429, 503].contains(&response.status) {
+        return None;
     }
+    response
+        .header("retry-after")?
+        .trim()
+        .parse()
+        .ok()
+        .map(Duration::from_secs)
 }
@@ -0,0 +1,267 @@
+// Copyright 2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Limits of the requests that are sent at the same time and per node, requests over the limits wait in a queue
+
+#[cfg(not(target_family = "wasm"))]
+use std::time::Instant;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+#[cfg(target_family = "wasm")]
+use instant::Instant;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{
+    error::{Error, Result},
+    node_manager::node_stats::node_origin,
+};
+
+/// Token bucket rate limit that applies to every node separately.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[must_use]
+pub struct NodeRateLimit {
+    #[serde(rename = "requestsPerSecond")]
+    pub(crate) requests_per_second: u32,
+    pub(crate) burst: u32,
+}
+
+impl NodeRateLimit {
+    /// Creates a rate limit with a burst of the same amount of requests.
+    pub fn new(requests_per_second: u32) -> Self {
+        Self {
+            requests_per_second,
+            burst: requests_per_second,
+        }
+    }
+
+    /// Sets how many requests can be sent at once after a node wasn't used for a while.
+    pub fn burst(mut self, burst: u32) -> Self {
+        self.burst = burst;
+        self
+    }
+}
+
+/// Requests that wait for the limits and requests that are being sent.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RequestQueueStats {
+    /// Requests that wait for a free slot or for the rate limit of their node
+    pub queued: usize,
+    /// Requests that are being sent
+    #[serde(rename = "inFlight")]
+    pub in_flight: usize,
+    /// Maximum amount of requests that are sent at the same time
+    #[serde(rename = "maxConcurrentRequests")]
+    pub max_concurrent_requests: usize,
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    // Negative if requests already reserved tokens that aren't refilled yet
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+// Shared by the clones of the HTTP client, so all requests of a client count against the same limits
+#[derive(Debug, Clone)]
+pub(crate) struct RequestLimiter {
+    max_concurrent_requests: usize,
+    #[cfg(not(target_family = "wasm"))]
+    semaphore: Arc<tokio::sync::Semaphore>,
+    node_rate_limit: Option<NodeRateLimit>,
+    buckets: Arc<Mutex<HashMap<Url, TokenBucket>>>,
+    queued: Arc<AtomicUsize>,
+    in_flight: Arc<AtomicUsize>,
+}
+
+/// Held while a request is sent, frees its slot when dropped.
+pub(crate) struct RequestPermit {
+    #[cfg(not(target_family = "wasm"))]
+    _permit: tokio::sync::OwnedSemaphorePermit,
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl Drop for RequestPermit {
+    fn drop(&mut self) {
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// Decrements the queue depth also if the waiting request is cancelled
+struct Queued<'a>(&'a AtomicUsize);
+
+impl Drop for Queued<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// Gives the reserved token back if the waiting request is cancelled
+struct ReservedToken<'a> {
+    limiter: &'a RequestLimiter,
+    url: &'a Url,
+    used: bool,
+}
+
+impl Drop for ReservedToken<'_> {
+    fn drop(&mut self) {
+        if !self.used {
+            self.limiter.return_token(self.url);
+        }
+    }
+}
+
+impl RequestLimiter {
+    pub(crate) fn new(max_concurrent_requests: usize, node_rate_limit: Option<NodeRateLimit>) -> Self {
+        let max_concurrent_requests = max_concurrent_requests.max(1);
+        Self {
+            max_concurrent_requests,
+            #[cfg(not(target_family = "wasm"))]
+            semaphore: Arc::new(tokio::sync::Semaphore::new(max_concurrent_requests)),
+            node_rate_limit: node_rate_limit.filter(|limit| limit.requests_per_second > 0),
+            buckets: Default::default(),
+            queued: Default::default(),
+            in_flight: Default::default(),
+        }
+    }
+
+    pub(crate) fn max_concurrent_requests(&self) -> usize {
+        self.max_concurrent_requests
+    }
+
+    /// Waits until the rate limit of the node allows another request and a slot is free. In wasm only the rate limit
+    /// applies, since requests can't run in parallel there.
+    pub(crate) async fn acquire(&self, url: &Url) -> Result<RequestPermit> {
+        self.queued.fetch_add(1, Ordering::SeqCst);
+        let queued = Queued(&self.queued);
+
+        let delay = self.reserve_token(url)?;
+        let mut reserved_token = ReservedToken {
+            limiter: self,
+            url,
+            used: false,
+        };
+        if !delay.is_zero() {
+            log::debug!("rate limit of {} reached, waiting {delay:?}", node_origin(url));
+            #[cfg(not(target_family = "wasm"))]
+            tokio::time::sleep(delay).await;
+            #[cfg(target_family = "wasm")]
+            gloo_timers::future::TimeoutFuture::new(delay.as_millis().try_into().unwrap_or(u32::MAX)).await;
+        }
+        #[cfg(not(target_family = "wasm"))]
+        let permit = self
+            .semaphore
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|_| Error::NodeError("request limiter was closed".to_string()))?;
+
+        reserved_token.used = true;
+        drop(queued);
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        Ok(RequestPermit {
+            #[cfg(not(target_family = "wasm"))]
+            _permit: permit,
+            in_flight: self.in_flight.clone(),
+        })
+    }
+
+    // Takes a token from the bucket of the node and returns how long to wait until it's refilled. Tokens are reserved
+    // in advance, so waiting requests are sent in the order they arrived.
+    fn reserve_token(&self, url: &Url) -> Result<Duration> {
+        self.reserve_token_at(url, Instant::now())
+    }
+
+    fn reserve_token_at(&self, url: &Url, now: Instant) -> Result<Duration> {
+        let limit = match self.node_rate_limit {
+            Some(limit) => limit,
+            None => return Ok(Duration::ZERO),
+        };
+        let rate = f64::from(limit.requests_per_second);
+        let burst = f64::from(limit.burst.max(1));
+
+        let mut buckets = self.buckets.lock().map_err(|_| Error::PoisonError)?;
+        let bucket = buckets.entry(node_origin(url)).or_insert(TokenBucket {
+            tokens: burst,
+            refilled_at: now,
+        });
+        let refilled = now.duration_since(bucket.refilled_at).as_secs_f64() * rate;
+        bucket.tokens = (bucket.tokens + refilled).min(burst) - 1.0;
+        bucket.refilled_at = now;
+
+        Ok(if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / rate)
+        })
+    }
+
+    fn return_token(&self, url: &Url) {
+        let burst = match self.node_rate_limit {
+            Some(limit) => f64::from(limit.burst.max(1)),
+            None => return,
+        };
+        if let Ok(mut buckets) = self.buckets.lock() {
+            if let Some(bucket) = buckets.get_mut(&node_origin(url)) {
+                bucket.tokens = (bucket.tokens + 1.0).min(burst);
+            }
+        }
+    }
+
+    pub(crate) fn stats(&self) -> RequestQueueStats {
+        RequestQueueStats {
+            queued: self.queued.load(Ordering::SeqCst),
+            in_flight: self.in_flight.load(Ordering::SeqCst),
+            max_concurrent_requests: self.max_concurrent_requests,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_bucket() {
+        let limiter = RequestLimiter::new(1, Some(NodeRateLimit::new(10).burst(2)));
+        let node = Url::parse("http://localhost:14265/api/core/v2/tips").unwrap();
+        let other_node = Url::parse("http://localhost:14266").unwrap();
+
+        let now = Instant::now();
+
+        assert_eq!(limiter.reserve_token_at(&node, now).unwrap(), Duration::ZERO);
+        assert_eq!(limiter.reserve_token_at(&node, now).unwrap(), Duration::ZERO);
+        assert_eq!(limiter.reserve_token_at(&node, now).unwrap().as_millis(), 100);
+        // The next request waits behind the reserved token
+        assert_eq!(limiter.reserve_token_at(&node, now).unwrap().as_millis(), 200);
+        assert_eq!(limiter.reserve_token_at(&other_node, now).unwrap(), Duration::ZERO);
+        // Both reserved tokens and another one are refilled after 300ms
+        assert_eq!(
+            limiter
+                .reserve_token_at(&node, now + Duration::from_millis(300))
+                .unwrap(),
+            Duration::ZERO
+        );
+    }
+
+    #[tokio::test]
+    async fn cancelled_waiter() {
+        let limiter = RequestLimiter::new(10, Some(NodeRateLimit::new(1)));
+        let node = Url::parse("http://localhost:14265").unwrap();
+
+        let _permit = limiter.acquire(&node).await.unwrap();
+        assert!(tokio::time::timeout(Duration::from_millis(10), limiter.acquire(&node))
+            .await
+            .is_err());
+        assert_eq!(limiter.stats().queued, 0);
+        // The cancelled request gave its token back, otherwise the next one would wait for two tokens
+        assert!(limiter.reserve_token(&node).unwrap() <= Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,300 @@
+// Copyright 2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Metrics of the HTTP requests per node and route, and observers that are called for every request
+
+#[cfg(not(target_family = "wasm"))]
+use std::time::Instant;
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+#[cfg(target_family = "wasm")]
+use instant::Instant;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{
+    error::{Error, Result},
+    node_manager::{node_stats::node_origin, retry::RetryableError, transport::TransportResponse},
+};
+
+/// Upper bounds of the latency histogram buckets in milliseconds, slower requests are counted in a last bucket
+const LATENCY_BUCKETS_MS: [u64; 10] = [10, 25, 50, 100, 250, 500, 1000, 2500, 5000, 10000];
+
+/// A request that is about to be sent.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RequestInfo {
+    /// Node url, without path and credentials
+    pub node: Url,
+    /// HTTP method
+    pub method: &'static str,
+    /// Path with IDs and indexes replaced by placeholders, e.g. `api/core/v2/blocks/{id}`
+    pub route: String,
+    /// Url with path and query, without credentials
+    pub url: Url,
+    /// Size of the request body
+    pub bytes_sent: usize,
+}
+
+impl RequestInfo {
+    pub(crate) fn new(method: &'static str, url: &Url, bytes_sent: usize) -> Self {
+        Self {
+            node: node_origin(url),
+            method,
+            route: route(url.path()),
+            url: url.clone(),
+            bytes_sent,
+        }
+    }
+}
+
+/// The outcome of a sent request.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RequestOutcome {
+    /// Status code, `None` if there was no response
+    pub status: Option<u16>,
+    /// Time until the response or the error
+    pub latency: Duration,
+    /// Size of the response body
+    pub bytes_received: usize,
+    /// Whether the request timed out
+    pub timed_out: bool,
+    /// Error message, if there was no response
+    pub error: Option<String>,
+}
+
+impl RequestOutcome {
+    pub(crate) fn new(response: &Result<TransportResponse>, latency: Duration) -> Self {
+        match response {
+            Ok(response) => Self {
+                status: Some(response.status),
+                latency,
+                bytes_received: response.body.len(),
+                timed_out: false,
+                error: None,
+            },
+            Err(error) => Self {
+                status: None,
+                latency,
+                bytes_received: 0,
+                timed_out: RetryableError::of(error) == Some(RetryableError::Timeout),
+                error: Some(error.to_string()),
+            },
+        }
+    }
+}
+
+/// Observes the HTTP requests to the nodes, e.g. to forward them to a monitoring system. The methods are called on the
+/// task that sends the request, so they should return quickly.
+pub trait RequestObserver: Send + Sync + Debug {
+    /// Called before a request is sent, also before every retry.
+    fn on_request(&self, _request: &RequestInfo) {}
+
+    /// Called after a request got a response or failed without one.
+    fn on_response(&self, _request: &RequestInfo, _outcome: &RequestOutcome) {}
+}
+
+/// Latency histogram with fixed buckets.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    /// Upper bounds of the buckets in milliseconds
+    #[serde(rename = "bucketsMs")]
+    pub buckets_ms: Vec<u64>,
+    /// Amount of requests per bucket, the last one counts the requests that were slower than all bounds
+    pub counts: Vec<u64>,
+    /// Sum of all latencies in milliseconds
+    #[serde(rename = "sumMs")]
+    pub sum_ms: u64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            buckets_ms: LATENCY_BUCKETS_MS.to_vec(),
+            counts: vec![0; LATENCY_BUCKETS_MS.len() + 1],
+            sum_ms: 0,
+        }
+    }
+}
+
+impl LatencyHistogram {
+    fn record(&mut self, latency: Duration) {
+        let latency_ms = latency.as_millis().try_into().unwrap_or(u64::MAX);
+        let bucket = self
+            .buckets_ms
+            .iter()
+            .position(|bound| latency_ms <= *bound)
+            .unwrap_or(self.buckets_ms.len());
+        self.counts[bucket] += 1;
+        self.sum_ms = self.sum_ms.saturating_add(latency_ms);
+    }
+}
+
+/// Counters of requests.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RequestMetrics {
+    /// Amount of sent requests
+    pub requests: u64,
+    /// Responses with a 2xx status
+    pub successes: u64,
+    /// Responses with a 4xx status
+    #[serde(rename = "clientErrors")]
+    pub client_errors: u64,
+    /// Responses with a 5xx status
+    #[serde(rename = "serverErrors")]
+    pub server_errors: u64,
+    /// Requests that timed out
+    pub timeouts: u64,
+    /// Requests that failed without a response for another reason
+    #[serde(rename = "connectionErrors")]
+    pub connection_errors: u64,
+    /// Size of all request bodies
+    #[serde(rename = "bytesSent")]
+    pub bytes_sent: u64,
+    /// Size of all response bodies
+    #[serde(rename = "bytesReceived")]
+    pub bytes_received: u64,
+    /// Latencies of the requests
+    pub latency: LatencyHistogram,
+}
+
+impl RequestMetrics {
+    fn record(&mut self, request: &RequestInfo, outcome: &RequestOutcome) {
+        self.requests += 1;
+        match outcome.status {
+            Some(200..=299) => self.successes += 1,
+            Some(400..=499) => self.client_errors += 1,
+            Some(500..=599) => self.server_errors += 1,
+            Some(_) => {}
+            None if outcome.timed_out => self.timeouts += 1,
+            None => self.connection_errors += 1,
+        }
+        self.bytes_sent += request.bytes_sent as u64;
+        self.bytes_received += outcome.bytes_received as u64;
+        self.latency.record(outcome.latency);
+    }
+}
+
+/// Metrics of the requests to a node.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NodeMetrics {
+    /// All requests to the node
+    pub total: RequestMetrics,
+    /// Requests to the node per route
+    pub routes: BTreeMap<String, RequestMetrics>,
+}
+
+/// Snapshot of the request metrics since the client was created.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Metrics {
+    /// Metrics per node url, without path and credentials
+    pub nodes: BTreeMap<String, NodeMetrics>,
+    /// Metrics per route, of all nodes
+    pub routes: BTreeMap<String, RequestMetrics>,
+}
+
+/// Records the metrics and calls the observers, shared by the clones of the HTTP client.
+#[derive(Clone, Default)]
+pub(crate) struct RequestMonitor {
+    metrics: Arc<RwLock<Metrics>>,
+    observers: Arc<RwLock<Vec<Arc<dyn RequestObserver>>>>,
+}
+
+impl Debug for RequestMonitor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RequestMonitor")
+            .field("observers", &self.observers)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RequestMonitor {
+    /// Calls the observers and returns the start of the request.
+    pub(crate) fn before(&self, request: &RequestInfo) -> Instant {
+        if let Ok(observers) = self.observers.read() {
+            observers.iter().for_each(|observer| observer.on_request(request));
+        }
+        Instant::now()
+    }
+
+    pub(crate) fn after(&self, request: &RequestInfo, start: Instant, response: &Result<TransportResponse>) {
+        let outcome = RequestOutcome::new(response, start.elapsed());
+        if let Ok(mut metrics) = self.metrics.write() {
+            let node = metrics.nodes.entry(request.node.to_string()).or_default();
+            node.total.record(request, &outcome);
+            node.routes
+                .entry(request.route.clone())
+                .or_default()
+                .record(request, &outcome);
+            metrics
+                .routes
+                .entry(request.route.clone())
+                .or_default()
+                .record(request, &outcome);
+        }
+        if let Ok(observers) = self.observers.read() {
+            observers
+                .iter()
+                .for_each(|observer| observer.on_response(request, &outcome));
+        }
+    }
+
+    pub(crate) fn metrics(&self) -> Result<Metrics> {
+        Ok(self.metrics.read().map_err(|_| Error::PoisonError)?.clone())
+    }
+
+    pub(crate) fn add_observer(&self, observer: Arc<dyn RequestObserver>) -> Result<()> {
+        self.observers.write().map_err(|_| Error::PoisonError)?.push(observer);
+        Ok(())
+    }
+}
+
+// IDs and indexes are replaced, so the metrics don't grow with every requested block or output
+fn route(path: &str) -> String {
+    path.trim_start_matches('/')
+        .split('/')
+        .map(|segment| {
+            if segment.starts_with("0x") {
+                "{id}"
+            } else if !segment.is_empty() && segment.bytes().all(|b| b.is_ascii_digit()) {
+                "{index}"
+            } else {
+                segment
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn routes() {
+        assert_eq!(route("/api/core/v2/info"), "api/core/v2/info");
+        assert_eq!(
+            route("/api/core/v2/blocks/0x1b6f73a0fc5d0e9a4cfd2c54e4fc8d0ff8f5ec1a7c2ba4bbfd6a0c4bd7e6d7af/metadata"),
+            "api/core/v2/blocks/{id}/metadata"
+        );
+        assert_eq!(
+            route("/api/core/v2/milestones/by-index/42/utxo-changes"),
+            "api/core/v2/milestones/by-index/{index}/utxo-changes"
+        );
+    }
+
+    #[test]
+    fn latency_histogram() {
+        let mut histogram = LatencyHistogram::default();
+        histogram.record(Duration::from_millis(5));
+        histogram.record(Duration::from_millis(10));
+        histogram.record(Duration::from_millis(300));
+        histogram.record(Duration::from_secs(60));
+        assert_eq!(histogram.counts, vec![2, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1]);
+        assert_eq!(histogram.sum_ms, 60_315);
+    }
+}
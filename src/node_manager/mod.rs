@@ -3,11 +3,27 @@
 
 //! The node manager that takes care of sending requests with synced nodes and quorum if enabled
 
+pub mod auth;
 pub mod builder;
+pub mod cache;
+pub mod capabilities;
+#[cfg(not(target_family = "wasm"))]
+pub mod cassette;
+pub mod circuit_breaker;
 pub(crate) mod http_client;
+pub mod limiter;
+pub mod metrics;
 /// Structs for nodes
 pub mod node;
+pub mod node_stats;
+pub mod quorum;
+pub mod response_source;
+pub mod retry;
+pub mod sync_status;
+pub mod transport;
 
+#[cfg(not(target_family = "wasm"))]
+use std::time::Instant;
 use std::{
     collections::{HashMap, HashSet},
     sync::{Arc, RwLock},
@@ -15,9 +31,21 @@ use std::{
 };
 
 use bee_api_types::responses::InfoResponse;
+use bee_block::BlockId;
+#[cfg(target_family = "wasm")]
+use instant::Instant;
 use serde_json::Value;
+use url::Url;
 
-use self::{http_client::HttpClient, node::Node};
+use self::{
+    cache::{CachedResource, ResponseCache},
+    capabilities::NodeCapability,
+    http_client::{HttpClient, Response, Retry},
+    node::Node,
+    node_stats::{node_origin, NodeStatsMap},
+    quorum::{Quorum, QuorumEndpoint, QuorumPolicy, QuorumReport, QuorumReportListener},
+    response_source::{is_history_route, ResponseSource},
+};
 use crate::{
     error::{Error, Result},
     node_manager::builder::NodeManagerBuilder,
@@ -27,9 +55,9 @@ use crate::{
 // will send the requests for some endpoints to multiple nodes and compares the results.
 #[derive(Clone)]
 pub(crate) struct NodeManager {
-    pub(crate) primary_node: Option<Node>,
+    pub(crate) primary_node: Arc<RwLock<Option<Node>>>,
     primary_pow_node: Option<Node>,
-    pub(crate) nodes: HashSet<Node>,
+    pub(crate) nodes: Arc<RwLock<HashSet<Node>>>,
     permanodes: Option<HashSet<Node>>,
     pub(crate) node_sync_enabled: bool,
     node_sync_interval: Duration,
@@ -37,7 +65,12 @@ pub(crate) struct NodeManager {
     quorum: bool,
     min_quorum_size: usize,
     quorum_threshold: usize,
+    quorum_policies: HashMap<QuorumEndpoint, QuorumPolicy>,
+    quorum_report_listeners: Arc<RwLock<Vec<QuorumReportListener>>>,
     pub(crate) http_client: HttpClient,
+    pub(crate) node_stats: NodeStatsMap,
+    pub(crate) response_cache: Option<Arc<ResponseCache>>,
+    pub(crate) offline: bool,
 }
 
 impl std::fmt::Debug for NodeManager {
@@ -52,7 +85,10 @@ impl std::fmt::Debug for NodeManager {
         d.field("synced_nodes", &self.synced_nodes);
         d.field("quorum", &self.quorum);
         d.field("min_quorum_size", &self.min_quorum_size);
-        d.field("quorum_threshold", &self.quorum_threshold).finish()
+        d.field("quorum_threshold", &self.quorum_threshold);
+        d.field("quorum_policies", &self.quorum_policies);
+        d.field("response_cache", &self.response_cache);
+        d.field("offline", &self.offline).finish()
     }
 }
 
@@ -68,6 +104,9 @@ impl NodeManager {
         use_primary_pow_node: bool,
         prefer_permanode: bool,
     ) -> Result<Vec<Node>> {
+        if self.offline {
+            return Err(Error::OfflineMode(path.to_string()));
+        }
         let mut nodes_with_modified_url: Vec<Node> = Vec::new();
 
         if prefer_permanode || (path == "api/core/v2/blocks" && query.is_some()) {
@@ -88,35 +127,43 @@ impl NodeManager {
             }
         }
 
-        if let Some(primary_node) = self.primary_node.clone() {
+        if let Some(primary_node) = self.primary_node.read().map_err(|_| Error::PoisonError)?.clone() {
             if !nodes_with_modified_url.iter().any(|n| n.url == primary_node.url) {
                 nodes_with_modified_url.push(primary_node);
             }
         }
 
-        // Add other nodes in random order, so they are not always used in the same order
-        let nodes_random_order = if self.node_sync_enabled {
-            #[cfg(not(target_family = "wasm"))]
-            {
-                self.synced_nodes.read().map_err(|_| crate::Error::PoisonError)?.clone()
-            }
-            #[cfg(target_family = "wasm")]
-            {
-                self.nodes.clone()
-            }
+        // Add other nodes in random order, so they are not always used in the same order if they have the same score
+        let nodes_random_order = self.pool_nodes()?;
+
+        // Add remaining nodes, the ones with the best score first
+        let mut remaining_nodes = nodes_random_order
+            .into_iter()
+            .filter(|node| !nodes_with_modified_url.iter().any(|n| n.url == node.url))
+            .collect::<Vec<_>>();
+        self.node_stats.sort_by_score(&mut remaining_nodes);
+        nodes_with_modified_url.extend(remaining_nodes);
+
+        // remove disabled nodes
+        nodes_with_modified_url.retain(|n| !n.disabled);
+
+        // Only nodes with the plugin of the route, blocks with remote PoW only to nodes with the pow feature
+        let capability = if use_primary_pow_node && path == "api/core/v2/blocks" {
+            Some(NodeCapability::Pow)
         } else {
-            self.nodes.clone()
+            NodeCapability::of_path(path)
         };
-
-        // Add remaining nodes in random order
-        for node in nodes_random_order {
-            if !nodes_with_modified_url.iter().any(|n| n.url == node.url) {
-                nodes_with_modified_url.push(node);
+        if let Some(capability) = capability {
+            if !nodes_with_modified_url.is_empty() {
+                self.node_stats.retain_capable(&mut nodes_with_modified_url, capability);
+                if nodes_with_modified_url.is_empty() {
+                    return Err(Error::PluginNotAvailable(capability));
+                }
             }
         }
 
-        // remove disabled nodes
-        nodes_with_modified_url.retain(|n| !n.disabled);
+        // skip nodes that failed repeatedly, until their cooldown elapsed
+        self.node_stats.retain_available(&mut nodes_with_modified_url);
 
         if nodes_with_modified_url.is_empty() {
             return Err(crate::Error::SyncedNodePoolEmpty);
@@ -131,156 +178,328 @@ impl NodeManager {
         Ok(nodes_with_modified_url)
     }
 
+    /// Returns the nodes of the pool that are synced, if node syncing is enabled. The synced nodes only filter the
+    /// pool, so nodes that were removed or disabled since the last sync are left out.
+    pub(crate) fn pool_nodes(&self) -> Result<HashSet<Node>> {
+        let nodes = self.nodes.read().map_err(|_| Error::PoisonError)?.clone();
+        if !self.node_sync_enabled || cfg!(target_family = "wasm") {
+            return Ok(nodes);
+        }
+        let synced_nodes = self.synced_nodes.read().map_err(|_| Error::PoisonError)?;
+        let synced_urls = synced_nodes.iter().map(|node| &node.url).collect::<HashSet<_>>();
+        Ok(nodes
+            .into_iter()
+            .filter(|node| synced_urls.contains(&node.url))
+            .collect())
+    }
+
+    /// Adds the node to the pool, or replaces the node with the same url. It's used right away, until the next sync
+    /// checks it.
+    pub(crate) fn add_node(&self, node: Node) -> Result<()> {
+        let origin = node_origin(&node.url);
+        let replace = |nodes: &mut HashSet<Node>| {
+            nodes.retain(|n| node_origin(&n.url) != origin);
+            nodes.insert(node.clone());
+        };
+        replace(&mut *self.nodes.write().map_err(|_| Error::PoisonError)?);
+        replace(&mut *self.synced_nodes.write().map_err(|_| Error::PoisonError)?);
+        Ok(())
+    }
+
+    /// Removes the node from the pool and as primary node, returns whether it was used.
+    pub(crate) fn remove_node(&self, url: &Url) -> Result<bool> {
+        let origin = node_origin(url);
+        let mut removed = false;
+        for nodes in [&self.nodes, &self.synced_nodes] {
+            let mut nodes = nodes.write().map_err(|_| Error::PoisonError)?;
+            let len = nodes.len();
+            nodes.retain(|n| node_origin(&n.url) != origin);
+            removed |= nodes.len() != len;
+        }
+        let mut primary_node = self.primary_node.write().map_err(|_| Error::PoisonError)?;
+        if primary_node.as_ref().is_some_and(|n| node_origin(&n.url) == origin) {
+            primary_node.take();
+            removed = true;
+        }
+        self.node_stats.remove(url);
+        Ok(removed)
+    }
+
+    pub(crate) fn set_primary_node(&self, node: Node) -> Result<()> {
+        self.primary_node.write().map_err(|_| Error::PoisonError)?.replace(node);
+        Ok(())
+    }
+
+    /// Sets whether the node is disabled, in the pool and as primary node. Returns whether the node was found.
+    pub(crate) fn set_node_disabled(&self, url: &Url, disabled: bool) -> Result<bool> {
+        let origin = node_origin(url);
+        let mut found = false;
+        {
+            let mut nodes = self.nodes.write().map_err(|_| Error::PoisonError)?;
+            let updated = nodes
+                .iter()
+                .filter(|n| node_origin(&n.url) == origin)
+                .cloned()
+                .collect::<Vec<_>>();
+            for mut node in updated {
+                found = true;
+                nodes.remove(&node);
+                node.disabled = disabled;
+                nodes.insert(node);
+            }
+        }
+        if let Some(primary_node) = self
+            .primary_node
+            .write()
+            .map_err(|_| Error::PoisonError)?
+            .as_mut()
+            .filter(|n| node_origin(&n.url) == origin)
+        {
+            found = true;
+            primary_node.disabled = disabled;
+        }
+        Ok(found)
+    }
+
     pub(crate) async fn get_request<T: serde::de::DeserializeOwned + std::fmt::Debug + serde::Serialize>(
         &self,
         path: &str,
         query: Option<&str>,
         timeout: Duration,
-        need_quorum: bool,
+        quorum_endpoint: Option<QuorumEndpoint>,
         prefer_permanode: bool,
     ) -> Result<T> {
-        let mut result: HashMap<String, usize> = HashMap::new();
+        self.get_request_with_source(path, query, timeout, quorum_endpoint, prefer_permanode)
+            .await
+            .map(|(value, _)| value)
+    }
+
+    /// Also returns the source of the response. If none of the nodes has the data of a history route anymore, the
+    /// permanodes that weren't asked yet are tried.
+    pub(crate) async fn get_request_with_source<T: serde::de::DeserializeOwned + std::fmt::Debug + serde::Serialize>(
+        &self,
+        path: &str,
+        query: Option<&str>,
+        timeout: Duration,
+        quorum_endpoint: Option<QuorumEndpoint>,
+        prefer_permanode: bool,
+    ) -> Result<(T, ResponseSource)> {
         // primary_pow_node should only be used for post request with remote PoW
         // Get node urls and set path
         let nodes = self.get_nodes(path, query, false, prefer_permanode).await?;
-        if self.quorum && need_quorum && nodes.len() < self.min_quorum_size {
-            return Err(Error::QuorumPoolSizeError {
-                available_nodes: nodes.len(),
-                minimum_threshold: self.min_quorum_size,
-            });
+        let quorum = self.quorum(quorum_endpoint);
+
+        let result = self.get_request_from(nodes.clone(), path, query, timeout, quorum).await;
+        if matches!(result, Err(Error::NotFound(_))) && is_history_route(path) {
+            let permanodes = self.fallback_permanodes(path, query, &nodes, quorum);
+            if !permanodes.is_empty() {
+                log::debug!("{path} not found on the nodes, requesting it from the permanodes");
+                return self.get_request_from(permanodes, path, query, timeout, quorum).await;
+            }
+        }
+        result
+    }
+
+    async fn get_request_from<T: serde::de::DeserializeOwned + std::fmt::Debug + serde::Serialize>(
+        &self,
+        nodes: Vec<Node>,
+        path: &str,
+        query: Option<&str>,
+        timeout: Duration,
+        quorum: Option<Quorum>,
+    ) -> Result<(T, ResponseSource)> {
+        if let Some(quorum) = quorum {
+            let mut results = Vec::new();
+            for (url, response) in self.quorum_responses(nodes, timeout, quorum, false).await? {
+                let result = match response {
+                    Ok(response) => match response.into_json::<T>().await {
+                        Ok(value) => serde_json::to_value(&value)
+                            .map(|json| (quorum.normalize(json), value))
+                            .map_err(Into::into),
+                        Err(e) => Err(e),
+                    },
+                    Err(e) => Err(e),
+                };
+                results.push((url, result));
+            }
+            return self.quorum_result(path, query, quorum, results);
         }
 
-        // Track amount of results for quorum
-        let mut result_counter = 0;
         let mut error = None;
-        // Send requests parallel for quorum
-        #[cfg(target_family = "wasm")]
-        let wasm = true;
-        #[cfg(not(target_family = "wasm"))]
-        let wasm = false;
-        if !wasm && self.quorum && need_quorum && query.is_none() {
-            #[cfg(not(target_family = "wasm"))]
-            {
-                let mut tasks = Vec::new();
-                for (index, node) in nodes.into_iter().enumerate() {
-                    if index < self.min_quorum_size {
-                        let client_ = self.http_client.clone();
-                        tasks.push(async move { tokio::spawn(async move { client_.get(node, timeout).await }).await });
-                    }
-                }
-                for res in futures::future::try_join_all(tasks).await? {
-                    match res {
-                        Ok(res) => {
-                            if let Ok(res_text) = res.into_text().await {
-                                let counters = result.entry(res_text.to_string()).or_insert(0);
-                                *counters += 1;
-                                result_counter += 1;
-                            } else {
-                                log::warn!("couldn't convert node response to text");
-                            }
-                        }
-                        Err(Error::ResponseError { code: 404, url, .. }) => {
-                            error.replace(crate::Error::NotFound(url));
-                        }
-                        Err(err) => {
-                            error.replace(err);
-                        }
-                    }
-                }
+        // Send requests
+        let forced = self.node_stats.none_available(&nodes);
+        for node in nodes {
+            if !self.node_stats.acquire(&node.url, forced) {
+                continue;
             }
-        } else {
-            // Send requests
-            for node in nodes {
-                match self.http_client.get(node.clone(), timeout).await {
-                    Ok(res) => {
-                        match res.status() {
-                            200 => {
-                                // Handle node_info extra because we also want to return the url
-                                if path == "api/core/v2/info" {
-                                    let node_info: InfoResponse = res.into_json().await?;
-                                    let wrapper = crate::client::NodeInfoWrapper {
-                                        node_info,
-                                        url: format!("{}://{}", node.url.scheme(), node.url.host_str().unwrap_or("")),
-                                    };
-                                    let serde_res = serde_json::to_string(&wrapper)?;
-                                    return Ok(serde_json::from_str(&serde_res)?);
-                                }
+            let start = Instant::now();
+            let response = self.http_client.get(node.clone(), timeout).await;
+            self.node_stats.record_request(&node.url, start.elapsed(), &response);
+            match response {
+                Ok(res) => {
+                    match res.status() {
+                        200 => {
+                            // Handle node_info extra because we also want to return the url
+                            if path == "api/core/v2/info" {
+                                let node_info: InfoResponse = res.into_json().await?;
+                                let wrapper = crate::client::NodeInfoWrapper {
+                                    node_info,
+                                    url: format!("{}://{}", node.url.scheme(), node.url.host_str().unwrap_or("")),
+                                };
+                                let serde_res = serde_json::to_string(&wrapper)?;
+                                return Ok((serde_json::from_str(&serde_res)?, self.response_source(&node.url)));
+                            }
 
-                                match res.into_json::<T>().await {
-                                    Ok(result_data) => {
-                                        let counters = result.entry(serde_json::to_string(&result_data)?).or_insert(0);
-                                        *counters += 1;
-                                        result_counter += 1;
-                                        // Without quorum it's enough if we got one response
-                                        if !self.quorum
-                                            || result_counter >= self.min_quorum_size
-                                            || !need_quorum
-                                            // with query we ignore quorum because the nodes can store a different amount of history
-                                            || query.is_some()
-                                        {
-                                            break;
-                                        }
-                                    }
-                                    Err(e) => {
-                                        error.replace(e);
-                                    }
+                            match res.into_json::<T>().await {
+                                // Without quorum it's enough if we got one response
+                                Ok(result_data) => return Ok((result_data, self.response_source(&node.url))),
+                                Err(e) => {
+                                    error.replace(e);
                                 }
                             }
+                        }
 
-                            _ => {
-                                error.replace(crate::Error::NodeError(
-                                    res.into_text()
-                                        .await
-                                        .unwrap_or_else(|_| "couldn't convert node response into text".to_string()),
-                                ));
-                            }
+                        _ => {
+                            error.replace(crate::Error::NodeError(
+                                res.into_text()
+                                    .await
+                                    .unwrap_or_else(|_| "couldn't convert node response into text".to_string()),
+                            ));
                         }
                     }
-                    Err(Error::ResponseError { code: 404, url, .. }) => {
-                        error.replace(crate::Error::NotFound(url));
-                    }
-                    Err(err) => {
-                        error.replace(err);
-                    }
+                }
+                Err(Error::ResponseError { code: 404, url, .. }) => {
+                    error.replace(crate::Error::NotFound(url));
+                }
+                Err(err) => {
+                    error.replace(err);
                 }
             }
         }
 
-        let res = result
-            .into_iter()
-            .max_by_key(|v| v.1)
-            .ok_or_else(|| error.unwrap_or_else(|| Error::NodeError("couldn't get a result from any node".into())))?;
+        Err(error.unwrap_or_else(|| Error::NodeError("couldn't get a result from any node".into())))
+    }
 
-        // Return if quorum is false or check if quorum was reached
-        if !self.quorum
-            || res.1 as f64 >= self.min_quorum_size as f64 * (self.quorum_threshold as f64 / 100.0)
-            || !need_quorum
-            // with query we ignore quorum because the nodes can store a different amount of history
-            || query.is_some()
-        {
-            Ok(serde_json::from_str(&res.0)?)
-        } else {
-            Err(Error::QuorumThresholdError {
-                quorum_size: res.1,
-                minimum_threshold: self.min_quorum_size,
-            })
+    pub(crate) async fn get_cached_request<T: serde::de::DeserializeOwned + std::fmt::Debug + serde::Serialize>(
+        &self,
+        resource: CachedResource,
+        path: &str,
+        timeout: Duration,
+        quorum_endpoint: Option<QuorumEndpoint>,
+        prefer_permanode: bool,
+    ) -> Result<T> {
+        self.get_cached_request_with_source(resource, path, timeout, quorum_endpoint, prefer_permanode)
+            .await
+            .map(|(value, _)| value)
+    }
+
+    // Responses of immutable resources are taken from the cache if there is one, only successful ones are cached
+    pub(crate) async fn get_cached_request_with_source<
+        T: serde::de::DeserializeOwned + std::fmt::Debug + serde::Serialize,
+    >(
+        &self,
+        resource: CachedResource,
+        path: &str,
+        timeout: Duration,
+        quorum_endpoint: Option<QuorumEndpoint>,
+        prefer_permanode: bool,
+    ) -> Result<(T, ResponseSource)> {
+        let cache = match &self.response_cache {
+            Some(cache) => cache,
+            None => {
+                return self
+                    .get_request_with_source(path, None, timeout, quorum_endpoint, prefer_permanode)
+                    .await;
+            }
+        };
+        if let Some(json) = cache.get_json(resource)? {
+            return Ok((serde_json::from_value(json)?, ResponseSource::Cache));
         }
+        let (response, source): (T, _) = self
+            .get_request_with_source(path, None, timeout, quorum_endpoint, prefer_permanode)
+            .await?;
+        cache.put_json(resource, serde_json::to_value(&response)?)?;
+        Ok((response, source))
+    }
+
+    pub(crate) async fn get_cached_request_bytes(
+        &self,
+        resource: CachedResource,
+        path: &str,
+        timeout: Duration,
+        quorum_endpoint: Option<QuorumEndpoint>,
+    ) -> Result<Vec<u8>> {
+        let cache = match &self.response_cache {
+            Some(cache) => cache,
+            None => return self.get_request_bytes(path, None, timeout, quorum_endpoint).await,
+        };
+        if let Some(bytes) = cache.get_bytes(resource)? {
+            return Ok(bytes);
+        }
+        let bytes = self.get_request_bytes(path, None, timeout, quorum_endpoint).await?;
+        cache.put_bytes(resource, bytes.clone())?;
+        Ok(bytes)
     }
 
-    // Only used for api/core/v2/blocks/{blockID}, that's why we don't need the quorum stuff
     pub(crate) async fn get_request_bytes(
         &self,
         path: &str,
         query: Option<&str>,
         timeout: Duration,
+        quorum_endpoint: Option<QuorumEndpoint>,
     ) -> Result<Vec<u8>> {
         // primary_pow_node should only be used for post request with remote Pow
         // Get node urls and set path
         let nodes = self.get_nodes(path, query, false, false).await?;
+        let quorum = self.quorum(quorum_endpoint);
+
+        let result = self
+            .get_request_bytes_from(nodes.clone(), path, query, timeout, quorum)
+            .await;
+        if matches!(result, Err(Error::NotFound(_))) && is_history_route(path) {
+            let permanodes = self.fallback_permanodes(path, query, &nodes, quorum);
+            if !permanodes.is_empty() {
+                log::debug!("{path} not found on the nodes, requesting it from the permanodes");
+                return self
+                    .get_request_bytes_from(permanodes, path, query, timeout, quorum)
+                    .await;
+            }
+        }
+        result
+    }
+
+    async fn get_request_bytes_from(
+        &self,
+        nodes: Vec<Node>,
+        path: &str,
+        query: Option<&str>,
+        timeout: Duration,
+        quorum: Option<Quorum>,
+    ) -> Result<Vec<u8>> {
+        // The binary representation has no volatile fields, so the bytes are compared as they are
+        if let Some(quorum) = quorum {
+            let mut results = Vec::new();
+            for (url, response) in self.quorum_responses(nodes, timeout, quorum, true).await? {
+                let result = match response {
+                    Ok(response) => response.into_bytes().await.map(|bytes| (bytes.clone(), bytes)),
+                    Err(e) => Err(e),
+                };
+                results.push((url, result));
+            }
+            return self.quorum_result(path, query, quorum, results).map(|(bytes, _)| bytes);
+        }
+
         let mut error = None;
         // Send requests
+        let forced = self.node_stats.none_available(&nodes);
         for node in nodes {
-            match self.http_client.get_bytes(node, timeout).await {
+            if !self.node_stats.acquire(&node.url, forced) {
+                continue;
+            }
+            let start = Instant::now();
+            let response = self.http_client.get_bytes(node.clone(), timeout).await;
+            self.node_stats.record_request(&node.url, start.elapsed(), &response);
+            match response {
                 Ok(res) => {
                     let status = res.status();
                     if let Ok(res_text) = res.into_bytes().await {
@@ -305,22 +524,201 @@ impl NodeManager {
         Err(error.unwrap_or_else(|| Error::NodeError("couldn't get a result from any node".into())))
     }
 
+    // The permanodes that weren't asked already, with the path and query of the request
+    fn fallback_permanodes(
+        &self,
+        path: &str,
+        query: Option<&str>,
+        nodes: &[Node],
+        quorum: Option<Quorum>,
+    ) -> Vec<Node> {
+        // With quorum only the first nodes were asked
+        let asked = quorum.map_or(nodes.len(), |quorum| quorum.min_quorum_size.min(nodes.len()));
+        let asked = nodes[..asked]
+            .iter()
+            .map(|node| node_origin(&node.url))
+            .collect::<HashSet<_>>();
+        let mut permanodes = self
+            .permanodes
+            .iter()
+            .flatten()
+            .filter(|node| !node.disabled && !asked.contains(&node_origin(&node.url)))
+            .cloned()
+            .collect::<Vec<_>>();
+        self.node_stats.retain_available(&mut permanodes);
+        permanodes.iter_mut().for_each(|node| {
+            node.url.set_path(path);
+            node.url.set_query(query);
+        });
+        permanodes
+    }
+
+    fn response_source(&self, url: &Url) -> ResponseSource {
+        let origin = node_origin(url);
+        if self
+            .permanodes
+            .iter()
+            .flatten()
+            .any(|permanode| node_origin(&permanode.url) == origin)
+        {
+            ResponseSource::Permanode(origin)
+        } else {
+            ResponseSource::Node(origin)
+        }
+    }
+
+    // Returns the quorum parameters if quorum is enabled for the client and the endpoint class.
+    fn quorum(&self, quorum_endpoint: Option<QuorumEndpoint>) -> Option<Quorum> {
+        if !self.quorum {
+            return None;
+        }
+        let endpoint = quorum_endpoint?;
+        Quorum::new(
+            endpoint,
+            self.quorum_policies.get(&endpoint),
+            self.min_quorum_size,
+            self.quorum_threshold,
+        )
+    }
+
+    // Sends the request in parallel to as many nodes as the quorum needs.
+    async fn quorum_responses(
+        &self,
+        nodes: Vec<Node>,
+        timeout: Duration,
+        quorum: Quorum,
+        bytes: bool,
+    ) -> Result<Vec<(Url, Result<Response>)>> {
+        if nodes.len() < quorum.min_quorum_size {
+            return Err(Error::QuorumPoolSizeError {
+                available_nodes: nodes.len(),
+                minimum_threshold: quorum.min_quorum_size,
+            });
+        }
+
+        // The probes are only taken of the nodes that are asked
+        let forced = self.node_stats.none_available(&nodes);
+        let nodes = nodes
+            .into_iter()
+            .filter(|node| self.node_stats.acquire(&node.url, forced))
+            .take(quorum.min_quorum_size)
+            .collect::<Vec<_>>();
+        let requests = nodes.into_iter().map(|node| async move {
+            let start = Instant::now();
+            let response = if bytes {
+                self.http_client.get_bytes(node.clone(), timeout).await
+            } else {
+                self.http_client.get(node.clone(), timeout).await
+            };
+            self.node_stats.record_request(&node.url, start.elapsed(), &response);
+            (node_origin(&node.url), response)
+        });
+        Ok(futures::future::join_all(requests).await)
+    }
+
+    // Groups the results by their normalized value and returns the most common one, if enough nodes agreed on it.
+    fn quorum_result<K: PartialEq, V>(
+        &self,
+        path: &str,
+        query: Option<&str>,
+        quorum: Quorum,
+        results: Vec<(Url, Result<(K, V)>)>,
+    ) -> Result<(V, ResponseSource)> {
+        let mut groups: Vec<(K, V, Vec<Url>)> = Vec::new();
+        let mut failed_nodes = Vec::new();
+        let mut error = None;
+        for (url, result) in results {
+            match result {
+                Ok((key, value)) => match groups.iter_mut().find(|(group_key, ..)| *group_key == key) {
+                    Some((.., urls)) => urls.push(url),
+                    None => groups.push((key, value, vec![url])),
+                },
+                Err(e) => {
+                    failed_nodes.push(url);
+                    error.replace(match e {
+                        Error::ResponseError { code: 404, url, .. } => Error::NotFound(url),
+                        e => e,
+                    });
+                }
+            }
+        }
+
+        // The first of the largest groups, so ties are decided by the order of the nodes
+        let largest = groups
+            .iter()
+            .enumerate()
+            .max_by(|(a_index, a), (b_index, b)| a.2.len().cmp(&b.2.len()).then(b_index.cmp(a_index)))
+            .map(|(index, _)| index)
+            .ok_or_else(|| error.unwrap_or_else(|| Error::NodeError("couldn't get a result from any node".into())))?;
+        let (_, value, agreeing_nodes) = groups.swap_remove(largest);
+        let disagreeing_nodes = groups.into_iter().flat_map(|(.., urls)| urls).collect::<Vec<_>>();
+
+        let report = QuorumReport {
+            endpoint: quorum.endpoint,
+            path: query.map_or_else(|| path.to_string(), |query| format!("{path}?{query}")),
+            reached: quorum.is_reached(agreeing_nodes.len()),
+            agreeing_nodes,
+            disagreeing_nodes,
+            failed_nodes,
+        };
+        if !report.disagreeing_nodes.is_empty() {
+            log::warn!(
+                "nodes {:?} disagree with {:?} on {}",
+                report.disagreeing_nodes,
+                report.agreeing_nodes,
+                report.path
+            );
+        }
+        if let Ok(listeners) = self.quorum_report_listeners.read() {
+            for listener in listeners.iter() {
+                listener(&report);
+            }
+        }
+
+        if report.reached {
+            Ok((value, ResponseSource::Quorum(report.agreeing_nodes)))
+        } else {
+            Err(Error::QuorumThresholdError {
+                quorum_size: report.agreeing_nodes.len(),
+                minimum_threshold: quorum.min_quorum_size,
+            })
+        }
+    }
+
+    pub(crate) fn add_quorum_report_listener(&self, listener: QuorumReportListener) -> Result<()> {
+        self.quorum_report_listeners
+            .write()
+            .map_err(|_| Error::PoisonError)?
+            .push(listener);
+        Ok(())
+    }
+
     pub(crate) async fn post_request_bytes<T: serde::de::DeserializeOwned>(
         &self,
         path: &str,
         timeout: Duration,
         body: &[u8],
         local_pow: bool,
+        block_id: Option<BlockId>,
     ) -> Result<T> {
         // primary_pow_node should only be used for post request with remote PoW
         let nodes = self.get_nodes(path, None, !local_pow, false).await?;
         if nodes.is_empty() {
             return Err(Error::NodeError("no available nodes with remote Pow".into()));
         }
+        let metadata_path = block_id.map(|block_id| format!("api/core/v2/blocks/{block_id}/metadata"));
+        let retry = retry_post(metadata_path.as_deref());
         let mut error = None;
         // Send requests
+        let forced = self.node_stats.none_available(&nodes);
         for node in nodes {
-            match self.http_client.post_bytes(node, timeout, body).await {
+            if !self.node_stats.acquire(&node.url, forced) {
+                continue;
+            }
+            let start = Instant::now();
+            let response = self.http_client.post_bytes(node.clone(), timeout, retry, body).await;
+            self.node_stats.record_request(&node.url, start.elapsed(), &response);
+            match response {
                 Ok(res) => {
                     match res.status() {
                         200 | 201 => match res.into_json::<T>().await {
@@ -348,16 +746,64 @@ impl NodeManager {
         timeout: Duration,
         json: Value,
         local_pow: bool,
+        block_id: Option<BlockId>,
     ) -> Result<T> {
         // primary_pow_node should only be used for post request with remote PoW
         let nodes = self.get_nodes(path, None, !local_pow, false).await?;
         if nodes.is_empty() {
             return Err(Error::NodeError("no available nodes with remote Pow".into()));
         }
+        let metadata_path = block_id.map(|block_id| format!("api/core/v2/blocks/{block_id}/metadata"));
+        self.post_request_json_from(nodes, timeout, json, retry_post(metadata_path.as_deref()))
+            .await
+    }
+
+    /// Sends a POST request that doesn't involve PoW to the nodes, like GET requests never to the primary PoW node and
+    /// without retries.
+    pub(crate) async fn post_request_json_without_pow<T: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+        timeout: Duration,
+        json: Value,
+    ) -> Result<T> {
+        let nodes = self.get_nodes(path, None, false, false).await?;
+        self.post_request_json_from(nodes, timeout, json, Retry::Never).await
+    }
+
+    /// Sends a POST request only to the node, without PoW and without failing over to other nodes.
+    pub(crate) async fn post_request_json_to<T: serde::de::DeserializeOwned>(
+        &self,
+        url: &Url,
+        path: &str,
+        timeout: Duration,
+        json: Value,
+    ) -> Result<T> {
+        let node = self.node_with_path(url, path)?;
+        self.post_request_json_from(vec![node], timeout, json, Retry::Never)
+            .await
+    }
+
+    async fn post_request_json_from<T: serde::de::DeserializeOwned>(
+        &self,
+        nodes: Vec<Node>,
+        timeout: Duration,
+        json: Value,
+        retry: Retry<'_>,
+    ) -> Result<T> {
         let mut error = None;
         // Send requests
+        let forced = self.node_stats.none_available(&nodes);
         for node in nodes {
-            match self.http_client.post_json(node, timeout, json.clone()).await {
+            if !self.node_stats.acquire(&node.url, forced) {
+                continue;
+            }
+            let start = Instant::now();
+            let response = self
+                .http_client
+                .post_json(node.clone(), timeout, retry, json.clone())
+                .await;
+            self.node_stats.record_request(&node.url, start.elapsed(), &response);
+            match response {
                 Ok(res) => {
                     match res.status() {
                         200 | 201 => match res.into_json::<T>().await {
@@ -378,4 +824,58 @@ impl NodeManager {
         }
         Err(error.unwrap_or_else(|| Error::NodeError("couldn't get a result from any node".into())))
     }
+
+    /// Sends a GET request only to the node, without failing over to other nodes.
+    pub(crate) async fn get_request_to<T: serde::de::DeserializeOwned + std::fmt::Debug + serde::Serialize>(
+        &self,
+        url: &Url,
+        path: &str,
+        timeout: Duration,
+    ) -> Result<T> {
+        let node = self.node_with_path(url, path)?;
+        self.get_request_from(vec![node], path, None, timeout, None)
+            .await
+            .map(|(value, _)| value)
+    }
+
+    /// Sends a DELETE request only to the node, without failing over to other nodes.
+    pub(crate) async fn delete_request_to(&self, url: &Url, path: &str, timeout: Duration) -> Result<()> {
+        let node = self.node_with_path(url, path)?;
+        let start = Instant::now();
+        let response = self.http_client.delete(node.clone(), timeout).await;
+        self.node_stats.record_request(&node.url, start.elapsed(), &response);
+        match response {
+            Ok(_) => Ok(()),
+            Err(Error::ResponseError { code: 404, url, .. }) => Err(Error::NotFound(url)),
+            Err(err) => Err(err),
+        }
+    }
+
+    // The node of the client with the origin of the url, for its credentials, or a node without credentials if the
+    // client doesn't use it. Requests to a single node ignore the circuit breaker, the caller chose the node.
+    fn node_with_path(&self, url: &Url, path: &str) -> Result<Node> {
+        if self.offline {
+            return Err(Error::OfflineMode(path.to_string()));
+        }
+        let origin = node_origin(url);
+        let primary_node = self.primary_node.read().map_err(|_| Error::PoisonError)?.clone();
+        let nodes = self.nodes.read().map_err(|_| Error::PoisonError)?;
+        let mut node = primary_node
+            .iter()
+            .chain(nodes.iter())
+            .chain(self.permanodes.iter().flatten())
+            .chain(self.primary_pow_node.iter())
+            .find(|node| node_origin(&node.url) == origin)
+            .cloned()
+            .unwrap_or_else(|| Node::from(url.clone()));
+        node.url.set_path(path);
+        node.url.set_query(None);
+        Ok(node)
+    }
+}
+
+// Without the block ID a post can't be retried safely, with remote PoW the node would create a new block. The response
+// of the block metadata contains the block ID, so it can replace the response of the post.
+fn retry_post(metadata_path: Option<&str>) -> Retry<'_> {
+    metadata_path.map_or(Retry::Never, Retry::UnlessFound)
 }
@@ -6,6 +6,8 @@ use std::hash::Hash;
 use serde::{Deserialize, Serialize};
 pub use url::Url;
 
+use crate::node_manager::auth::{BasicAuth, JwtAuth, SharedAuthProvider};
+
 /// Node authentication object.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct NodeAuth {
@@ -25,6 +27,37 @@ pub struct Node {
     /// Whether the node is disabled or not.
     #[serde(default)]
     pub disabled: bool,
+    /// Provides the credentials for requests, takes precedence over `auth`.
+    #[serde(skip)]
+    pub auth_provider: Option<SharedAuthProvider>,
+}
+
+impl Node {
+    /// Returns the provider of the credentials for requests to the node: the auth provider if one is set, otherwise
+    /// the JWT or the basic auth of `auth` or the credentials of the url.
+    pub fn auth_provider(&self) -> Option<SharedAuthProvider> {
+        if let Some(auth_provider) = &self.auth_provider {
+            return Some(auth_provider.clone());
+        }
+        if let Some(auth) = &self.auth {
+            if let Some(jwt) = &auth.jwt {
+                return Some(SharedAuthProvider::new(JwtAuth(jwt.clone())));
+            }
+            if let Some((username, password)) = &auth.basic_auth_name_pwd {
+                return Some(SharedAuthProvider::new(BasicAuth {
+                    username: username.clone(),
+                    password: password.clone(),
+                }));
+            }
+        }
+        if !self.url.username().is_empty() {
+            return Some(SharedAuthProvider::new(BasicAuth {
+                username: self.url.username().to_string(),
+                password: self.url.password().unwrap_or_default().to_string(),
+            }));
+        }
+        None
+    }
 }
 
 impl From<Url> for Node {
@@ -33,6 +66,7 @@ impl From<Url> for Node {
             url,
             auth: None,
             disabled: false,
+            auth_provider: None,
         }
     }
 }
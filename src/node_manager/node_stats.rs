@@ -0,0 +1,446 @@
+// Copyright 2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Scores of the nodes from their recent latency, error rate and sync lag, used to order the nodes for requests, and
+//! their circuit breakers
+
+#[cfg(not(target_family = "wasm"))]
+use std::time::Instant;
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+#[cfg(target_family = "wasm")]
+use instant::Instant;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{
+    error::{Error, Result},
+    node_manager::{
+        capabilities::{NodeCapabilities, NodeCapability},
+        circuit_breaker::{CircuitBreaker, CircuitBreakerOptions, CircuitState, CircuitStateChange},
+        node::Node,
+        sync_status::NodeSyncStatus,
+    },
+};
+
+/// Weight of a new sample in the moving averages
+const SAMPLE_WEIGHT: f64 = 0.2;
+/// Latency in milliseconds that halves the score of a node
+const LATENCY_REFERENCE_MS: f64 = 250.0;
+/// Amount of milestones a node can be behind the most synced node before its score is halved
+const SYNC_LAG_REFERENCE: f64 = 2.0;
+/// Factor applied to the score of a node that reported itself as not healthy
+const UNHEALTHY_FACTOR: f64 = 0.1;
+
+/// Statistics of a node, the nodes with the highest score are tried first
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct NodeStats {
+    /// Node url, without credentials
+    pub url: Url,
+    /// Score between 0 and 1, higher is better
+    pub score: f64,
+    /// Moving average of the request latency in milliseconds, `None` until a request was sent to the node
+    #[serde(rename = "latencyMs")]
+    pub latency_ms: Option<f64>,
+    /// Moving average of the failed requests, between 0 and 1
+    #[serde(rename = "errorRate")]
+    pub error_rate: f64,
+    /// Amount of sent requests
+    pub requests: u64,
+    /// Amount of failed requests, errors from the connection, timeouts and 5xx responses
+    pub failures: u64,
+    /// Amount of milestones the node was behind the most synced node during the last node sync
+    #[serde(rename = "syncLag")]
+    pub sync_lag: Option<u32>,
+    /// Health status the node reported during the last node sync
+    #[serde(rename = "isHealthy")]
+    pub is_healthy: Option<bool>,
+    /// Outcome of the last node sync, `None` if the node wasn't synced yet
+    #[serde(rename = "syncStatus")]
+    pub sync_status: Option<NodeSyncStatus>,
+    /// State of the circuit breaker, the node is skipped while it's open
+    #[serde(rename = "circuitState")]
+    pub circuit_state: CircuitState,
+    /// Routes and features of the node from the last node sync, `None` if it wasn't synced yet
+    pub capabilities: Option<NodeCapabilities>,
+}
+
+impl NodeStats {
+    fn new(url: Url) -> Self {
+        Self {
+            url,
+            score: 1.0,
+            latency_ms: None,
+            error_rate: 0.0,
+            requests: 0,
+            failures: 0,
+            sync_lag: None,
+            is_healthy: None,
+            sync_status: None,
+            circuit_state: CircuitState::Closed,
+            capabilities: None,
+        }
+    }
+
+    fn update_score(&mut self) {
+        let latency_factor = self
+            .latency_ms
+            .map_or(1.0, |latency| LATENCY_REFERENCE_MS / (LATENCY_REFERENCE_MS + latency));
+        let sync_factor = self
+            .sync_lag
+            .map_or(1.0, |lag| SYNC_LAG_REFERENCE / (SYNC_LAG_REFERENCE + lag as f64));
+        let health_factor = match (&self.sync_status, self.is_healthy) {
+            // Quarantined nodes are only used if no other node is left
+            (Some(NodeSyncStatus::WrongNetwork { .. }), _) => 0.0,
+            (_, Some(false)) => UNHEALTHY_FACTOR,
+            _ => 1.0,
+        };
+        // Failures weigh more than latency, a failed request often means waiting for a timeout first
+        let reliability_factor = (1.0 - self.error_rate).powi(2);
+        self.score = latency_factor * sync_factor * health_factor * reliability_factor;
+    }
+}
+
+type CircuitStateListener = Arc<dyn Fn(&CircuitStateChange) + Send + Sync>;
+
+struct NodeEntry {
+    stats: NodeStats,
+    circuit_breaker: CircuitBreaker,
+}
+
+impl NodeEntry {
+    fn new(url: Url) -> Self {
+        Self {
+            stats: NodeStats::new(url),
+            circuit_breaker: CircuitBreaker::default(),
+        }
+    }
+}
+
+/// The stats and circuit breakers of all nodes that were used, shared between the node manager and the node syncing
+/// process
+#[derive(Clone, Default)]
+pub(crate) struct NodeStatsMap {
+    nodes: Arc<RwLock<HashMap<Url, NodeEntry>>>,
+    circuit_breaker_options: CircuitBreakerOptions,
+    circuit_state_listeners: Arc<RwLock<Vec<CircuitStateListener>>>,
+}
+
+impl std::fmt::Debug for NodeStatsMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NodeStatsMap")
+            .field("nodes", &self.snapshot().unwrap_or_default())
+            .field("circuit_breaker_options", &self.circuit_breaker_options)
+            .finish()
+    }
+}
+
+impl NodeStatsMap {
+    pub(crate) fn new<'a>(
+        nodes: impl IntoIterator<Item = &'a Node>,
+        circuit_breaker_options: CircuitBreakerOptions,
+    ) -> Self {
+        Self {
+            nodes: Arc::new(RwLock::new(
+                nodes
+                    .into_iter()
+                    .map(|node| {
+                        let key = node_origin(&node.url);
+                        (key.clone(), NodeEntry::new(key))
+                    })
+                    .collect(),
+            )),
+            circuit_breaker_options,
+            circuit_state_listeners: Default::default(),
+        }
+    }
+
+    /// Records the outcome of a request, responses with a 4xx status count as success since the node is reachable.
+    pub(crate) fn record_request<T>(&self, url: &Url, latency: Duration, response: &Result<T>) {
+        let failed = match response {
+            Ok(_) => false,
+            Err(Error::ResponseError { code, .. }) => *code >= 500,
+            Err(_) => true,
+        };
+        self.update(url, |entry| {
+            let stats = &mut entry.stats;
+            let latency_ms = latency.as_secs_f64() * 1000.0;
+            stats.latency_ms = Some(
+                stats
+                    .latency_ms
+                    .map_or(latency_ms, |average| moving_average(average, latency_ms)),
+            );
+            stats.error_rate = moving_average(stats.error_rate, if failed { 1.0 } else { 0.0 });
+            stats.requests += 1;
+            if failed {
+                stats.failures += 1;
+            }
+            entry
+                .circuit_breaker
+                .record(failed, &self.circuit_breaker_options, Instant::now());
+        });
+    }
+
+    /// Records the health and the sync lag from the node info of the node syncing process.
+    pub(crate) fn record_sync(&self, url: &Url, is_healthy: bool, sync_lag: Option<u32>) {
+        self.update(url, |entry| {
+            entry.stats.is_healthy.replace(is_healthy);
+            entry.stats.sync_lag = sync_lag;
+        });
+    }
+
+    /// Records whether the node syncing process added the node to the pool.
+    pub(crate) fn record_sync_status(&self, url: &Url, sync_status: NodeSyncStatus) {
+        self.update(url, |entry| {
+            entry.stats.sync_status.replace(sync_status);
+        });
+    }
+
+    /// Records the routes and features from the node syncing process.
+    pub(crate) fn record_capabilities(&self, url: &Url, capabilities: NodeCapabilities) {
+        self.update(url, |entry| {
+            entry.stats.capabilities.replace(capabilities);
+        });
+    }
+
+    /// Removes the nodes without the capability. Nodes that weren't synced yet are kept, they can still have it.
+    pub(crate) fn retain_capable(&self, nodes: &mut Vec<Node>, capability: NodeCapability) {
+        if let Ok(entries) = self.nodes.read() {
+            nodes.retain(|node| {
+                entries
+                    .get(&node_origin(&node.url))
+                    .and_then(|entry| entry.stats.capabilities.as_ref())
+                    .is_none_or(|capabilities| capabilities.supports(capability))
+            });
+        }
+    }
+
+    /// Removes the nodes with an open circuit breaker, unless no other node would be left. It only checks the state,
+    /// the probe of a node is taken with [`NodeStatsMap::acquire()`] once a request is sent to it.
+    pub(crate) fn retain_available(&self, nodes: &mut Vec<Node>) {
+        if let Ok(entries) = self.nodes.read() {
+            let now = Instant::now();
+            let available = nodes
+                .iter()
+                .filter(|node| {
+                    entries
+                        .get(&node_origin(&node.url))
+                        .is_none_or(|entry| entry.circuit_breaker.is_available(now))
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            if !available.is_empty() {
+                *nodes = available;
+            }
+        }
+    }
+
+    /// Whether the circuit breakers skip all nodes, then [`NodeStatsMap::retain_available()`] kept them and they are
+    /// tried anyway.
+    pub(crate) fn none_available(&self, nodes: &[Node]) -> bool {
+        let now = Instant::now();
+        self.nodes.read().is_ok_and(|entries| {
+            !nodes.iter().any(|node| {
+                entries
+                    .get(&node_origin(&node.url))
+                    .is_none_or(|entry| entry.circuit_breaker.is_available(now))
+            })
+        })
+    }
+
+    /// Called right before a request is sent to the node, takes the probe if the cooldown of the node elapsed. Returns
+    /// whether the request can be sent, which is always the case if `force` is set. A node whose probe was taken by
+    /// another request in the meantime is skipped.
+    pub(crate) fn acquire(&self, url: &Url, force: bool) -> bool {
+        let mut acquired = force;
+        let mut change = None;
+        if let Ok(mut entries) = self.nodes.write() {
+            let key = node_origin(url);
+            let entry = entries.entry(key.clone()).or_insert_with(|| NodeEntry::new(key));
+            let previous_state = entry.circuit_breaker.state();
+            acquired |= entry
+                .circuit_breaker
+                .try_acquire(&self.circuit_breaker_options, Instant::now());
+            change = state_change(entry, previous_state);
+        }
+        self.notify(change);
+        acquired
+    }
+
+    /// Orders the nodes by their score, highest first. Nodes with the same score keep their order.
+    pub(crate) fn sort_by_score(&self, nodes: &mut [Node]) {
+        if let Ok(entries) = self.nodes.read() {
+            let score = |node: &Node| {
+                entries
+                    .get(&node_origin(&node.url))
+                    .map_or(1.0, |entry| entry.stats.score)
+            };
+            nodes.sort_by(|a, b| score(b).total_cmp(&score(a)));
+        }
+    }
+
+    /// Returns the stats of all nodes, highest score first.
+    pub(crate) fn snapshot(&self) -> Result<Vec<NodeStats>> {
+        let mut stats = self
+            .nodes
+            .read()
+            .map_err(|_| Error::PoisonError)?
+            .values()
+            .map(|entry| entry.stats.clone())
+            .collect::<Vec<_>>();
+        stats.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.url.cmp(&b.url)));
+        Ok(stats)
+    }
+
+    /// Removes the stats of a node that was removed from the pool.
+    pub(crate) fn remove(&self, url: &Url) {
+        if let Ok(mut entries) = self.nodes.write() {
+            entries.remove(&node_origin(url));
+        }
+    }
+
+    pub(crate) fn add_circuit_state_listener(&self, listener: CircuitStateListener) -> Result<()> {
+        self.circuit_state_listeners
+            .write()
+            .map_err(|_| Error::PoisonError)?
+            .push(listener);
+        Ok(())
+    }
+
+    fn update(&self, url: &Url, f: impl FnOnce(&mut NodeEntry)) {
+        let mut change = None;
+        if let Ok(mut entries) = self.nodes.write() {
+            let key = node_origin(url);
+            let entry = entries.entry(key.clone()).or_insert_with(|| NodeEntry::new(key));
+            let previous_state = entry.circuit_breaker.state();
+            f(entry);
+            entry.stats.circuit_state = entry.circuit_breaker.state();
+            entry.stats.update_score();
+            change = state_change(entry, previous_state);
+        }
+        self.notify(change);
+    }
+
+    // Called without holding the lock of the nodes, so the listeners can access the stats
+    fn notify(&self, changes: impl IntoIterator<Item = CircuitStateChange>) {
+        for change in changes {
+            match change.state {
+                CircuitState::Open => log::warn!("skipping {} after failed requests", change.url),
+                CircuitState::HalfOpen => log::debug!("probing {}", change.url),
+                CircuitState::Closed => log::info!("using {} again", change.url),
+            }
+            if let Ok(listeners) = self.circuit_state_listeners.read() {
+                for listener in listeners.iter() {
+                    listener(&change);
+                }
+            }
+        }
+    }
+}
+
+fn state_change(entry: &mut NodeEntry, previous_state: CircuitState) -> Option<CircuitStateChange> {
+    let state = entry.circuit_breaker.state();
+    entry.stats.circuit_state = state;
+    (state != previous_state).then(|| CircuitStateChange {
+        url: entry.stats.url.clone(),
+        previous_state,
+        state,
+    })
+}
+
+fn moving_average(average: f64, sample: f64) -> f64 {
+    average + SAMPLE_WEIGHT * (sample - average)
+}
+
+// The node manager sets the path and query of the node url for each request and the credentials shouldn't end up in
+// the stats, so only the origin identifies a node
+pub(crate) fn node_origin(url: &Url) -> Url {
+    let mut url = url.clone();
+    url.set_path("");
+    url.set_query(None);
+    url.set_fragment(None);
+    // Only fails for urls that can't have credentials, then there are none to remove
+    url.set_username("").ok();
+    url.set_password(None).ok();
+    url
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(url: &str) -> Node {
+        Node::from(Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn slow_and_failing_nodes_come_last() {
+        let nodes = [
+            node("http://slow:14265"),
+            node("http://failing:14265"),
+            node("http://fast:14265"),
+        ];
+        let stats = NodeStatsMap::new(&nodes, CircuitBreakerOptions::default());
+        let path = |url: &str| Url::parse(url).unwrap().join("api/core/v2/info").unwrap();
+
+        stats.record_request(&path("http://slow:14265"), Duration::from_millis(900), &Ok(()));
+        stats.record_request(&path("http://fast:14265"), Duration::from_millis(20), &Ok(()));
+        for _ in 0..5 {
+            stats.record_request(
+                &path("http://failing:14265"),
+                Duration::from_millis(20),
+                &Err::<(), _>(Error::ResponseError {
+                    code: 503,
+                    text: String::new(),
+                    url: String::new(),
+                }),
+            );
+        }
+        // Not found responses don't count as failures
+        stats.record_request(
+            &path("http://fast:14265"),
+            Duration::from_millis(20),
+            &Err::<(), _>(Error::ResponseError {
+                code: 404,
+                text: String::new(),
+                url: String::new(),
+            }),
+        );
+
+        let mut ordered = nodes.to_vec();
+        stats.sort_by_score(&mut ordered);
+        let hosts = ordered
+            .iter()
+            .map(|node| node.url.host_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(hosts, ["fast", "slow", "failing"]);
+    }
+
+    #[test]
+    fn sync_lag_and_health_lower_the_score() {
+        let nodes = [node("http://behind:14265"), node("http://synced:14265")];
+        let stats = NodeStatsMap::new(&nodes, CircuitBreakerOptions::default());
+
+        stats.record_sync(&nodes[0].url, true, Some(4));
+        stats.record_sync(&nodes[1].url, true, Some(0));
+        let snapshot = stats.snapshot().unwrap();
+        assert_eq!(snapshot[0].url.host_str(), Some("synced"));
+        assert_eq!(snapshot[1].sync_lag, Some(4));
+
+        stats.record_sync(&nodes[1].url, false, Some(0));
+        assert_eq!(stats.snapshot().unwrap()[0].url.host_str(), Some("behind"));
+    }
+
+    #[test]
+    fn credentials_are_not_part_of_the_stats() {
+        let mut url = Url::parse("https://node:443/api/core/v2/info?key=value").unwrap();
+        url.set_username("user").unwrap();
+        url.set_password(Some("password")).unwrap();
+
+        assert_eq!(node_origin(&url).as_str(), "https://node/");
+    }
+}
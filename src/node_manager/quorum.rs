@@ -0,0 +1,235 @@
+// Copyright 2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Quorum policies per endpoint class and the normalization of responses before they're compared
+
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use url::Url;
+
+/// Class of endpoints with its own quorum policy.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum QuorumEndpoint {
+    /// Outputs and their metadata
+    Outputs,
+    /// Milestones
+    Milestones,
+    /// Blocks, their metadata and the blocks including a transaction
+    Blocks,
+    /// Output ID queries of the indexer plugin
+    Indexer,
+}
+
+impl QuorumEndpoint {
+    // Only block requests were compared before the policies could be configured, the other classes are opt-in since
+    // quorum sends every request to multiple nodes
+    fn default_policy(self) -> QuorumPolicy {
+        match self {
+            Self::Blocks => QuorumPolicy::new(),
+            Self::Outputs | Self::Milestones | Self::Indexer => QuorumPolicy::disabled(),
+        }
+    }
+
+    // Fields that differ between nodes without the data being different
+    fn volatile_fields(self) -> &'static [&'static str] {
+        match self {
+            Self::Blocks => &["ledgerIndex", "shouldPromote", "shouldReattach"],
+            Self::Outputs | Self::Milestones | Self::Indexer => &["ledgerIndex"],
+        }
+    }
+}
+
+/// Quorum policy of an endpoint class, only used if quorum is enabled on the client.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[must_use]
+pub struct QuorumPolicy {
+    #[serde(default = "default_enabled")]
+    pub(crate) enabled: bool,
+    #[serde(rename = "minQuorumSize", default)]
+    pub(crate) min_quorum_size: Option<usize>,
+    #[serde(rename = "quorumThreshold", default)]
+    pub(crate) quorum_threshold: Option<usize>,
+    #[serde(rename = "compareLedgerIndex", default)]
+    pub(crate) compare_ledger_index: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Default for QuorumPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            min_quorum_size: None,
+            quorum_threshold: None,
+            compare_ledger_index: false,
+        }
+    }
+}
+
+impl QuorumPolicy {
+    /// Creates a policy that uses the quorum size and threshold of the client.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Creates a policy that sends the requests to a single node.
+    pub fn disabled() -> Self {
+        Self::new().enabled(false)
+    }
+
+    /// Sets whether the responses of multiple nodes are compared.
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    /// Sets the amount of nodes the request is sent to, instead of the minimum quorum size of the client.
+    pub fn min_quorum_size(mut self, min_quorum_size: usize) -> Self {
+        self.min_quorum_size.replace(min_quorum_size);
+        self
+    }
+
+    /// Sets the % of nodes that have to return the same response, instead of the quorum threshold of the client.
+    pub fn quorum_threshold(mut self, quorum_threshold: usize) -> Self {
+        self.quorum_threshold.replace(quorum_threshold.min(100));
+        self
+    }
+
+    /// Sets whether the `ledgerIndex` fields of the responses are compared, so the responses only agree if the nodes
+    /// answered at the same ledger index. The requests aren't sent for a specific ledger index, nodes that are a
+    /// milestone apart disagree then. Otherwise the ledger index is ignored, so they still agree on the same data.
+    pub fn compare_ledger_index(mut self, compare_ledger_index: bool) -> Self {
+        self.compare_ledger_index = compare_ledger_index;
+        self
+    }
+}
+
+pub(crate) type QuorumReportListener = Arc<dyn Fn(&QuorumReport) + Send + Sync>;
+
+/// Quorum parameters of a single request.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Quorum {
+    pub(crate) endpoint: QuorumEndpoint,
+    pub(crate) min_quorum_size: usize,
+    pub(crate) quorum_threshold: usize,
+    pub(crate) compare_ledger_index: bool,
+}
+
+impl Quorum {
+    /// Returns the quorum parameters for the endpoint class, if its policy is enabled.
+    pub(crate) fn new(
+        endpoint: QuorumEndpoint,
+        policy: Option<&QuorumPolicy>,
+        min_quorum_size: usize,
+        quorum_threshold: usize,
+    ) -> Option<Self> {
+        let policy = policy.cloned().unwrap_or_else(|| endpoint.default_policy());
+        policy.enabled.then(|| Self {
+            endpoint,
+            min_quorum_size: policy.min_quorum_size.unwrap_or(min_quorum_size),
+            quorum_threshold: policy.quorum_threshold.unwrap_or(quorum_threshold),
+            compare_ledger_index: policy.compare_ledger_index,
+        })
+    }
+
+    pub(crate) fn is_reached(&self, agreeing_nodes: usize) -> bool {
+        agreeing_nodes as f64 >= self.min_quorum_size as f64 * (self.quorum_threshold as f64 / 100.0)
+    }
+
+    /// Removes the fields that can differ between nodes that have the same data. The ledger index stays if it's
+    /// compared.
+    pub(crate) fn normalize(&self, mut value: Value) -> Value {
+        for field in self.endpoint.volatile_fields() {
+            if !(self.compare_ledger_index && *field == "ledgerIndex") {
+                remove_field(&mut value, field);
+            }
+        }
+        value
+    }
+}
+
+fn remove_field(value: &mut Value, field: &str) {
+    match value {
+        Value::Object(map) => {
+            map.remove(field);
+            map.values_mut().for_each(|value| remove_field(value, field));
+        }
+        Value::Array(values) => values.iter_mut().for_each(|value| remove_field(value, field)),
+        _ => {}
+    }
+}
+
+/// Outcome of a request that was sent to multiple nodes for quorum.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct QuorumReport {
+    /// Endpoint class of the request
+    pub endpoint: QuorumEndpoint,
+    /// Path and query of the request
+    pub path: String,
+    /// Whether enough nodes returned the same response
+    pub reached: bool,
+    /// Nodes that returned the response that was used
+    #[serde(rename = "agreeingNodes")]
+    pub agreeing_nodes: Vec<Url>,
+    /// Nodes that returned a different response
+    #[serde(rename = "disagreeingNodes")]
+    pub disagreeing_nodes: Vec<Url>,
+    /// Nodes that returned an error
+    #[serde(rename = "failedNodes")]
+    pub failed_nodes: Vec<Url>,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn ledger_index_is_only_compared_when_enabled() {
+        let response = |ledger_index: u32| {
+            json!({
+                "metadata": { "blockId": "0x01", "isSpent": false, "ledgerIndex": ledger_index },
+                "output": { "type": 3, "amount": "1000000" },
+            })
+        };
+        let quorum = Quorum::new(QuorumEndpoint::Outputs, Some(&QuorumPolicy::new()), 3, 66).unwrap();
+        assert_eq!(quorum.normalize(response(10)), quorum.normalize(response(11)));
+
+        let compared = Quorum::new(
+            QuorumEndpoint::Outputs,
+            Some(&QuorumPolicy::new().compare_ledger_index(true)),
+            3,
+            66,
+        )
+        .unwrap();
+        assert_ne!(compared.normalize(response(10)), compared.normalize(response(11)));
+    }
+
+    #[test]
+    fn policies() {
+        // Only block requests are compared unless a policy enables the other classes
+        assert!(Quorum::new(QuorumEndpoint::Indexer, None, 3, 66).is_none());
+        assert!(Quorum::new(QuorumEndpoint::Outputs, None, 3, 66).is_none());
+        assert!(Quorum::new(QuorumEndpoint::Milestones, None, 3, 66).is_none());
+        assert!(Quorum::new(QuorumEndpoint::Blocks, Some(&QuorumPolicy::disabled()), 3, 66).is_none());
+        let quorum = Quorum::new(QuorumEndpoint::Blocks, None, 3, 66).unwrap();
+        assert!(quorum.is_reached(2));
+        assert!(!quorum.is_reached(1));
+
+        let quorum = Quorum::new(
+            QuorumEndpoint::Indexer,
+            Some(&QuorumPolicy::new().min_quorum_size(4).quorum_threshold(100)),
+            3,
+            66,
+        )
+        .unwrap();
+        assert_eq!(quorum.min_quorum_size, 4);
+        assert!(!quorum.is_reached(3));
+    }
+}
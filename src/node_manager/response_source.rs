@@ -0,0 +1,34 @@
+// Copyright 2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Where the response to a request came from, a node of the pool, a permanode or the response cache
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// The source that answered a request.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", content = "url", rename_all = "camelCase")]
+pub enum ResponseSource {
+    /// A node of the pool
+    Node(Url),
+    /// The nodes that agreed on the response with quorum enabled
+    Quorum(Vec<Url>),
+    /// A permanode, preferred for the request or asked after the nodes of the pool had pruned the data
+    Permanode(Url),
+    /// The response cache
+    Cache,
+}
+
+/// Whether the route returns data that nodes prune after some time, but permanodes keep. A 404 on these routes is
+/// retried with the permanodes.
+pub(crate) fn is_history_route(path: &str) -> bool {
+    [
+        "api/core/v2/blocks/",
+        "api/core/v2/outputs/",
+        "api/core/v2/transactions/",
+        "api/core/v2/milestones/",
+    ]
+    .iter()
+    .any(|prefix| path.starts_with(prefix))
+}
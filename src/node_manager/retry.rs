@@ -0,0 +1,196 @@
+// Copyright 2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Retries of requests to a node that failed with a transient error, before the next node is tried
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    constants::{
+        DEFAULT_RETRYABLE_STATUS_CODES, DEFAULT_RETRY_POLICY_INITIAL_BACKOFF, DEFAULT_RETRY_POLICY_MAX_ATTEMPTS,
+        DEFAULT_RETRY_POLICY_MAX_BACKOFF,
+    },
+    Error,
+};
+
+/// Errors without a response that can be retried.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum RetryableError {
+    /// The request timed out
+    Timeout,
+    /// No connection could be established
+    Connect,
+}
+
+impl RetryableError {
+    /// Returns the kind of the error, if it's one that can be retried.
+    pub(crate) fn of(error: &Error) -> Option<Self> {
+        match error {
+            Error::ReqwestError(error) if error.is_timeout() => Some(Self::Timeout),
+            #[cfg(not(target_family = "wasm"))]
+            Error::ReqwestError(error) if error.is_connect() => Some(Self::Connect),
+            Error::TransportError { kind, .. } => *kind,
+            _ => None,
+        }
+    }
+}
+
+/// Policy for retrying requests to the same node.
+///
+/// Block posts are only retried if the PoW was done locally, then the node is asked for the block ID before the block
+/// is sent again. With remote PoW the node would create a new block for every attempt.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[must_use]
+pub struct RetryPolicy {
+    #[serde(default = "default_max_attempts", rename = "maxAttempts")]
+    pub(crate) max_attempts: u32,
+    #[serde(default = "default_initial_backoff", rename = "initialBackoff")]
+    pub(crate) initial_backoff: Duration,
+    #[serde(default = "default_max_backoff", rename = "maxBackoff")]
+    pub(crate) max_backoff: Duration,
+    #[serde(default = "default_retryable_status_codes", rename = "retryableStatusCodes")]
+    pub(crate) retryable_status_codes: Vec<u16>,
+    #[serde(default, rename = "retryableErrors")]
+    pub(crate) retryable_errors: Vec<RetryableError>,
+}
+
+fn default_max_attempts() -> u32 {
+    DEFAULT_RETRY_POLICY_MAX_ATTEMPTS
+}
+
+fn default_initial_backoff() -> Duration {
+    DEFAULT_RETRY_POLICY_INITIAL_BACKOFF
+}
+
+fn default_max_backoff() -> Duration {
+    DEFAULT_RETRY_POLICY_MAX_BACKOFF
+}
+
+fn default_retryable_status_codes() -> Vec<u16> {
+    DEFAULT_RETRYABLE_STATUS_CODES.to_vec()
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_max_attempts(),
+            initial_backoff: default_initial_backoff(),
+            max_backoff: default_max_backoff(),
+            retryable_status_codes: default_retryable_status_codes(),
+            retryable_errors: Vec::new(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Creates the default retry policy, which retries 429, 502, 503 and 504 responses.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Creates a policy that sends every request only once per node.
+    pub fn disabled() -> Self {
+        Self::new().max_attempts(1)
+    }
+
+    /// Sets how often a request is sent to the same node at most, including the first attempt.
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Sets the delay before the first retry, it doubles for every further retry. A random part of up to half of the
+    /// delay is subtracted, so clients don't retry in lockstep.
+    pub fn initial_backoff(mut self, initial_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self
+    }
+
+    /// Sets the upper limit of the delay between retries. A `Retry-After` header that asks for a longer delay makes the
+    /// request fail instead.
+    pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Sets the status codes of responses that are retried.
+    pub fn retryable_status_codes(mut self, retryable_status_codes: Vec<u16>) -> Self {
+        self.retryable_status_codes = retryable_status_codes;
+        self
+    }
+
+    /// Sets the errors without a response that are retried, none by default since the next node is tried anyway.
+    pub fn retryable_errors(mut self, retryable_errors: Vec<RetryableError>) -> Self {
+        self.retryable_errors = retryable_errors;
+        self
+    }
+
+    pub(crate) fn is_retryable_status(&self, status: u16) -> bool {
+        self.retryable_status_codes.contains(&status)
+    }
+
+    pub(crate) fn is_retryable_error(&self, error: &Error) -> bool {
+        RetryableError::of(error).is_some_and(|kind| self.retryable_errors.contains(&kind))
+    }
+
+    /// Returns the delay before the next attempt, if there is one. `attempt` is the number of the failed attempt,
+    /// starting at 1.
+    pub(crate) fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+        match retry_after {
+            Some(retry_after) => (retry_after <= self.max_backoff).then_some(retry_after),
+            None => Some(self.backoff(attempt)),
+        }
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        let backoff = self
+            .initial_backoff
+            .checked_mul(2u32.saturating_pow(attempt - 1))
+            .map_or(self.max_backoff, |backoff| backoff.min(self.max_backoff));
+        let mut entropy = [0u8; 4];
+        let jitter = crypto::utils::rand::fill(&mut entropy)
+            .map_or(0.0, |_| f64::from(u32::from_le_bytes(entropy)) / f64::from(u32::MAX));
+        backoff.mul_f64(1.0 - jitter / 2.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exponential_backoff_with_jitter() {
+        let policy = RetryPolicy::new()
+            .max_attempts(5)
+            .initial_backoff(Duration::from_secs(1))
+            .max_backoff(Duration::from_secs(3));
+
+        for _ in 0..20 {
+            let first = policy.delay(1, None).unwrap();
+            assert!(first >= Duration::from_millis(500) && first <= Duration::from_secs(1));
+            let second = policy.delay(2, None).unwrap();
+            assert!(second >= Duration::from_secs(1) && second <= Duration::from_secs(2));
+            let capped = policy.delay(4, None).unwrap();
+            assert!(capped >= Duration::from_millis(1500) && capped <= Duration::from_secs(3));
+        }
+        assert_eq!(policy.delay(5, None), None);
+    }
+
+    #[test]
+    fn retry_after() {
+        let policy = RetryPolicy::new().max_backoff(Duration::from_secs(10));
+
+        assert_eq!(
+            policy.delay(1, Some(Duration::from_secs(10))),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(policy.delay(1, Some(Duration::from_secs(11))), None);
+        assert_eq!(RetryPolicy::disabled().delay(1, None), None);
+    }
+}
@@ -0,0 +1,166 @@
+// Copyright 2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! The network the nodes are on and whether the node syncing process added them to the pool
+
+use bee_api_types::responses::ProtocolResponse;
+use bee_block::protocol::ProtocolParameters;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::hash_network;
+
+/// The network a node is on, from its node info.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct NodeNetwork {
+    /// Network name
+    #[serde(rename = "networkName")]
+    pub network_name: String,
+    /// Network ID, the hash of the network name
+    #[serde(rename = "networkId")]
+    pub network_id: u64,
+    /// Protocol version
+    #[serde(rename = "protocolVersion")]
+    pub protocol_version: u8,
+    /// Bech32 HRP of the addresses
+    #[serde(rename = "bech32Hrp")]
+    pub bech32_hrp: String,
+}
+
+impl From<&ProtocolResponse> for NodeNetwork {
+    fn from(protocol: &ProtocolResponse) -> Self {
+        Self {
+            network_name: protocol.network_name.clone(),
+            network_id: hash_network(&protocol.network_name).unwrap_or_default(),
+            protocol_version: protocol.version,
+            bech32_hrp: protocol.bech32_hrp.clone(),
+        }
+    }
+}
+
+impl From<&ProtocolParameters> for NodeNetwork {
+    fn from(protocol_parameters: &ProtocolParameters) -> Self {
+        Self {
+            network_name: protocol_parameters.network_name().to_string(),
+            network_id: hash_network(protocol_parameters.network_name()).unwrap_or_default(),
+            protocol_version: protocol_parameters.version(),
+            bech32_hrp: protocol_parameters.bech32_hrp().to_string(),
+        }
+    }
+}
+
+/// Outcome of the last node sync for a node.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum NodeSyncStatus {
+    /// The node is healthy and on the expected network, it's in the pool
+    Synced,
+    /// The node info couldn't be requested
+    Unreachable,
+    /// The node reported itself as not healthy
+    Unhealthy,
+    /// The node is on another network than the other nodes or the configured one, it's quarantined until it's on
+    /// the expected network again
+    WrongNetwork {
+        /// Network of the node
+        network: NodeNetwork,
+        /// Network of the pool, `None` if the client has no network yet and no healthy node is on the configured
+        /// network
+        expected: Option<NodeNetwork>,
+    },
+    /// Remote proof of work is used, but the node doesn't support it
+    NoRemotePow,
+}
+
+/// Returns the network the nodes have to be on. Once the client has a network, from an earlier sync or the configured
+/// protocol parameters, it's pinned and never replaced by the network of the nodes, even if most of them are on
+/// another one. Otherwise it's the network of most healthy nodes whose network name contains the configured one.
+pub(crate) fn expected_network<'a>(
+    networks: impl IntoIterator<Item = &'a NodeNetwork>,
+    configured_network: Option<&str>,
+    pinned_network: Option<NodeNetwork>,
+) -> Option<NodeNetwork> {
+    if let Some(pinned_network) =
+        pinned_network.filter(|network| configured_network.is_none_or(|name| network.network_name.contains(name)))
+    {
+        return Some(pinned_network);
+    }
+
+    let mut counts: Vec<(&NodeNetwork, usize)> = Vec::new();
+    for network in networks {
+        if configured_network.is_some_and(|name| !network.network_name.contains(name)) {
+            continue;
+        }
+        match counts.iter_mut().find(|(counted, _)| *counted == network) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((network, 1)),
+        }
+    }
+    // On a tie the network of the first node wins
+    counts
+        .into_iter()
+        .rev()
+        .max_by_key(|(_, count)| *count)
+        .map(|(network, _)| network.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn network(name: &str, protocol_version: u8, bech32_hrp: &str) -> NodeNetwork {
+        NodeNetwork {
+            network_name: name.to_string(),
+            network_id: hash_network(name).unwrap(),
+            protocol_version,
+            bech32_hrp: bech32_hrp.to_string(),
+        }
+    }
+
+    #[test]
+    fn majority_of_configured_network() {
+        let mainnet = network("shimmer", 2, "smr");
+        let devnet = network("testnet-1", 2, "rms");
+        let outdated = network("shimmer", 1, "smr");
+        let networks = [
+            devnet.clone(),
+            mainnet.clone(),
+            outdated,
+            devnet.clone(),
+            devnet.clone(),
+            mainnet.clone(),
+        ];
+
+        assert_eq!(expected_network(&networks, None, None), Some(devnet.clone()));
+        assert_eq!(
+            expected_network(&networks, Some("shimmer"), None),
+            Some(mainnet.clone())
+        );
+        assert_eq!(expected_network(&networks, Some("iota"), None), None);
+
+        // The first network wins a tie
+        let tied = [devnet.clone(), mainnet.clone()];
+        assert_eq!(expected_network(&tied, None, None), Some(devnet.clone()));
+    }
+
+    #[test]
+    fn pinned_network() {
+        let mainnet = network("shimmer", 2, "smr");
+        let devnet = network("testnet-1", 2, "rms");
+        let networks = [devnet.clone(), devnet.clone(), mainnet.clone()];
+
+        // The pinned network stays, even without any node on it
+        assert_eq!(
+            expected_network(&networks, None, Some(mainnet.clone())),
+            Some(mainnet.clone())
+        );
+        assert_eq!(
+            expected_network(&[], Some("shimmer"), Some(mainnet.clone())),
+            Some(mainnet.clone())
+        );
+        // Unless it's not the configured network
+        assert_eq!(
+            expected_network(&networks, Some("testnet"), Some(mainnet)),
+            Some(devnet)
+        );
+    }
+}
@@ -0,0 +1,387 @@
+// Copyright 2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! The transport that sends single HTTP requests to a node, retries and credential refreshes are handled on top of it
+
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    hash::{Hash, Hasher},
+    ops::Deref,
+    sync::Arc,
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use url::Url;
+
+use crate::{node_manager::auth::Credentials, Result};
+
+/// A request to a node.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransportRequest {
+    /// Url with path and query, without credentials
+    pub url: Url,
+    /// Time after which the request should fail
+    pub timeout: Duration,
+    /// Credentials of the node, if it has an auth provider
+    pub credentials: Option<Credentials>,
+}
+
+/// The response of a node, with any status code.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TransportResponse {
+    /// Status code
+    pub status: u16,
+    /// Headers with lowercase names
+    pub headers: HashMap<String, String>,
+    /// Body
+    pub body: Vec<u8>,
+}
+
+impl TransportResponse {
+    /// Returns the value of the header, the name is case-insensitive.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(&name.to_lowercase()).map(String::as_str)
+    }
+}
+
+/// Sends the requests to the nodes. Errors without a response can be returned as [`Error::TransportError`] to be
+/// retried according to the retry policy.
+///
+/// [`Error::TransportError`]: crate::Error::TransportError
+#[cfg_attr(target_family = "wasm", async_trait(?Send))]
+#[cfg_attr(not(target_family = "wasm"), async_trait)]
+pub trait Transport: Send + Sync + Debug {
+    /// Sends a GET request for JSON.
+    async fn get(&self, request: TransportRequest) -> Result<TransportResponse>;
+
+    /// Sends a GET request for the binary representation, with `Accept: application/vnd.iota.serializer-v1`.
+    async fn get_bytes(&self, request: TransportRequest) -> Result<TransportResponse>;
+
+    /// Sends a POST request with a JSON body.
+    async fn post_json(&self, request: TransportRequest, json: &Value) -> Result<TransportResponse>;
+
+    /// Sends a POST request with the binary representation as body, with `Content-Type:
+    /// application/vnd.iota.serializer-v1`.
+    async fn post_bytes(&self, request: TransportRequest, body: &[u8]) -> Result<TransportResponse>;
+
+    /// Sends a DELETE request. Not implemented by default, it's only needed to remove peers.
+    async fn delete(&self, request: TransportRequest) -> Result<TransportResponse> {
+        Err(crate::Error::TransportError {
+            message: format!("DELETE {} isn't supported by the transport", request.url),
+            kind: None,
+        })
+    }
+}
+
+/// A [`Transport`] that can be shared between clients and compared by identity, so it can be part of the
+/// [`ClientBuilder`].
+///
+/// [`ClientBuilder`]: crate::ClientBuilder
+#[derive(Clone)]
+pub struct SharedTransport(Arc<dyn Transport>);
+
+impl SharedTransport {
+    /// Wraps a transport.
+    pub fn new(transport: impl Transport + 'static) -> Self {
+        Self(Arc::new(transport))
+    }
+}
+
+impl<T: Transport + 'static> From<T> for SharedTransport {
+    fn from(transport: T) -> Self {
+        Self::new(transport)
+    }
+}
+
+impl Deref for SharedTransport {
+    type Target = dyn Transport;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref()
+    }
+}
+
+impl Debug for SharedTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl PartialEq for SharedTransport {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for SharedTransport {}
+
+impl Hash for SharedTransport {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (Arc::as_ptr(&self.0) as *const () as usize).hash(state);
+    }
+}
+
+/// Which requests are sent through a proxy.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ProxyScope {
+    /// HTTP and HTTPS requests
+    #[default]
+    All,
+    /// Only HTTP requests
+    Http,
+    /// Only HTTPS requests
+    Https,
+}
+
+/// An HTTP, HTTPS or SOCKS5 proxy, selected by the scheme of its url (`http`, `https`, `socks5` or `socks5h`).
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[must_use]
+pub struct ProxyOptions {
+    pub(crate) url: Url,
+    #[serde(default)]
+    pub(crate) scope: ProxyScope,
+    #[serde(default)]
+    pub(crate) username: Option<String>,
+    #[serde(default)]
+    pub(crate) password: Option<String>,
+}
+
+impl ProxyOptions {
+    /// Creates a proxy for all requests.
+    pub fn new(url: &str) -> Result<Self> {
+        Ok(Self {
+            url: Url::parse(url)?,
+            scope: ProxyScope::All,
+            username: None,
+            password: None,
+        })
+    }
+
+    /// Sets which requests are sent through the proxy.
+    pub fn scope(mut self, scope: ProxyScope) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    /// Sets the credentials for the proxy.
+    pub fn basic_auth(mut self, username: &str, password: &str) -> Self {
+        self.username.replace(username.to_string());
+        self.password.replace(password.to_string());
+        self
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    fn to_reqwest(&self) -> Result<reqwest::Proxy> {
+        let url = self.url.as_str();
+        let proxy = match self.scope {
+            ProxyScope::All => reqwest::Proxy::all(url)?,
+            ProxyScope::Http => reqwest::Proxy::http(url)?,
+            ProxyScope::Https => reqwest::Proxy::https(url)?,
+        };
+        Ok(match &self.username {
+            Some(username) => proxy.basic_auth(username, self.password.as_deref().unwrap_or_default()),
+            None => proxy,
+        })
+    }
+}
+
+/// A PEM encoded client certificate and its private key, for nodes that require mutual TLS.
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ClientCertificate {
+    pub(crate) certificate: String,
+    pub(crate) key: String,
+}
+
+impl ClientCertificate {
+    /// Creates a client certificate from the PEM encoded certificate chain and private key.
+    pub fn new(certificate: impl Into<String>, key: impl Into<String>) -> Self {
+        Self {
+            certificate: certificate.into(),
+            key: key.into(),
+        }
+    }
+}
+
+impl Debug for ClientCertificate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClientCertificate")
+            .field("certificate", &self.certificate)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Proxy and TLS options of the default [`ReqwestTransport`]. They aren't supported in wasm, where the browser takes
+/// care of them.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[must_use]
+pub struct TransportOptions {
+    #[serde(default)]
+    pub(crate) proxies: Vec<ProxyOptions>,
+    #[serde(rename = "rootCertificates", default)]
+    pub(crate) root_certificates: Vec<String>,
+    #[serde(rename = "clientCertificate", default)]
+    pub(crate) client_certificate: Option<ClientCertificate>,
+    #[serde(rename = "dangerAcceptInvalidCerts", default)]
+    pub(crate) danger_accept_invalid_certs: bool,
+}
+
+impl TransportOptions {
+    /// Creates options without proxies and with the default root certificates.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a proxy, the first one whose scope matches a request is used.
+    pub fn proxy(mut self, proxy: ProxyOptions) -> Self {
+        self.proxies.push(proxy);
+        self
+    }
+
+    /// Adds a PEM encoded certificate that is trusted in addition to the default root certificates, e.g. of an
+    /// internal certificate authority.
+    pub fn root_certificate(mut self, pem: impl Into<String>) -> Self {
+        self.root_certificates.push(pem.into());
+        self
+    }
+
+    /// Sets the certificate the client authenticates itself with to the nodes.
+    pub fn client_certificate(mut self, client_certificate: ClientCertificate) -> Self {
+        self.client_certificate.replace(client_certificate);
+        self
+    }
+
+    /// Accepts any certificate of the nodes, including expired and self-signed ones. Only use this for local test
+    /// nodes, the connection isn't secure anymore.
+    pub fn danger_accept_invalid_certs(mut self, danger_accept_invalid_certs: bool) -> Self {
+        self.danger_accept_invalid_certs = danger_accept_invalid_certs;
+        self
+    }
+
+    #[cfg(any(target_family = "wasm", not(feature = "tls")))]
+    fn uses_tls(&self) -> bool {
+        !self.root_certificates.is_empty() || self.client_certificate.is_some() || self.danger_accept_invalid_certs
+    }
+}
+
+/// The default transport, using `reqwest`.
+#[derive(Clone, Debug, Default)]
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+}
+
+impl ReqwestTransport {
+    /// Creates the transport with a default `reqwest` client.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Creates the transport with a configured `reqwest` client.
+    pub fn with_client(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+
+    /// Creates the transport with the proxies and TLS options. Invalid certificates make it fail.
+    #[cfg(not(target_family = "wasm"))]
+    pub fn with_options(options: &TransportOptions) -> Result<Self> {
+        let mut builder = reqwest::Client::builder();
+        for proxy in &options.proxies {
+            builder = builder.proxy(proxy.to_reqwest()?);
+        }
+        #[cfg(feature = "tls")]
+        {
+            for pem in &options.root_certificates {
+                builder = builder.add_root_certificate(reqwest::Certificate::from_pem(pem.as_bytes())?);
+            }
+            if let Some(client_certificate) = &options.client_certificate {
+                // reqwest expects the key and the certificates in one PEM buffer
+                let pem = format!("{}\n{}", client_certificate.key, client_certificate.certificate);
+                builder = builder.identity(reqwest::Identity::from_pem(pem.as_bytes())?);
+            }
+            builder = builder.danger_accept_invalid_certs(options.danger_accept_invalid_certs);
+        }
+        #[cfg(not(feature = "tls"))]
+        if options.uses_tls() {
+            return Err(crate::Error::InvalidTransportOptions(
+                "TLS options require the `tls` feature".to_string(),
+            ));
+        }
+        Ok(Self::with_client(builder.build()?))
+    }
+
+    /// Creates the default transport, other options aren't supported in wasm.
+    #[cfg(target_family = "wasm")]
+    pub fn with_options(options: &TransportOptions) -> Result<Self> {
+        if !options.proxies.is_empty() || options.uses_tls() {
+            return Err(crate::Error::InvalidTransportOptions(
+                "proxies and TLS options aren't supported in wasm".to_string(),
+            ));
+        }
+        Ok(Self::new())
+    }
+
+    async fn send(
+        &self,
+        request: TransportRequest,
+        request_builder: reqwest::RequestBuilder,
+    ) -> Result<TransportResponse> {
+        let mut request_builder = match request.credentials {
+            Some(credentials) => credentials.apply(request_builder),
+            None => request_builder,
+        };
+        // Timeouts aren't supported by reqwest in wasm
+        #[cfg(not(target_family = "wasm"))]
+        {
+            request_builder = request_builder.timeout(request.timeout);
+        }
+        let response = request_builder.send().await?;
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.as_str().to_lowercase(), value.to_str().ok()?.to_string())))
+            .collect();
+        let body = response.bytes().await?.to_vec();
+        Ok(TransportResponse { status, headers, body })
+    }
+}
+
+#[cfg_attr(target_family = "wasm", async_trait(?Send))]
+#[cfg_attr(not(target_family = "wasm"), async_trait)]
+impl Transport for ReqwestTransport {
+    async fn get(&self, request: TransportRequest) -> Result<TransportResponse> {
+        let request_builder = self.client.get(request.url.clone());
+        self.send(request, request_builder).await
+    }
+
+    async fn get_bytes(&self, request: TransportRequest) -> Result<TransportResponse> {
+        let request_builder = self
+            .client
+            .get(request.url.clone())
+            .header("accept", "application/vnd.iota.serializer-v1");
+        self.send(request, request_builder).await
+    }
+
+    async fn post_json(&self, request: TransportRequest, json: &Value) -> Result<TransportResponse> {
+        let request_builder = self.client.post(request.url.clone()).json(json);
+        self.send(request, request_builder).await
+    }
+
+    async fn post_bytes(&self, request: TransportRequest, body: &[u8]) -> Result<TransportResponse> {
+        let request_builder = self
+            .client
+            .post(request.url.clone())
+            .header("Content-Type", "application/vnd.iota.serializer-v1")
+            .body(body.to_vec());
+        self.send(request, request_builder).await
+    }
+
+    async fn delete(&self, request: TransportRequest) -> Result<TransportResponse> {
+        let request_builder = self.client.delete(request.url.clone());
+        self.send(request, request_builder).await
+    }
+}
@@ -0,0 +1,779 @@
+// Copyright 2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(feature = "mock-node")]
+
+use std::time::Duration;
+
+use bee_api_types::dtos::LedgerInclusionStateDto;
+use bee_block::{
+    address::Address,
+    payload::{milestone::MilestoneId, Payload},
+    protocol::ProtocolParameters,
+    BlockId,
+};
+use futures::StreamExt;
+use iota_client::{
+    api::{
+        verify_protocol_parameters, GetAddressesBuilderOptions, InclusionProof, LedgerCheckpoint, LedgerEvent,
+        MerkleAuditPath, MilestoneKeyRange, MilestoneStream, MilestoneVerificationError, MilestoneVerifier, PastCone,
+        PastConeLimits,
+    },
+    mock_node::MockNode,
+    node_api::indexer::query_parameters::QueryParameter,
+    node_manager::node::NodeAuth,
+    secret::{mnemonic::MnemonicSecretManager, SecretManager},
+    Client, Error,
+};
+
+// THIS SEED SERVES FOR TESTING PURPOSES! DON'T USE THIS SEED IN PRODUCTION!
+const DEFAULT_DEVELOPMENT_SEED: &str = "0x256a818b2aac458941f7274985a410e57fb750f3a3a67969ece5bd9ae7eef5b2";
+
+fn setup_secret_manager() -> SecretManager {
+    SecretManager::Mnemonic(MnemonicSecretManager::try_from_hex_seed(DEFAULT_DEVELOPMENT_SEED).unwrap())
+}
+
+fn setup_client(node: &MockNode) -> Client {
+    Client::builder().with_node(node.url()).unwrap().finish().unwrap()
+}
+
+async fn addresses(client: &Client, secret_manager: &SecretManager, range: std::ops::Range<u32>) -> Vec<String> {
+    client
+        .get_addresses(secret_manager)
+        .with_range(range)
+        .finish()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn tagged_data_block() {
+    let node = MockNode::builder().finish().unwrap();
+    let client = setup_client(&node);
+
+    let block = client
+        .block()
+        .with_tag(b"Hello".to_vec())
+        .with_data(b"Tangle".to_vec())
+        .finish()
+        .await
+        .unwrap();
+
+    assert_eq!(client.get_block(&block.id()).await.unwrap(), block);
+    let metadata = client.get_block_metadata(&block.id()).await.unwrap();
+    assert_eq!(
+        metadata.ledger_inclusion_state,
+        Some(LedgerInclusionStateDto::NoTransaction)
+    );
+    assert!(node.is_referenced(&block.id()).unwrap());
+}
+
+#[tokio::test]
+async fn transaction_until_included() {
+    let node = MockNode::builder().with_auto_confirm(false).finish().unwrap();
+    let client = setup_client(&node);
+    let secret_manager = setup_secret_manager();
+    let addresses = addresses(&client, &secret_manager, 0..2).await;
+
+    node.fund(&Address::try_from_bech32(&addresses[0]).unwrap().1, 10_000_000)
+        .unwrap();
+
+    let block = client
+        .block()
+        .with_secret_manager(&secret_manager)
+        .with_output(&addresses[1], 1_000_000)
+        .unwrap()
+        .finish()
+        .await
+        .unwrap();
+    assert!(matches!(block.payload(), Some(Payload::Transaction(_))));
+    assert!(!node.is_referenced(&block.id()).unwrap());
+
+    node.confirm().unwrap();
+    let included = client.retry_until_included(&block.id(), Some(0), None).await.unwrap();
+    assert_eq!(included[0].0, block.id());
+
+    let balance = client.get_address().balance(&addresses[1]).await.unwrap();
+    assert_eq!(balance.balance, 1_000_000);
+}
+
+#[tokio::test]
+async fn consolidate_funds() {
+    let node = MockNode::builder().finish().unwrap();
+    let client = setup_client(&node);
+    let secret_manager = setup_secret_manager();
+    let addresses = addresses(&client, &secret_manager, 0..3).await;
+
+    for address in &addresses {
+        node.fund(&Address::try_from_bech32(address).unwrap().1, 1_000_000)
+            .unwrap();
+    }
+
+    let consolidation_address = client
+        .consolidate_funds(
+            &secret_manager,
+            GetAddressesBuilderOptions {
+                range: Some(0..3),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(consolidation_address, addresses[0]);
+
+    let balance = client.get_address().balance(&addresses[0]).await.unwrap();
+    assert_eq!(balance.balance, 3_000_000);
+    for address in &addresses[1..] {
+        assert_eq!(client.get_address().balance(address).await.unwrap().balance, 0);
+    }
+}
+
+#[tokio::test]
+async fn offline_transaction() {
+    let node = MockNode::builder().finish().unwrap();
+    let client = setup_client(&node);
+    let secret_manager = setup_secret_manager();
+    let addresses = addresses(&client, &secret_manager, 0..2).await;
+    let output_id = node
+        .fund(&Address::try_from_bech32(&addresses[0]).unwrap().1, 2_000_000)
+        .unwrap();
+
+    // The protocol parameters and the input are exported with network access
+    let protocol = client.get_info().await.unwrap().node_info.protocol;
+    let input = client.get_output(&output_id).await.unwrap();
+
+    let offline_client = Client::builder()
+        .with_offline_mode()
+        .with_protocol_parameters(&protocol)
+        .unwrap()
+        .finish()
+        .unwrap();
+    assert_eq!(
+        offline_client.get_network_id().await.unwrap(),
+        client.get_network_id().await.unwrap()
+    );
+    assert_eq!(
+        offline_client.get_token_supply().await.unwrap(),
+        protocol.token_supply.parse::<u64>().unwrap()
+    );
+    assert!(matches!(offline_client.get_tips().await, Err(Error::OfflineMode(_))));
+    assert!(matches!(
+        offline_client.get_time_checked().await,
+        Err(Error::OfflineMode(_))
+    ));
+    // Without protocol parameters there's nothing to request them from
+    let unconfigured_client = Client::builder().with_offline_mode().finish().unwrap();
+    assert!(matches!(
+        unconfigured_client.get_bech32_hrp().await,
+        Err(Error::OfflineMode(_))
+    ));
+    assert!(matches!(
+        unconfigured_client.get_token_supply().await,
+        Err(Error::OfflineMode(_))
+    ));
+    assert!(matches!(
+        unconfigured_client.get_protocol_parameters().await,
+        Err(Error::OfflineMode(_))
+    ));
+
+    let builder = offline_client
+        .block()
+        .with_secret_manager(&secret_manager)
+        .with_input_data(vec![input])
+        .unwrap()
+        .with_output(&addresses[1], 1_000_000)
+        .unwrap();
+    let prepared_transaction = builder.prepare_transaction().await.unwrap();
+    let payload = builder.sign_transaction(prepared_transaction.clone()).await.unwrap();
+    // A bare offline client signs without protocol parameters to check the transaction against
+    assert_eq!(
+        unconfigured_client
+            .block()
+            .with_secret_manager(&secret_manager)
+            .sign_transaction(prepared_transaction)
+            .await
+            .unwrap(),
+        payload
+    );
+    assert!(matches!(
+        offline_client.block().finish_block(Some(payload.clone())).await,
+        Err(Error::OfflineMode(_))
+    ));
+
+    let block = client.block().finish_block(Some(payload)).await.unwrap();
+    assert!(node.is_referenced(&block.id()).unwrap());
+    assert_eq!(
+        client.get_address().balance(&addresses[1]).await.unwrap().balance,
+        1_000_000
+    );
+}
+
+#[tokio::test]
+async fn protocol_parameters() {
+    let node = MockNode::builder().with_token_supply(3_000_000).finish().unwrap();
+    let client = setup_client(&node);
+    let secret_manager = setup_secret_manager();
+    let addresses = addresses(&client, &secret_manager, 0..2).await;
+    let output_id = node
+        .fund(&Address::try_from_bech32(&addresses[0]).unwrap().1, 2_000_000)
+        .unwrap();
+
+    let protocol_parameters = client.get_protocol_parameters().await.unwrap();
+    assert_eq!(protocol_parameters.network_name(), "mock");
+    assert_eq!(protocol_parameters.token_supply(), 3_000_000);
+    assert_eq!(protocol_parameters.bech32_hrp(), client.get_bech32_hrp().await.unwrap());
+
+    // Serialized for a client without network access
+    let json = serde_json::to_string(&protocol_parameters).unwrap();
+    let offline_client = Client::builder()
+        .with_offline_mode()
+        .with_typed_protocol_parameters(serde_json::from_str(&json).unwrap())
+        .unwrap()
+        .finish()
+        .unwrap();
+    assert_eq!(
+        offline_client.get_protocol_parameters().await.unwrap(),
+        protocol_parameters
+    );
+    assert_eq!(
+        offline_client.get_network_id().await.unwrap(),
+        client.get_network_id().await.unwrap()
+    );
+    // Options serialized before the protocol version was part of the network info
+    let mut options = serde_json::to_value(client.get_network_info().await.unwrap()).unwrap();
+    options.as_object_mut().unwrap().remove("protocolVersion");
+    options["offline"] = true.into();
+    let options_client = Client::builder()
+        .from_json(&options.to_string())
+        .unwrap()
+        .finish()
+        .unwrap();
+    assert_eq!(
+        options_client.get_protocol_parameters().await.unwrap(),
+        protocol_parameters
+    );
+
+    assert!(matches!(
+        client
+            .block()
+            .with_secret_manager(&secret_manager)
+            .with_output(&addresses[1], 4_000_000)
+            .unwrap()
+            .prepare_transaction()
+            .await,
+        Err(Error::TokenSupplyExceeded {
+            amount: 4_000_000,
+            token_supply: 3_000_000
+        })
+    ));
+
+    // A transaction prepared for another network isn't signed
+    let input = client.get_output(&output_id).await.unwrap();
+    let prepared_transaction = client
+        .block()
+        .with_secret_manager(&secret_manager)
+        .with_input_data(vec![input])
+        .unwrap()
+        .with_output(&addresses[1], 1_000_000)
+        .unwrap()
+        .prepare_transaction()
+        .await
+        .unwrap();
+    options_client
+        .block()
+        .with_secret_manager(&secret_manager)
+        .sign_transaction(prepared_transaction.clone())
+        .await
+        .unwrap();
+    let other_network = ProtocolParameters::new(
+        protocol_parameters.version(),
+        "other".to_string(),
+        protocol_parameters.bech32_hrp().to_string(),
+        protocol_parameters.min_pow_score(),
+        protocol_parameters.below_max_depth(),
+        protocol_parameters.rent_structure().clone(),
+        protocol_parameters.token_supply(),
+    )
+    .unwrap();
+    assert!(matches!(
+        verify_protocol_parameters(&prepared_transaction.essence, &other_network),
+        Err(Error::NetworkIdMismatch { .. })
+    ));
+    let other_client = Client::builder()
+        .with_offline_mode()
+        .with_typed_protocol_parameters(other_network)
+        .unwrap()
+        .finish()
+        .unwrap();
+    assert!(matches!(
+        other_client
+            .block()
+            .with_secret_manager(&secret_manager)
+            .sign_transaction(prepared_transaction.clone())
+            .await,
+        Err(Error::NetworkIdMismatch { .. })
+    ));
+    verify_protocol_parameters(&prepared_transaction.essence, &protocol_parameters).unwrap();
+}
+
+async fn milestone_indexes(stream: &mut MilestoneStream) -> Vec<Result<u32, u32>> {
+    let mut indexes = Vec::new();
+    while let Some(item) = stream.next().await {
+        indexes.push(match item {
+            Ok((milestone, utxo_changes)) => {
+                assert_eq!(*milestone.essence().index(), utxo_changes.index);
+                Ok(utxo_changes.index)
+            }
+            Err(Error::MilestoneGap(index)) => Err(index),
+            Err(e) => panic!("unexpected error: {e}"),
+        });
+    }
+    indexes
+}
+
+#[tokio::test]
+async fn milestone_stream() {
+    let node = MockNode::builder().finish().unwrap();
+    let permanode = MockNode::builder().finish().unwrap();
+    for _ in 0..4 {
+        node.confirm().unwrap();
+        permanode.confirm().unwrap();
+    }
+    // Milestone 1 is gone everywhere, 2 and 3 are only on the permanode
+    node.prune_milestones(3).unwrap();
+    permanode.prune_milestones(1).unwrap();
+    let client = Client::builder()
+        .with_node(node.url())
+        .unwrap()
+        .with_permanode(permanode.url(), None)
+        .unwrap()
+        .with_node_sync_disabled()
+        .finish()
+        .unwrap();
+
+    let mut stream = client.milestones(1..=5).with_prefetch(2);
+    assert_eq!(
+        milestone_indexes(&mut stream).await,
+        [Err(1), Ok(2), Ok(3), Ok(4), Ok(5)]
+    );
+    assert_eq!(stream.next_index(), 6);
+    let (milestone, _) = client.milestones(2..3).next().await.unwrap().unwrap();
+    assert_eq!(Some(milestone), permanode.milestone(2).unwrap());
+    // The permanode is asked first for milestones it has
+    let (milestone, _) = client.milestones(4..5).next().await.unwrap().unwrap();
+    assert_eq!(Some(milestone), permanode.milestone(4).unwrap());
+
+    // Without follow mode the stream ends at the latest confirmed milestone
+    assert_eq!(milestone_indexes(&mut client.milestones(4..)).await, [Ok(4), Ok(5)]);
+    assert!(client.milestones(6..).next().await.is_none());
+
+    let mut stream = client.milestones(6..=7).with_follow_mode(Duration::from_millis(20));
+    assert!(tokio::time::timeout(Duration::from_millis(100), stream.next())
+        .await
+        .is_err());
+    node.confirm().unwrap();
+    node.confirm().unwrap();
+    assert_eq!(milestone_indexes(&mut stream).await, [Ok(6), Ok(7)]);
+
+    // A milestone above the pruning index that isn't found is requested again instead of being skipped
+    let mut stream = client
+        .milestones(6..=7)
+        .with_prefetch(1)
+        .with_follow_mode(Duration::from_millis(20));
+    assert_eq!(stream.next().await.unwrap().unwrap().1.index, 6);
+    node.fail_next_requests(1, 404, None).unwrap();
+    assert_eq!(milestone_indexes(&mut stream).await, [Ok(7)]);
+}
+
+#[tokio::test]
+async fn ledger_follower() {
+    let node = MockNode::builder().finish().unwrap();
+    let client = setup_client(&node);
+    let secret_manager = setup_secret_manager();
+    let addresses = addresses(&client, &secret_manager, 0..2).await;
+    let sender = Address::try_from_bech32(&addresses[0]).unwrap().1;
+    let receiver = Address::try_from_bech32(&addresses[1]).unwrap().1;
+
+    let mut follower = client
+        .ledger_follower()
+        .with_address(sender)
+        .with_poll_interval(Duration::from_millis(20))
+        .finish()
+        .await
+        .unwrap();
+    assert!(follower.try_next_milestone().await.unwrap().is_none());
+
+    let funded_output_id = node.fund(&sender, 2_000_000).unwrap();
+    let events = follower.next_milestone().await.unwrap();
+    assert_eq!(events.checkpoint.milestone_index, events.milestone_index + 1);
+    match &events.events[..] {
+        [LedgerEvent::OutputCreated(created)] => {
+            assert_eq!(created.output_id, funded_output_id);
+            assert_eq!(created.addresses, [sender]);
+        }
+        events => panic!("unexpected events: {events:?}"),
+    }
+    // Outputs of other addresses are ignored
+    node.fund(&receiver, 1_000_000).unwrap();
+    assert!(follower.next_milestone().await.unwrap().events.is_empty());
+
+    // A restarted follower continues after the persisted checkpoint
+    let checkpoint: LedgerCheckpoint =
+        serde_json::from_str(&serde_json::to_string(&follower.checkpoint()).unwrap()).unwrap();
+    follower.watch_address(receiver);
+    client
+        .block()
+        .with_secret_manager(&secret_manager)
+        .with_input(funded_output_id.into())
+        .unwrap()
+        .with_output(&addresses[1], 1_000_000)
+        .unwrap()
+        .finish()
+        .await
+        .unwrap();
+    let mut restarted = client
+        .ledger_follower()
+        .with_addresses([sender, receiver])
+        .with_checkpoint(checkpoint)
+        .finish()
+        .await
+        .unwrap();
+
+    let events = follower.next_milestone().await.unwrap();
+    assert_eq!(restarted.next_milestone().await.unwrap(), events);
+    let spent = events
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            LedgerEvent::OutputSpent(spent) => Some(spent.output_id),
+            LedgerEvent::OutputCreated(_) => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(spent, [funded_output_id]);
+    // The output to the receiver and the remainder
+    assert_eq!(events.events.len(), 3);
+    assert!(matches!(events.events[2], LedgerEvent::OutputSpent(_)));
+    assert!(restarted.try_next_milestone().await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn past_cone() {
+    let node = MockNode::builder().with_auto_confirm(false).finish().unwrap();
+    let client = setup_client(&node);
+    let mut block_ids = Vec::new();
+    for _ in 0..3 {
+        block_ids.push(client.block().with_tag(b"cone".to_vec()).finish().await.unwrap().id());
+    }
+    let root = block_ids[2];
+
+    // Unreferenced blocks are traversed until the blocks of the last milestone
+    let cone = client.past_cone(&root, PastConeLimits::new()).await.unwrap();
+    assert_eq!(cone.root, root);
+    assert_eq!(cone.blocks[0].block_id, root);
+    assert!(!cone.truncated && cone.missing.is_empty());
+    for block_id in &block_ids {
+        assert!(cone.block(block_id).unwrap().expanded);
+    }
+    assert!(cone
+        .blocks
+        .iter()
+        .all(|block| block.expanded == block.referenced_by_milestone_index.is_none()));
+
+    let cone = client
+        .past_cone(&root, PastConeLimits::new().max_depth(1))
+        .await
+        .unwrap();
+    assert!(cone.blocks.iter().all(|block| block.depth <= 1));
+    assert!(cone.blocks.iter().all(|block| block.expanded == (block.depth == 0)));
+    let cone = client
+        .past_cone(&root, PastConeLimits::new().max_blocks(2))
+        .await
+        .unwrap();
+    assert_eq!(cone.blocks.len(), 2);
+    assert!(cone.truncated);
+
+    // Once referenced, the blocks of the same milestone are traversed
+    let index = node.confirm().unwrap();
+    let cone = client.past_cone(&root, PastConeLimits::new()).await.unwrap();
+    for block_id in &block_ids {
+        let block = cone.block(block_id).unwrap();
+        assert_eq!(block.referenced_by_milestone_index, Some(index));
+        assert!(block.expanded);
+    }
+    assert!(cone.blocks.iter().any(|block| !block.expanded));
+
+    // Without the boundary the traversal reaches the genesis, whose parent doesn't exist
+    let cone = client
+        .past_cone(&root, PastConeLimits::new().stop_at_milestone_boundary(false))
+        .await
+        .unwrap();
+    assert_eq!(cone.missing, [BlockId::null()]);
+    assert!(cone.blocks.iter().all(|block| block.expanded));
+
+    assert_eq!(
+        serde_json::from_str::<PastCone>(&cone.to_json().unwrap()).unwrap(),
+        cone
+    );
+    let dot = cone.to_dot();
+    assert!(dot.starts_with("digraph"));
+    assert!(dot.contains(&format!("\"{root}\" [label=")));
+    for block in &cone.blocks {
+        for parent in &block.parents {
+            assert!(dot.contains(&format!("\"{}\" -> \"{parent}\";", block.block_id)));
+        }
+    }
+}
+
+#[tokio::test]
+async fn milestone_verification() {
+    let node = MockNode::builder().with_auto_confirm(false).finish().unwrap();
+    let client = setup_client(&node);
+    let secret_manager = setup_secret_manager();
+    let addresses = addresses(&client, &secret_manager, 0..1).await;
+    node.fund(&Address::try_from_bech32(&addresses[0]).unwrap().1, 2_000_000)
+        .unwrap();
+    let tagged_data = client.block().with_tag(b"tag".to_vec()).finish().await.unwrap();
+    let transaction = client
+        .block()
+        .with_secret_manager(&secret_manager)
+        .with_output(&addresses[0], 1_000_000)
+        .unwrap()
+        .finish()
+        .await
+        .unwrap();
+    let index = node.confirm().unwrap();
+    let key_ranges = vec![MilestoneKeyRange::new(node.milestone_public_key().unwrap(), 1, 0)];
+
+    let mut verifier = MilestoneVerifier::new(key_ranges.clone(), 1).unwrap();
+    for index in 1..=index {
+        client
+            .get_verified_milestone_by_index(&mut verifier, index)
+            .await
+            .unwrap();
+    }
+    let milestone = node.milestone(index).unwrap().unwrap();
+    assert_eq!(verifier.latest_milestone(), Some((index, milestone.id())));
+    let mut verifier = MilestoneVerifier::new(key_ranges.clone(), 1).unwrap();
+    assert_eq!(
+        client
+            .get_verified_milestone_by_id(&mut verifier, &milestone.id())
+            .await
+            .unwrap(),
+        milestone
+    );
+
+    // The chain has to continue with the next milestone
+    let mut verifier = MilestoneVerifier::new(key_ranges.clone(), 1).unwrap();
+    client.get_verified_milestone_by_index(&mut verifier, 1).await.unwrap();
+    assert!(matches!(
+        client.get_verified_milestone_by_index(&mut verifier, 3).await,
+        Err(Error::MilestoneVerification(
+            MilestoneVerificationError::IndexMismatch { expected: 2, found: 3 }
+        ))
+    ));
+    // A chain that started at another milestone 1
+    let mut verifier = MilestoneVerifier::new(key_ranges.clone(), 1)
+        .unwrap()
+        .with_trusted_milestone(1, MilestoneId::new([1; 32]));
+    assert!(matches!(
+        client.get_verified_milestone_by_index(&mut verifier, 2).await,
+        Err(Error::MilestoneVerification(
+            MilestoneVerificationError::PreviousMilestoneMismatch { index: 2, .. }
+        ))
+    ));
+    assert_eq!(verifier.latest_milestone(), Some((1, MilestoneId::new([1; 32]))));
+
+    // Signatures of keys that don't apply to the index, or too few of them
+    let verifier = MilestoneVerifier::new(
+        vec![MilestoneKeyRange::new("0x".to_string() + &"11".repeat(32), 1, 0)],
+        1,
+    )
+    .unwrap();
+    assert!(matches!(
+        verifier.verify_signatures(&milestone),
+        Err(Error::MilestoneVerification(
+            MilestoneVerificationError::UnapplicablePublicKey { .. }
+        ))
+    ));
+    let verifier =
+        MilestoneVerifier::new(vec![MilestoneKeyRange::new(key_ranges[0].public_key.clone(), 1, 1)], 1).unwrap();
+    assert!(matches!(
+        verifier.verify_signatures(&milestone),
+        Err(Error::MilestoneVerification(
+            MilestoneVerificationError::TooFewApplicableKeys { applicable: 0, .. }
+        ))
+    ));
+    assert!(matches!(
+        MilestoneVerifier::new(key_ranges, 0),
+        Err(Error::MilestoneVerification(
+            MilestoneVerificationError::InvalidThreshold
+        ))
+    ));
+
+    // Merkle roots
+    let referenced = client.get_milestone_referenced_blocks(&milestone).await.unwrap();
+    let referenced_block_ids = referenced
+        .iter()
+        .map(|metadata| metadata.block_id.parse::<BlockId>().unwrap())
+        .collect::<Vec<_>>();
+    assert!(referenced_block_ids.contains(&tagged_data.id()));
+    assert!(referenced_block_ids.contains(&transaction.id()));
+    client.verify_milestone_merkle_roots(&milestone).await.unwrap();
+    MilestoneVerifier::verify_merkle_roots(&milestone, &referenced_block_ids, &[transaction.id()]).unwrap();
+    assert!(matches!(
+        MilestoneVerifier::verify_merkle_roots(&milestone, &referenced_block_ids[1..], &[transaction.id()]),
+        Err(Error::MilestoneVerification(
+            MilestoneVerificationError::InclusionMerkleRootMismatch { .. }
+        ))
+    ));
+    assert!(matches!(
+        MilestoneVerifier::verify_merkle_roots(&milestone, &referenced_block_ids, &[]),
+        Err(Error::MilestoneVerification(
+            MilestoneVerificationError::AppliedMerkleRootMismatch { .. }
+        ))
+    ));
+}
+
+#[tokio::test]
+async fn inclusion_proof() {
+    let node = MockNode::builder()
+        .with_auto_confirm(false)
+        .with_required_header("authorization", "Bearer jwt")
+        .finish()
+        .unwrap();
+    let client = Client::builder()
+        .with_node_auth(
+            node.url(),
+            Some(NodeAuth {
+                jwt: Some("jwt".to_string()),
+                basic_auth_name_pwd: None,
+            }),
+        )
+        .unwrap()
+        .finish()
+        .unwrap();
+    let blocks = futures::future::try_join_all((0..3u8).map(|i| client.block().with_tag(vec![i]).finish()))
+        .await
+        .unwrap();
+    let block_id = blocks[1].id();
+    assert!(matches!(
+        client.build_inclusion_proof(&block_id).await,
+        Err(Error::InclusionProofError(_))
+    ));
+    node.confirm().unwrap();
+
+    let proof = client.create_inclusion_proof(&block_id).await.unwrap();
+    assert_eq!(proof, client.build_inclusion_proof(&block_id).await.unwrap());
+    assert_eq!(proof.block, blocks[1]);
+    assert!(proof.audit_path.contains(&block_id));
+    let verifier = MilestoneVerifier::new(
+        vec![MilestoneKeyRange::new(node.milestone_public_key().unwrap(), 1, 0)],
+        1,
+    )
+    .unwrap();
+    proof.verify(&verifier).unwrap();
+    assert!(client.validate_inclusion_proof(&proof).await.unwrap());
+
+    // Both encodings carry the whole proof
+    assert_eq!(InclusionProof::from_json(&proof.to_json().unwrap()).unwrap(), proof);
+    let bytes = proof.to_bytes();
+    assert_eq!(InclusionProof::from_bytes(&bytes).unwrap(), proof);
+    assert!(matches!(
+        InclusionProof::from_bytes(&bytes[..bytes.len() - 1]),
+        Err(Error::InclusionProofError(_))
+    ));
+
+    // Another block doesn't match the audit path
+    let mut tampered = proof.clone();
+    tampered.block = blocks[0].clone();
+    assert!(matches!(
+        tampered.verify(&verifier),
+        Err(Error::MilestoneVerification(
+            MilestoneVerificationError::BlockNotInAuditPath { .. }
+        ))
+    ));
+    assert!(!client.validate_inclusion_proof(&tampered).await.unwrap());
+    // Neither does a path with another sibling hash
+    let mut tampered = proof.clone();
+    tampered.audit_path = MerkleAuditPath::Node(
+        Box::new(MerkleAuditPath::Value(block_id)),
+        Box::new(MerkleAuditPath::Hash([0; 32])),
+    );
+    assert!(matches!(
+        tampered.verify(&verifier),
+        Err(Error::MilestoneVerification(
+            MilestoneVerificationError::InclusionMerkleRootMismatch { .. }
+        ))
+    ));
+    // A milestone of another key isn't trusted
+    let verifier = MilestoneVerifier::new(
+        vec![MilestoneKeyRange::new("0x".to_string() + &"11".repeat(32), 1, 0)],
+        1,
+    )
+    .unwrap();
+    assert!(matches!(
+        proof.verify(&verifier),
+        Err(Error::MilestoneVerification(
+            MilestoneVerificationError::UnapplicablePublicKey { .. }
+        ))
+    ));
+}
+
+#[tokio::test]
+async fn output_ids_stream() {
+    let node = MockNode::builder().finish().unwrap();
+    let client = setup_client(&node);
+    let secret_manager = setup_secret_manager();
+    let address = addresses(&client, &secret_manager, 0..1).await.remove(0);
+    for amount in 1..=5 {
+        node.fund(&Address::try_from_bech32(&address).unwrap().1, amount * 1_000_000)
+            .unwrap();
+    }
+    let query_parameters = vec![QueryParameter::Address(address)];
+    let output_ids = client.basic_output_ids(query_parameters.clone()).await.unwrap();
+
+    let mut stream = client
+        .basic_output_ids_stream(query_parameters.clone())
+        .unwrap()
+        .with_page_size(2);
+    assert!(stream.cursor().is_none());
+    let first_page = stream.next().await.unwrap().unwrap();
+    assert_eq!(first_page.output_ids, output_ids[..2]);
+    assert!(first_page.outputs.is_none());
+    assert_eq!(stream.cursor(), first_page.cursor.as_deref());
+    let rest = stream
+        .map(|page| page.unwrap().output_ids.len())
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(rest, [2, 1]);
+
+    // A new stream resumes at the cursor of the consumed page, with its page size
+    let pages = client
+        .basic_output_ids_stream(query_parameters.clone())
+        .unwrap()
+        .with_cursor(first_page.cursor.unwrap())
+        .with_outputs()
+        .map(Result::unwrap)
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(pages.len(), 2);
+    assert!(pages[1].cursor.is_none());
+    let resumed = pages
+        .iter()
+        .flat_map(|page| page.output_ids.iter().copied())
+        .collect::<Vec<_>>();
+    assert_eq!(resumed, output_ids[2..]);
+    for page in &pages {
+        let outputs = page.outputs.as_ref().unwrap();
+        assert_eq!(outputs.len(), page.output_ids.len());
+        for (output_id, output) in page.output_ids.iter().zip(outputs) {
+            assert_eq!(output.metadata.transaction_id, output_id.transaction_id().to_string());
+            assert_eq!(output.metadata.output_index, output_id.index());
+        }
+    }
+
+    assert!(matches!(
+        client.basic_output_ids_stream(vec![QueryParameter::Governor(String::new())]),
+        Err(Error::UnsupportedQueryParameter(_))
+    ));
+}